@@ -0,0 +1,78 @@
+//! Benchmarks for the encode/decode path of [`CookieMessageStore`].
+//!
+//! Run them with `cargo bench --features cookies`.
+//! To check a change for regressions, save a baseline on `main` with `-- --save-baseline main`
+//! and then compare your branch against it with `-- --baseline main`.
+use actix_web::cookie::{Cookie, Key};
+use actix_web::dev::ResponseHead;
+use actix_web::http::{header, StatusCode};
+use actix_web::test::TestRequest;
+use actix_web_flash_messages::storage::{CookieMessageStore, FlashMessageStore};
+use actix_web_flash_messages::FlashMessage;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+
+/// Representative batches of outgoing messages: a single confirmation, a form with a few
+/// validation errors and a batch close to the default cookie size limit.
+fn batches() -> Vec<(&'static str, Vec<FlashMessage>)> {
+    let validation_errors = (0..5)
+        .map(|i| FlashMessage::error(format!("Field #{} is invalid: it must not be empty.", i)))
+        .collect();
+    let large = (0..12)
+        .map(|i| FlashMessage::warning(format!("Message #{}: {}", i, "x".repeat(64))))
+        .collect();
+    vec![
+        (
+            "single",
+            vec![FlashMessage::success("Your changes have been saved!")],
+        ),
+        ("validation_errors", validation_errors),
+        ("large", large),
+    ]
+}
+
+fn store(c: &mut Criterion) {
+    let store = CookieMessageStore::builder(Key::generate()).build();
+    let request = TestRequest::default().to_http_request();
+    let mut group = c.benchmark_group("CookieMessageStore::store");
+    for (name, messages) in batches() {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(name),
+            &messages,
+            |b, messages| {
+                b.iter(|| {
+                    let mut response_head = ResponseHead::new(StatusCode::OK);
+                    store
+                        .store(black_box(messages), request.clone(), &mut response_head)
+                        .unwrap();
+                    response_head
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+fn load(c: &mut Criterion) {
+    let store = CookieMessageStore::builder(Key::generate()).build();
+    let mut group = c.benchmark_group("CookieMessageStore::load");
+    for (name, messages) in batches() {
+        let mut response_head = ResponseHead::new(StatusCode::OK);
+        store
+            .store(
+                &messages,
+                TestRequest::default().to_http_request(),
+                &mut response_head,
+            )
+            .unwrap();
+        let set_cookie = response_head.headers().get(header::SET_COOKIE).unwrap();
+        let cookie = Cookie::parse_encoded(set_cookie.to_str().unwrap().to_owned()).unwrap();
+        let request = TestRequest::default().cookie(cookie).to_http_request();
+        group.bench_with_input(BenchmarkId::from_parameter(name), &request, |b, request| {
+            b.iter(|| store.load(black_box(request)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, store, load);
+criterion_main!(benches);
@@ -1,7 +1,14 @@
 use crate::storage::FlashMessageStore;
-use crate::Level;
+use crate::{FlashMessage, Level};
+use actix_web::dev::ServiceRequest;
+use actix_web::web::Query;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 
+// Identifiers start from 1: 0 is never assigned to a framework instance.
+static NEXT_FRAMEWORK_ID: AtomicUsize = AtomicUsize::new(1);
+
 #[derive(Clone)]
 /// `actix-web` middleware providing support for sending and receiving [`FlashMessage`]s.
 ///
@@ -26,10 +33,98 @@ use std::sync::Arc;
 /// }
 /// ```
 ///
+/// [`FlashMessagesFramework`] can also be registered on a [`Scope`](actix_web::Scope), via
+/// `web::scope("/admin").wrap(message_framework.clone())`, if you only need flash messages on a
+/// subset of your routes.
+///
+/// # Registering more than one framework
+///
+/// Each [`FlashMessagesFramework`] instance comes with its own outgoing mailbox.  
+/// If you wrap your application with more than one instance (e.g. one backed by [`CookieMessageStore`]
+/// and another one backed by [`SessionMessageStore`]), or nest them (e.g. an app-wide instance and
+/// a scope-specific one), the **innermost** one - i.e. the one registered first via `wrap` - receives
+/// all the messages sent by your handlers and serves the [`IncomingFlashMessages`] extractor.  
+/// The outer instances behave as if the request neither read nor sent any flash message: unless
+/// [`FlashMessagesFrameworkBuilder::clear_on_read`] is enabled, they will clear their storage.
+///
+/// Avoid registering more than one instance per application.  
+/// If you are combining [`CookieMessageStore`] with cookie-based sessions, make sure that the name
+/// of the flash cookie (`_flash` by default) differs from the name of the session cookie - the two
+/// cookies would otherwise overwrite each other.
+///
+/// # Streaming responses
+///
+/// Outgoing flash messages are flushed - e.g. as a `Set-Cookie` header - once your handler
+/// has returned its response, **before** the response body is streamed to the client: headers
+/// cannot be modified after that point.  
+/// As a consequence, you can't send flash messages while a streaming body is being polled:
+/// [`FlashMessage::send`] panics outside of the handler execution, while messages pushed to a
+/// [`FlashScope`] that is dropped by the body stream are silently lost.  
+/// Work out which flash messages should be sent (e.g. by validating the input of a long-running
+/// export) before returning a streaming response.
+///
+/// # Errors
+///
+/// Handlers returning an error (e.g. `Result<HttpResponse, MyError>` where `MyError` implements
+/// [`ResponseError`](actix_web::ResponseError)) are supported: `actix-web` turns the error into a
+/// response before it reaches [`FlashMessagesFramework`], so the messages sent by the handler are
+/// flushed onto the error response.
+/// The same does not hold if a middleware wrapped by [`FlashMessagesFramework`] (i.e. registered
+/// before it) fails: the request is gone by the time the error reaches us, so outgoing messages
+/// are dropped. Such middlewares should return error responses rather than errors.
+///
+/// # The `disabled` feature flag
+///
+/// Enable the `disabled` feature flag to strip flash messages from a build (e.g. an API-only
+/// profile) without changing your call sites:
+///
+/// - [`FlashMessage::send`] (and its variants) and [`FlashScope`] discard every message;
+/// - the [`IncomingFlashMessages`] extractor (and its variants) always returns no message;
+/// - [`FlashMessagesFramework`] becomes a pass-through middleware - nothing is ever loaded from
+///   or written to the configured store.
+///
+/// None of the above requires [`FlashMessagesFramework`] to be registered when the feature is
+/// enabled. Keep registering it nonetheless if you toggle the feature on and off: it is still
+/// required when flash messages are enabled.
+///
+/// ⚠️ `disabled` is **not** additive: Cargo unifies feature flags across the dependency graph, so
+/// a single crate enabling it turns flash messages off for every crate in the build. Only enable it
+/// from your binary crate - a library must never enable it, not even as a default feature.
+///
 /// [`FlashMessage`]: crate::FlashMessage
+/// [`FlashMessage::send`]: crate::FlashMessage::send
+/// [`FlashScope`]: crate::FlashScope
+/// [`IncomingFlashMessages`]: crate::IncomingFlashMessages
+/// [`CookieMessageStore`]: crate::storage::CookieMessageStore
+/// [`SessionMessageStore`]: crate::storage::SessionMessageStore
 pub struct FlashMessagesFramework {
     pub(crate) minimum_level: Level,
+    pub(crate) dynamic_minimum_level: Option<DynamicMinimumLevel>,
+    pub(crate) debug_param: Option<DebugParam>,
+    pub(crate) skip_empty: bool,
+    pub(crate) truncate_content: Option<usize>,
+    pub(crate) content_prefix: Option<Arc<str>>,
+    pub(crate) max_per_level: Arc<BTreeMap<Level, usize>>,
+    pub(crate) initial_capacity: usize,
+    pub(crate) max_messages_per_request: Option<usize>,
     pub(crate) storage_backend: Arc<dyn FlashMessageStore>,
+    pub(crate) clear_on_read: bool,
+    pub(crate) record_source_path: bool,
+    pub(crate) renderer: Option<MessageRenderer>,
+    pub(crate) on_load: Option<OnLoad>,
+    /// Distinguishes nested framework instances. Clones share the same identifier.
+    pub(crate) id: usize,
+}
+
+/// It shows the minimum level and the type of the message store - e.g. to log your configuration
+/// on startup. The store itself is not shown: it may hold secrets (e.g. a signing key).
+impl std::fmt::Debug for FlashMessagesFramework {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FlashMessagesFramework")
+            .field("minimum_level", &self.minimum_level)
+            .field("storage_backend", &self.storage_backend.type_name())
+            .finish_non_exhaustive()
+    }
 }
 
 impl FlashMessagesFramework {
@@ -44,10 +139,97 @@ impl FlashMessagesFramework {
     /// [`CookieMessageStore`]: crate::storage::CookieMessageStore
     pub fn builder<S: FlashMessageStore + 'static>(
         storage_backend: S,
+    ) -> FlashMessagesFrameworkBuilder {
+        Self::builder_arc(Arc::new(storage_backend))
+    }
+
+    /// A shortcut for [`FlashMessagesFramework::builder`] followed by
+    /// [`FlashMessagesFrameworkBuilder::minimum_level`], for the common "store + level" setup:
+    ///
+    /// ```rust
+    /// use actix_web_flash_messages::{FlashMessagesFramework, Level, storage::CookieMessageStore};
+    /// use actix_web::cookie::Key;
+    ///
+    /// let store = CookieMessageStore::builder(Key::generate()).build();
+    /// let message_framework = FlashMessagesFramework::builder_with_level(store, Level::Debug).build();
+    /// ```
+    ///
+    /// `minimum_level` accepts an `Option<Level>` as well: `None` retains the default minimum
+    /// level ([`Level::Info`]) - e.g. when the level is an optional configuration value.
+    pub fn builder_with_level<S: FlashMessageStore + 'static, L: Into<Option<Level>>>(
+        storage_backend: S,
+        minimum_level: L,
+    ) -> FlashMessagesFrameworkBuilder {
+        let mut builder = Self::builder(storage_backend);
+        builder.minimum_level = minimum_level.into();
+        builder
+    }
+
+    /// A fluent API to configure [`FlashMessagesFramework`], starting from a message store that
+    /// has already been wrapped in an [`Arc`].
+    ///
+    /// It comes in handy to share the same store instance across several frameworks, or when
+    /// your own combinators already hand out an `Arc<dyn FlashMessageStore>`:
+    ///
+    /// ```rust
+    /// use actix_web_flash_messages::{FlashMessagesFramework, storage::{CookieMessageStore, FlashMessageStore}};
+    /// use actix_web::cookie::Key;
+    /// use std::sync::Arc;
+    ///
+    /// let store: Arc<dyn FlashMessageStore> =
+    ///     Arc::new(CookieMessageStore::builder(Key::generate()).build());
+    /// let public = FlashMessagesFramework::builder_arc(store.clone()).build();
+    /// let admin = FlashMessagesFramework::builder_arc(store).build();
+    /// ```
+    ///
+    /// See [`FlashMessagesFramework::builder`] for more details.
+    pub fn builder_arc(
+        storage_backend: Arc<dyn FlashMessageStore>,
     ) -> FlashMessagesFrameworkBuilder {
         FlashMessagesFrameworkBuilder {
             minimum_level: None,
+            dynamic_minimum_level: None,
+            debug_param: None,
+            skip_empty: None,
+            truncate_content: None,
+            content_prefix: None,
+            max_per_level: BTreeMap::new(),
+            initial_capacity: None,
+            max_messages_per_request: None,
+            storage_backend,
+            clear_on_read: None,
+            record_source_path: None,
+            renderer: None,
+            on_load: None,
+        }
+    }
+
+    /// Replace the message store of an existing [`FlashMessagesFramework`] instance, retaining
+    /// the rest of its configuration.
+    ///
+    /// It comes in handy in tests, when your application factory builds the framework
+    /// internally - e.g. to swap in an in-memory store:
+    ///
+    /// ```rust
+    /// use actix_web_flash_messages::{FlashMessagesFramework, Level, storage::{CookieMessageStore, HeaderMessageStore}};
+    ///
+    /// fn build_framework() -> FlashMessagesFramework {
+    ///     let message_store = CookieMessageStore::builder(actix_web::cookie::Key::generate()).build();
+    ///     FlashMessagesFramework::builder(message_store)
+    ///         .minimum_level(Level::Debug)
+    ///         .build()
+    /// }
+    ///
+    /// let message_framework = build_framework().with_store(HeaderMessageStore::default());
+    /// ```
+    ///
+    /// The returned instance is distinct from the original one - see
+    /// ["Registering more than one framework"](#registering-more-than-one-framework).
+    pub fn with_store<S: FlashMessageStore + 'static>(self, storage_backend: S) -> Self {
+        Self {
             storage_backend: Arc::new(storage_backend),
+            id: NEXT_FRAMEWORK_ID.fetch_add(1, Ordering::Relaxed),
+            ..self
         }
     }
 }
@@ -55,7 +237,19 @@ impl FlashMessagesFramework {
 /// A fluent builder to construct a [`FlashMessagesFramework`] instance.
 pub struct FlashMessagesFrameworkBuilder {
     pub(crate) minimum_level: Option<Level>,
+    pub(crate) dynamic_minimum_level: Option<DynamicMinimumLevel>,
+    pub(crate) debug_param: Option<DebugParam>,
+    pub(crate) skip_empty: Option<bool>,
+    pub(crate) truncate_content: Option<usize>,
+    pub(crate) content_prefix: Option<Arc<str>>,
+    pub(crate) max_per_level: BTreeMap<Level, usize>,
+    pub(crate) initial_capacity: Option<usize>,
+    pub(crate) max_messages_per_request: Option<usize>,
     pub(crate) storage_backend: Arc<dyn FlashMessageStore>,
+    pub(crate) clear_on_read: Option<bool>,
+    pub(crate) record_source_path: Option<bool>,
+    pub(crate) renderer: Option<MessageRenderer>,
+    pub(crate) on_load: Option<OnLoad>,
 }
 
 impl FlashMessagesFrameworkBuilder {
@@ -95,11 +289,304 @@ impl FlashMessagesFrameworkBuilder {
         self
     }
 
+    /// Determine the minimum level on a per-request basis - e.g. to show debug-level messages
+    /// to authenticated admins passing `?debug=1`, while retaining `info` for everybody else.
+    ///
+    /// ```rust
+    /// use actix_web_flash_messages::{FlashMessagesFramework, Level, storage::CookieMessageStore};
+    ///
+    /// # let message_store = CookieMessageStore::builder(actix_web::cookie::Key::generate()).build();
+    /// let message_framework = FlashMessagesFramework::builder(message_store)
+    ///     .dynamic_minimum_level(|request| {
+    ///         // [...] Check that the user is an admin!
+    ///         if request.query_string().contains("debug=1") {
+    ///             Level::Debug
+    ///         } else {
+    ///             Level::Info
+    ///         }
+    ///     })
+    ///     .build();
+    /// ```
+    ///
+    /// The function is invoked once per request, before the request handler.
+    /// It takes precedence over [`FlashMessagesFrameworkBuilder::minimum_level`], which is
+    /// used when no function has been configured.
+    pub fn dynamic_minimum_level<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&ServiceRequest) -> Level + Send + Sync + 'static,
+    {
+        self.dynamic_minimum_level = Some(DynamicMinimumLevel(Arc::new(f)));
+        self
+    }
+
+    /// By default, messages below the minimum level are discarded when they are sent: there is
+    /// no way to look at them afterwards.
+    ///
+    /// Use `debug_param` to troubleshoot production issues: messages below the minimum level are
+    /// stored alongside the others, but they are filtered out when incoming messages are read -
+    /// unless the request carries the `name` query parameter set to `value`.  
+    /// Hidden messages are cleared along with the visible ones once incoming messages are read.
+    ///
+    /// ```rust
+    /// use actix_web_flash_messages::{FlashMessagesFramework, storage::CookieMessageStore};
+    ///
+    /// # let message_store = CookieMessageStore::builder(actix_web::cookie::Key::generate()).build();
+    /// // `GET /dashboard?flash_debug=1` shows debug-level messages as well.
+    /// let message_framework = FlashMessagesFramework::builder(message_store)
+    ///     .debug_param("flash_debug", "1")
+    ///     .build();
+    /// ```
+    ///
+    /// Anybody can add a query parameter to a URL: leave `debug_param` unset in hardened
+    /// deployments, or pick a hard-to-guess `value`.  
+    /// Storing messages below the minimum level makes the stored payload larger.
+    pub fn debug_param<N: Into<String>, V: Into<String>>(mut self, name: N, value: V) -> Self {
+        self.debug_param = Some(DebugParam {
+            name: name.into(),
+            value: value.into(),
+        });
+        self
+    }
+
+    /// By default, [`FlashMessagesFramework`] dispatches messages regardless of their content.
+    ///
+    /// If you enable `skip_empty`, messages whose content is empty or only made of whitespace
+    /// (see [`FlashMessage::is_empty`]) are dropped when sent - e.g. a formatted error that
+    /// turned out to be blank.
+    pub fn skip_empty(mut self, skip_empty: bool) -> Self {
+        self.skip_empty = Some(skip_empty);
+        self
+    }
+
+    /// By default, the content of outgoing flash messages is dispatched as is, whatever its length.
+    ///
+    /// Use `truncate_content` to cap the length, in bytes, of the content of outgoing messages -
+    /// e.g. to keep a full error report from blowing the cookie size limit.  
+    /// Longer content is truncated when the message is sent, using [`FlashMessage::content_truncated`].
+    pub fn truncate_content(mut self, max: usize) -> Self {
+        self.truncate_content = Some(max);
+        self
+    }
+
+    /// By default, the content of outgoing flash messages is dispatched as is.
+    ///
+    /// Use `content_prefix` to prepend a fixed tag to the content of every outgoing message -
+    /// e.g. `"[STAGING] "` to tell environments apart without touching your call sites.  
+    /// The prefix is added when the message is sent: [`skip_empty`](Self::skip_empty) looks at
+    /// the original content, while [`truncate_content`](Self::truncate_content) caps the length
+    /// of the prefixed content.
+    pub fn content_prefix<S: Into<String>>(mut self, prefix: S) -> Self {
+        self.content_prefix = Some(prefix.into().into());
+        self
+    }
+
+    /// By default, there is no limit on the number of messages of a given [`Level`] that can be
+    /// dispatched in a single response.
+    ///
+    /// Use `max_per_level` to cap it - e.g. to avoid overwhelming the user with a burst of
+    /// validation errors. You can call it once for each level you want to limit:
+    ///
+    /// ```rust
+    /// use actix_web_flash_messages::{FlashMessagesFramework, Level, storage::CookieMessageStore};
+    ///
+    /// # let message_store = CookieMessageStore::builder(actix_web::cookie::Key::generate()).build();
+    /// let message_framework = FlashMessagesFramework::builder(message_store)
+    ///     .max_per_level(Level::Error, 3)
+    ///     .max_per_level(Level::Warning, 5)
+    ///     .build();
+    /// ```
+    ///
+    /// The limit is enforced when outgoing messages are flushed, at the end of the request - it
+    /// includes incoming messages that are carried over.  
+    /// The **last** `max` messages of a limited level, in delivery order, are kept: the earlier
+    /// ones are dropped. Messages of other levels are not affected.
+    pub fn max_per_level(mut self, level: Level, max: usize) -> Self {
+        self.max_per_level.insert(level, max);
+        self
+    }
+
+    /// By default, the buffer holding the outgoing flash messages of a request starts empty and
+    /// grows as messages are sent.
+    ///
+    /// Use `initial_capacity` to pre-allocate room for `capacity` messages - e.g. for hot
+    /// endpoints that predictably send several messages, to avoid repeated reallocations.  
+    /// It is a capacity, not a limit: more messages can still be sent.
+    pub fn initial_capacity(mut self, capacity: usize) -> Self {
+        self.initial_capacity = Some(capacity);
+        self
+    }
+
+    /// By default, there is no limit on the number of flash messages a request can send.
+    ///
+    /// Use `max_messages_per_request` to cap it - e.g. to guard against a handler flooding the
+    /// user with messages in a loop.  
+    /// The cap is enforced when messages are sent (via [`FlashMessage::send`], [`FlashScope`],
+    /// etc.): once `max` messages have been queued, additional messages are dropped and a
+    /// warning is logged (if the `tracing` feature flag is enabled). It fails fast, at the
+    /// source, rather than when outgoing messages are flushed (e.g. with
+    /// [`StoreError::SizeLimitExceeded`]).  
+    /// Incoming messages that are carried over to the outgoing response do not count towards the
+    /// cap.
+    ///
+    /// [`FlashScope`]: crate::FlashScope
+    /// [`StoreError::SizeLimitExceeded`]: crate::storage::StoreError::SizeLimitExceeded
+    pub fn max_messages_per_request(mut self, max: usize) -> Self {
+        self.max_messages_per_request = Some(max);
+        self
+    }
+
+    /// By default, flash messages do not record where they come from.
+    ///
+    /// If you enable `record_source_path`, the path of the request is attached to the messages
+    /// it sends, when they are flushed - see [`FlashMessage::source_path`]. It gives you analytics
+    /// context (e.g. which page generated each message) without having to thread the path
+    /// through your request handlers.  
+    /// Messages that already carry a source path - e.g. incoming messages that are carried over -
+    /// retain it.
+    ///
+    /// It is disabled by default to keep stored payloads lean.
+    pub fn record_source_path(mut self, record_source_path: bool) -> Self {
+        self.record_source_path = Some(record_source_path);
+        self
+    }
+
+    /// By default, incoming flash messages are cleared by the first response, whether or not
+    /// they were read by the request handler.
+    ///
+    /// If you enable `clear_on_read`, incoming flash messages are retained until a request
+    /// actually reads them via the [`IncomingFlashMessages`] extractor: they are cleared on the
+    /// response to that very request.  
+    /// In both modes, the messages read by a request are cleared even if the same request sends new
+    /// flash messages - only the newly sent ones are stored.
+    ///
+    /// Be mindful of the cost: when `clear_on_read` is enabled, requests that do not read
+    /// incoming flash messages have to load them in order to carry them over.
+    ///
+    /// It has no effect if the message store is not one-time - see
+    /// [`FlashMessageStore::is_one_time`].
+    ///
+    /// [`IncomingFlashMessages`]: crate::IncomingFlashMessages
+    pub fn clear_on_read(mut self, clear_on_read: bool) -> Self {
+        self.clear_on_read = Some(clear_on_read);
+        self
+    }
+
+    /// Set a default renderer for incoming flash messages - e.g. to convert markdown to HTML
+    /// or to linkify URLs.
+    ///
+    /// The renderer is applied by [`IncomingFlashMessages::rendered_iter`], keeping rendering
+    /// logic in one place instead of repeating it in every template.  
+    /// By default, no renderer is configured: [`IncomingFlashMessages::rendered_iter`] yields
+    /// the raw message content.
+    ///
+    /// ```rust
+    /// use actix_web_flash_messages::{FlashMessagesFramework, storage::CookieMessageStore};
+    ///
+    /// # let message_store = CookieMessageStore::builder(actix_web::cookie::Key::generate()).build();
+    /// let message_framework = FlashMessagesFramework::builder(message_store)
+    ///     .renderer(|message| format!("<strong>{}</strong>", message.content()))
+    ///     .build();
+    /// ```
+    ///
+    /// [`IncomingFlashMessages::rendered_iter`]: crate::IncomingFlashMessages::rendered_iter
+    pub fn renderer<F>(mut self, renderer: F) -> Self
+    where
+        F: Fn(&FlashMessage) -> String + Send + Sync + 'static,
+    {
+        self.renderer = Some(MessageRenderer(Arc::new(renderer)));
+        self
+    }
+
+    /// Post-process incoming flash messages right after they have been loaded from the store -
+    /// e.g. to strip a prefix, to translate message codes into localized strings or to drop
+    /// messages that should not be shown.
+    ///
+    /// The hook is applied once per request, before incoming messages are handed over to
+    /// [`IncomingFlashMessages`] (and the other extractors): it is a single choke point for
+    /// read-side normalisation across all your handlers.  
+    /// The hook only affects what the extractors see: messages that are carried over to the
+    /// outgoing response because they have not been read (see
+    /// [`FlashMessagesFrameworkBuilder::clear_on_read`]) are stored as loaded from the store,
+    /// and the hook runs on them again - exactly once - when they are eventually loaded.
+    ///
+    /// By default, incoming messages are returned as loaded.
+    ///
+    /// ```rust
+    /// use actix_web_flash_messages::{FlashMessagesFramework, Level, storage::CookieMessageStore};
+    ///
+    /// # let message_store = CookieMessageStore::builder(actix_web::cookie::Key::generate()).build();
+    /// let message_framework = FlashMessagesFramework::builder(message_store)
+    ///     .on_load(|messages| {
+    ///         messages
+    ///             .into_iter()
+    ///             .filter(|m| m.level() != Level::Debug)
+    ///             .collect()
+    ///     })
+    ///     .build();
+    /// ```
+    ///
+    /// [`IncomingFlashMessages`]: crate::IncomingFlashMessages
+    pub fn on_load<F>(mut self, on_load: F) -> Self
+    where
+        F: Fn(Vec<FlashMessage>) -> Vec<FlashMessage> + Send + Sync + 'static,
+    {
+        self.on_load = Some(OnLoad(Arc::new(on_load)));
+        self
+    }
+
     /// Finalise the builder and return a [`FlashMessagesFramework`] instance.
     pub fn build(self) -> FlashMessagesFramework {
         FlashMessagesFramework {
             minimum_level: self.minimum_level.unwrap_or(Level::Info),
+            dynamic_minimum_level: self.dynamic_minimum_level,
+            debug_param: self.debug_param,
+            skip_empty: self.skip_empty.unwrap_or(false),
+            truncate_content: self.truncate_content,
+            content_prefix: self.content_prefix,
+            max_per_level: Arc::new(self.max_per_level),
+            initial_capacity: self.initial_capacity.unwrap_or(0),
+            max_messages_per_request: self.max_messages_per_request,
             storage_backend: self.storage_backend,
+            clear_on_read: self.clear_on_read.unwrap_or(false),
+            record_source_path: self.record_source_path.unwrap_or(false),
+            renderer: self.renderer,
+            on_load: self.on_load,
+            id: NEXT_FRAMEWORK_ID.fetch_add(1, Ordering::Relaxed),
         }
     }
 }
+
+/// A function to render incoming flash messages, configured via [`FlashMessagesFrameworkBuilder::renderer`].
+#[derive(Clone)]
+pub(crate) struct MessageRenderer(pub(crate) Arc<dyn Fn(&FlashMessage) -> String + Send + Sync>);
+
+/// A function to post-process incoming flash messages, configured via
+/// [`FlashMessagesFrameworkBuilder::on_load`].
+#[derive(Clone)]
+pub(crate) struct OnLoad(
+    pub(crate) Arc<dyn Fn(Vec<FlashMessage>) -> Vec<FlashMessage> + Send + Sync>,
+);
+
+/// The query parameter that lifts the read-time level filter, configured via
+/// [`FlashMessagesFrameworkBuilder::debug_param`].
+#[derive(Clone)]
+pub(crate) struct DebugParam {
+    name: String,
+    value: String,
+}
+
+impl DebugParam {
+    /// Returns `true` if `request` carries the debug query parameter with the expected value.
+    pub(crate) fn matches(&self, request: &ServiceRequest) -> bool {
+        Query::<HashMap<String, String>>::from_query(request.query_string())
+            .map(|query| query.get(&self.name) == Some(&self.value))
+            .unwrap_or(false)
+    }
+}
+
+/// A function to determine the minimum level on a per-request basis, configured via
+/// [`FlashMessagesFrameworkBuilder::dynamic_minimum_level`].
+#[derive(Clone)]
+pub(crate) struct DynamicMinimumLevel(
+    pub(crate) Arc<dyn Fn(&ServiceRequest) -> Level + Send + Sync>,
+);
@@ -0,0 +1,208 @@
+use crate::storage::FlashMessageStore;
+#[cfg(feature = "sessions")]
+use crate::storage::SessionMessageStore;
+#[cfg(feature = "cookies")]
+use crate::storage::{check_cookie_name, check_key_id, CookieMessageStore};
+use crate::{FlashMessagesFramework, Level};
+use actix_web::cookie::Key;
+use std::sync::Arc;
+
+/// The configuration of [`FlashMessagesFramework`], as a plain data structure - e.g. to load the
+/// entire flash messages setup from a configuration file.
+///
+/// ```rust
+/// use actix_web::cookie::Key;
+/// use actix_web_flash_messages::{FlashConfig, FlashMessagesFramework};
+///
+/// let config: FlashConfig = serde_json::from_str(
+///     r#"{ "minimum_level": "debug", "cookie": { "cookie_name": "_notices" } }"#,
+/// )
+/// .unwrap();
+/// let signing_key = Key::generate(); // This will usually come from a secret store!
+/// let message_framework = FlashMessagesFramework::from_config(config, signing_key).unwrap();
+/// ```
+///
+/// Exactly one store must be configured - see [`FlashMessagesFramework::from_config`].
+/// Unknown fields are rejected, to surface typos in your configuration files early.
+///
+/// It is only available if the `cookies` or the `sessions` feature flag is enabled.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct FlashConfig {
+    /// See [`FlashMessagesFrameworkBuilder::minimum_level`] - it defaults to [`Level::Info`].
+    ///
+    /// [`FlashMessagesFrameworkBuilder::minimum_level`]: crate::FlashMessagesFrameworkBuilder::minimum_level
+    #[serde(default = "default_minimum_level")]
+    pub minimum_level: Level,
+    /// Store flash messages in a signed cookie.
+    #[cfg(feature = "cookies")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cookie: Option<CookieConfig>,
+    /// Store flash messages in the session.
+    #[cfg(feature = "sessions")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session: Option<SessionConfig>,
+}
+
+fn default_minimum_level() -> Level {
+    Level::Info
+}
+
+/// The configuration of a [`CookieMessageStore`], within a [`FlashConfig`].
+///
+/// Each field maps to the [`CookieMessageStoreBuilder`] method with the same name - unset fields
+/// retain the builder defaults.
+///
+/// [`CookieMessageStoreBuilder`]: crate::storage::CookieMessageStoreBuilder
+#[cfg(feature = "cookies")]
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq, Default)]
+#[serde(deny_unknown_fields)]
+pub struct CookieConfig {
+    /// The name of the flash cookie - see [`CookieMessageStoreBuilder::cookie_name`].
+    ///
+    /// [`CookieMessageStoreBuilder::cookie_name`]: crate::storage::CookieMessageStoreBuilder::cookie_name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cookie_name: Option<String>,
+    /// The identifier of the signing key - see [`CookieMessageStoreBuilder::key_id`].
+    ///
+    /// [`CookieMessageStoreBuilder::key_id`]: crate::storage::CookieMessageStoreBuilder::key_id
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_id: Option<String>,
+    /// The maximum size of the flash cookie, in bytes - see
+    /// [`CookieMessageStoreBuilder::bytes_size_limit`].
+    ///
+    /// [`CookieMessageStoreBuilder::bytes_size_limit`]: crate::storage::CookieMessageStoreBuilder::bytes_size_limit
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bytes_size_limit: Option<u32>,
+    /// The maximum number of chunk cookies - see [`CookieMessageStoreBuilder::max_chunks`].
+    ///
+    /// [`CookieMessageStoreBuilder::max_chunks`]: crate::storage::CookieMessageStoreBuilder::max_chunks
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_chunks: Option<usize>,
+    /// The `Path` attribute of the flash cookie - see [`CookieMessageStoreBuilder::path`].
+    ///
+    /// [`CookieMessageStoreBuilder::path`]: crate::storage::CookieMessageStoreBuilder::path
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// The `Domain` attribute of the flash cookie - see [`CookieMessageStoreBuilder::domain`].
+    ///
+    /// [`CookieMessageStoreBuilder::domain`]: crate::storage::CookieMessageStoreBuilder::domain
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub domain: Option<String>,
+}
+
+#[cfg(feature = "cookies")]
+impl CookieConfig {
+    fn into_store(self, signing_key: Key) -> Result<CookieMessageStore, ConfigError> {
+        let mut builder = CookieMessageStore::builder(signing_key);
+        if let Some(cookie_name) = self.cookie_name {
+            check_cookie_name(&cookie_name).map_err(ConfigError::InvalidCookieConfig)?;
+            builder = builder.cookie_name(cookie_name);
+        }
+        if let Some(key_id) = self.key_id {
+            check_key_id(&key_id).map_err(ConfigError::InvalidCookieConfig)?;
+            builder = builder.key_id(key_id);
+        }
+        if let Some(bytes_size_limit) = self.bytes_size_limit {
+            builder = builder.bytes_size_limit(bytes_size_limit);
+        }
+        if let Some(max_chunks) = self.max_chunks {
+            builder = builder.max_chunks(max_chunks);
+        }
+        if let Some(path) = self.path {
+            builder = builder.path(path);
+        }
+        if let Some(domain) = self.domain {
+            builder = builder.domain(domain);
+        }
+        builder
+            .try_build()
+            .map_err(ConfigError::InvalidCookieConfig)
+    }
+}
+
+/// The configuration of a [`SessionMessageStore`], within a [`FlashConfig`].
+///
+/// Each field maps to the [`SessionMessageStore`] method with the same name - unset fields
+/// retain the defaults. `key` is the key used to store flash messages in the session map.
+#[cfg(feature = "sessions")]
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq, Default)]
+#[serde(deny_unknown_fields)]
+pub struct SessionConfig {
+    /// The key used to store flash messages in the session map - see
+    /// [`SessionMessageStore::new`]. It defaults to `_flash`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    /// The maximum size of the serialised flash messages, in bytes - see
+    /// [`SessionMessageStore::bytes_size_limit`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bytes_size_limit: Option<u32>,
+    /// Whether outgoing messages are merged with the ones written during the request - see
+    /// [`SessionMessageStore::merge`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub merge: Option<bool>,
+}
+
+#[cfg(feature = "sessions")]
+impl SessionConfig {
+    fn into_store(self) -> SessionMessageStore {
+        let mut store = match self.key {
+            Some(key) => SessionMessageStore::new(key),
+            None => SessionMessageStore::default(),
+        };
+        if let Some(bytes_size_limit) = self.bytes_size_limit {
+            store = store.bytes_size_limit(bytes_size_limit);
+        }
+        if let Some(merge) = self.merge {
+            store = store.merge(merge);
+        }
+        store
+    }
+}
+
+impl FlashMessagesFramework {
+    /// Build a [`FlashMessagesFramework`] out of a [`FlashConfig`].
+    ///
+    /// `signing_key` is used to sign flash cookies if [`FlashConfig::cookie`] is set. It is
+    /// ignored otherwise.
+    ///
+    /// It returns an error unless exactly one store is configured, or if the store configuration
+    /// is invalid - e.g. a cookie name containing spaces.
+    #[cfg_attr(not(feature = "cookies"), allow(unused_variables))]
+    pub fn from_config(
+        config: FlashConfig,
+        signing_key: Key,
+    ) -> Result<FlashMessagesFramework, ConfigError> {
+        let mut stores: Vec<Arc<dyn FlashMessageStore>> = vec![];
+        #[cfg(feature = "cookies")]
+        if let Some(cookie) = config.cookie {
+            stores.push(Arc::new(cookie.into_store(signing_key)?));
+        }
+        #[cfg(feature = "sessions")]
+        if let Some(session) = config.session {
+            stores.push(Arc::new(session.into_store()));
+        }
+        match stores.len() {
+            0 => Err(ConfigError::MissingStore),
+            1 => Ok(FlashMessagesFramework::builder_arc(stores.pop().unwrap())
+                .minimum_level(config.minimum_level)
+                .build()),
+            _ => Err(ConfigError::MultipleStores),
+        }
+    }
+}
+
+/// Possible failures modes for [`FlashMessagesFramework::from_config`].
+#[derive(thiserror::Error, Debug)]
+pub enum ConfigError {
+    /// Neither `cookie` nor `session` is set in the [`FlashConfig`].
+    #[error("No flash message store is configured - set either `cookie` or `session`")]
+    MissingStore,
+    /// Both `cookie` and `session` are set in the [`FlashConfig`].
+    #[error("More than one flash message store is configured - set either `cookie` or `session`, not both")]
+    MultipleStores,
+    /// [`FlashConfig::cookie`] holds invalid values - e.g. a cookie name containing spaces.
+    #[cfg(feature = "cookies")]
+    #[error("The flash cookie configuration is invalid")]
+    InvalidCookieConfig(#[source] anyhow::Error),
+}
@@ -1,5 +1,9 @@
 use crate::middleware::OUTGOING_MAILBOX;
+use actix_web::http::StatusCode;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
 use std::fmt::{Debug, Display, Formatter};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 #[derive(serde::Deserialize, serde::Serialize, Clone)]
 /// A **one-time** user notification.
@@ -11,17 +15,135 @@ use std::fmt::{Debug, Display, Formatter};
 /// - Use different colours, in the UI, to display messages (e.g. red for errors, orange for warnings, etc.);
 ///
 /// You can build a flash message via [`FlashMessage::new`] by specifying its content and [`Level`].
-/// You can also use the shorter level-based constructors - e.g. [`FlashMessage::info`].
+/// You can also use the shorter level-based constructors - e.g. [`FlashMessage::info`].  
+/// Use [`FlashMessage::builder`] if you need to set any of the optional fields - e.g. a title.
+///
+/// # Serialization
+///
+/// [`FlashMessage`] is serialized as a map with the following keys:
+///
+/// - `content`, a string;
+/// - `level`, see [`Level`] for its representation;
+/// - `title`, a string - omitted if unset;
+/// - `metadata`, a map of strings - omitted if empty;
+/// - `data`, the payload attached via [`FlashMessage::with_data`] - omitted if unset;
+/// - `channel`, a string - omitted if unset;
+/// - `code`, a string - omitted if unset;
+/// - `expires_at`, a unix timestamp in seconds - omitted if unset;
+/// - `source_path`, a string - omitted if unset.
+///
+/// The optional keys can be omitted when deserializing.  
+/// This representation is stable: you can embed a [`FlashMessage`] in your own types using
+/// `#[serde(flatten)]`.
+///
+/// ```rust
+/// use actix_web_flash_messages::FlashMessage;
+///
+/// #[derive(serde::Serialize, serde::Deserialize)]
+/// struct AppFlash {
+///     #[serde(flatten)]
+///     message: FlashMessage,
+///     dismissible: bool,
+/// }
+///
+/// let flash: AppFlash = serde_json::from_str(
+///     r#"{"content": "Hey there!", "level": "info", "dismissible": true}"#
+/// ).unwrap();
+/// assert_eq!(flash.message.content(), "Hey there!");
+/// ```
 #[must_use = "You must call `.send()` on a `FlashMessage` for it to have an effect"]
 pub struct FlashMessage {
-    content: String,
+    // Field names are pinned explicitly: they are part of the stable serialized representation.
+    // Static content (e.g. string literals) is not copied on the heap - see `FlashMessage::new_static`.
+    #[serde(rename = "content")]
+    content: Cow<'static, str>,
+    #[serde(rename = "level")]
     level: Level,
+    #[serde(rename = "title", default, skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    #[serde(
+        rename = "metadata",
+        default,
+        skip_serializing_if = "BTreeMap::is_empty"
+    )]
+    metadata: BTreeMap<String, String>,
+    #[serde(rename = "data", default, skip_serializing_if = "Option::is_none")]
+    data: Option<serde_json::Value>,
+    #[serde(rename = "channel", default, skip_serializing_if = "Option::is_none")]
+    channel: Option<String>,
+    #[serde(rename = "code", default, skip_serializing_if = "Option::is_none")]
+    code: Option<String>,
+    #[serde(
+        rename = "expires_at",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    expires_at: Option<u64>,
+    #[serde(
+        rename = "source_path",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    source_path: Option<String>,
 }
 
 impl FlashMessage {
     /// Build a [`FlashMessage`] by specifying its content and [`Level`].
     pub fn new(content: String, level: Level) -> Self {
-        Self { content, level }
+        Self::with_cow_content(Cow::Owned(content), level)
+    }
+
+    /// Build a [`FlashMessage`] from static content - e.g. a string literal - and a [`Level`].
+    ///
+    /// Unlike [`FlashMessage::new`], the content is not copied: no allocation is performed until
+    /// the message is serialized.
+    ///
+    /// ```rust
+    /// use actix_web_flash_messages::{FlashMessage, Level};
+    ///
+    /// let message = FlashMessage::new_static("Saved successfully", Level::Success);
+    /// assert_eq!(message.content(), "Saved successfully");
+    /// ```
+    pub fn new_static(content: &'static str, level: Level) -> Self {
+        Self::with_cow_content(Cow::Borrowed(content), level)
+    }
+
+    fn with_cow_content(content: Cow<'static, str>, level: Level) -> Self {
+        Self {
+            content,
+            level,
+            title: None,
+            metadata: BTreeMap::new(),
+            data: None,
+            channel: None,
+            code: None,
+            expires_at: None,
+            source_path: None,
+        }
+    }
+
+    /// The name of the channel that messages are assigned to if they are not sent to a specific one.
+    ///
+    /// See [`FlashMessage::channel`] for more details.
+    pub const DEFAULT_CHANNEL: &'static str = "default";
+
+    /// A fluent API to build a [`FlashMessage`], including its optional fields.
+    ///
+    /// ```rust
+    /// use actix_web_flash_messages::{FlashMessage, Level};
+    ///
+    /// let message = FlashMessage::builder()
+    ///     .content("Your password will expire in 3 days")
+    ///     .level(Level::Warning)
+    ///     .title("Security")
+    ///     .metadata("expires_in_days", "3")
+    ///     .build();
+    /// assert_eq!(message.title(), Some("Security"));
+    /// ```
+    pub fn builder() -> FlashMessageBuilder {
+        FlashMessageBuilder {
+            message: Self::new(String::new(), Level::Info),
+        }
     }
 
     /// The string content of this flash message.
@@ -29,82 +151,600 @@ impl FlashMessage {
         &self.content
     }
 
+    /// The content of this flash message, moved out of it.
+    ///
+    /// It avoids a copy compared to `message.content().to_owned()` - unless the message was
+    /// built via [`FlashMessage::new_static`].
+    pub fn into_content(self) -> String {
+        self.content.into_owned()
+    }
+
     /// The [`Level`] of this flash message.
     pub fn level(&self) -> Level {
         self.level
     }
 
+    /// The [`Level`] of this flash message, as a lowercase string - e.g. `"info"`.
+    ///
+    /// It matches the `Display` implementation of [`Level`], without
+    /// allocating - e.g. to be used as a CSS class in templates.
+    pub fn level_str(&self) -> &'static str {
+        level_to_str(&self.level)
+    }
+
+    /// The content of this flash message, prefixed by the emoji of its [`Level`] - see
+    /// [`Level::emoji`].
+    ///
+    /// ```rust
+    /// use actix_web_flash_messages::FlashMessage;
+    ///
+    /// assert_eq!(FlashMessage::success("Saved!").display_with_emoji(), "✅ Saved!");
+    /// ```
+    pub fn display_with_emoji(&self) -> String {
+        format!("{} {}", self.level.emoji(), self.content)
+    }
+
+    /// Replace the content of this [`FlashMessage`], retaining all its other fields.
+    ///
+    /// It comes in handy to rewrite messages in bulk - e.g. to translate or sanitize them:
+    ///
+    /// ```rust
+    /// use actix_web_flash_messages::{FlashMessage, Level};
+    ///
+    /// let message = FlashMessage::warning("Disk almost full");
+    /// let message = message.clone().with_content(format!("[staging] {}", message.content()));
+    /// assert_eq!(message.content(), "[staging] Disk almost full");
+    /// assert_eq!(message.level(), Level::Warning);
+    /// ```
+    pub fn with_content<S: Into<String>>(mut self, content: S) -> Self {
+        self.content = Cow::Owned(content.into());
+        self
+    }
+
+    /// The content of this flash message, truncated to at most `max` bytes - ellipsis included.
+    ///
+    /// Content is truncated on a character boundary and an ellipsis (`…`, 3 bytes long) is appended.
+    /// If `max` is smaller than the ellipsis, the content is cut to `max` bytes (on a character
+    /// boundary) with no ellipsis - the result is never longer than `max` bytes.
+    ///
+    /// ```rust
+    /// use actix_web_flash_messages::FlashMessage;
+    ///
+    /// let message = FlashMessage::error("Connection refused (os error 111)");
+    /// assert_eq!(message.content_truncated(21), "Connection refused…");
+    /// assert_eq!(message.content_truncated(100), "Connection refused (os error 111)");
+    /// ```
+    pub fn content_truncated(&self, max: usize) -> Cow<'_, str> {
+        const ELLIPSIS: char = '…';
+        if self.content.len() <= max {
+            return Cow::Borrowed(&self.content);
+        }
+        let ellipsis_fits = max >= ELLIPSIS.len_utf8();
+        let mut end = if ellipsis_fits {
+            max - ELLIPSIS.len_utf8()
+        } else {
+            max
+        };
+        while !self.content.is_char_boundary(end) {
+            end -= 1;
+        }
+        if !ellipsis_fits {
+            return Cow::Borrowed(&self.content[..end]);
+        }
+        let mut truncated = String::with_capacity(end + ELLIPSIS.len_utf8());
+        truncated.push_str(&self.content[..end]);
+        truncated.push(ELLIPSIS);
+        Cow::Owned(truncated)
+    }
+
+    /// Replace the [`Level`] of this [`FlashMessage`], retaining all its other fields.
+    pub fn with_level(mut self, level: Level) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// The title of this flash message, if any.
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    /// The channel this flash message was sent to, if any.
+    ///
+    /// Channels let you target separate regions of your UI - e.g. a top banner and an inline
+    /// form-errors region. Use [`FlashMessageBuilder::channel`] to set it and
+    /// [`IncomingFlashMessages::channel`] to retrieve the messages in a given channel.  
+    /// Messages without a channel belong to [`FlashMessage::DEFAULT_CHANNEL`].
+    ///
+    /// [`IncomingFlashMessages::channel`]: crate::IncomingFlashMessages::channel
+    pub fn channel(&self) -> Option<&str> {
+        self.channel.as_deref()
+    }
+
+    /// Attach a stable, machine-readable code to this [`FlashMessage`] - e.g. `"password.reset.sent"`.
+    ///
+    /// API clients can branch on the code while user interfaces render the content.
+    ///
+    /// ```rust
+    /// use actix_web_flash_messages::FlashMessage;
+    ///
+    /// let message = FlashMessage::success("Check your inbox!").with_code("password.reset.sent");
+    /// assert_eq!(message.code(), Some("password.reset.sent"));
+    /// ```
+    pub fn with_code<S: Into<String>>(mut self, code: S) -> Self {
+        self.code = Some(code.into());
+        self
+    }
+
+    /// The machine-readable code of this flash message, if any.
+    ///
+    /// See [`FlashMessage::with_code`] for more details.
+    pub fn code(&self) -> Option<&str> {
+        self.code.as_deref()
+    }
+
+    /// Set an absolute expiry time for this [`FlashMessage`], as a unix timestamp in seconds -
+    /// e.g. for "This offer ends at 5pm".
+    ///
+    /// Expired messages are dropped when incoming messages are loaded: they are never shown to
+    /// the user and they are removed from the store.
+    ///
+    /// ```rust
+    /// use actix_web_flash_messages::FlashMessage;
+    ///
+    /// let message = FlashMessage::info("This offer ends at 5pm").with_expires_at(1_700_000_000);
+    /// assert_eq!(message.expires_at(), Some(1_700_000_000));
+    /// assert!(message.is_expired());
+    /// ```
+    pub fn with_expires_at(mut self, expires_at: u64) -> Self {
+        self.expires_at = Some(expires_at);
+        self
+    }
+
+    /// The expiry time of this flash message, as a unix timestamp in seconds, if any.
+    ///
+    /// See [`FlashMessage::with_expires_at`] for more details.
+    pub fn expires_at(&self) -> Option<u64> {
+        self.expires_at
+    }
+
+    /// The path of the request that sent this flash message, if recorded - e.g. to attribute
+    /// messages to pages in your product analytics.
+    ///
+    /// It is only set if [`FlashMessagesFrameworkBuilder::record_source_path`] is enabled.
+    ///
+    /// [`FlashMessagesFrameworkBuilder::record_source_path`]: crate::FlashMessagesFrameworkBuilder::record_source_path
+    pub fn source_path(&self) -> Option<&str> {
+        self.source_path.as_deref()
+    }
+
+    pub(crate) fn set_source_path_if_missing(&mut self, path: &str) {
+        if self.source_path.is_none() {
+            self.source_path = Some(path.to_owned());
+        }
+    }
+
+    /// Returns `true` if this flash message has an expiry time and it lies in the past.
+    pub fn is_expired(&self) -> bool {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+
+    /// Returns `true` if the content of this flash message is empty or only made of whitespace.
+    ///
+    /// ```rust
+    /// use actix_web_flash_messages::FlashMessage;
+    ///
+    /// assert!(FlashMessage::error(" \n").is_empty());
+    /// assert!(!FlashMessage::error("Invalid email").is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.content.trim().is_empty()
+    }
+
+    /// The string key-value pairs attached to this flash message.
+    pub fn metadata(&self) -> &BTreeMap<String, String> {
+        &self.metadata
+    }
+
+    /// Attach a typed payload to this [`FlashMessage`] - e.g. `{ "retry_after_seconds": 30 }`.
+    ///
+    /// The payload is serialized alongside the message content and can be retrieved, on the
+    /// receiving end, using [`FlashMessage::data`].  
+    /// It fails if `data` cannot be represented as JSON.
+    ///
+    /// ```rust
+    /// use actix_web_flash_messages::FlashMessage;
+    ///
+    /// #[derive(serde::Serialize, serde::Deserialize)]
+    /// struct RetryAfter {
+    ///     retry_after_seconds: u64,
+    /// }
+    ///
+    /// let message = FlashMessage::warning("Too many attempts")
+    ///     .with_data(&RetryAfter { retry_after_seconds: 30 })
+    ///     .unwrap();
+    /// let data: RetryAfter = message.data().unwrap().unwrap();
+    /// assert_eq!(data.retry_after_seconds, 30);
+    /// ```
+    pub fn with_data<T: serde::Serialize>(mut self, data: &T) -> Result<Self, serde_json::Error> {
+        self.data = Some(serde_json::to_value(data)?);
+        Ok(self)
+    }
+
+    /// The typed payload attached to this flash message via [`FlashMessage::with_data`], if any.
+    ///
+    /// It returns an error if the payload cannot be deserialized as `T`.
+    pub fn data<T: serde::de::DeserializeOwned>(&self) -> Option<Result<T, serde_json::Error>> {
+        self.data
+            .as_ref()
+            .map(|data| serde_json::from_value(data.clone()))
+    }
+
     /// Build an info-level [`FlashMessage`] by specifying its content.
     pub fn info<S: Into<String>>(content: S) -> Self {
-        Self {
-            content: content.into(),
-            level: Level::Info,
-        }
+        Self::new(content.into(), Level::Info)
     }
 
     /// Build a debug-level [`FlashMessage`] by specifying its content.
     pub fn debug<S: Into<String>>(content: S) -> Self {
-        Self {
-            content: content.into(),
-            level: Level::Debug,
-        }
+        Self::new(content.into(), Level::Debug)
     }
 
     /// Build a success-level [`FlashMessage`] by specifying its content.
     pub fn success<S: Into<String>>(content: S) -> Self {
-        Self {
-            content: content.into(),
-            level: Level::Success,
-        }
+        Self::new(content.into(), Level::Success)
     }
 
     /// Build a warning-level [`FlashMessage`] by specifying its content.
     pub fn warning<S: Into<String>>(content: S) -> Self {
-        Self {
-            content: content.into(),
-            level: Level::Warning,
-        }
+        Self::new(content.into(), Level::Warning)
     }
 
     /// Build an error-level [`FlashMessage`] by specifying its content.
     pub fn error<S: Into<String>>(content: S) -> Self {
-        Self {
-            content: content.into(),
-            level: Level::Error,
-        }
+        Self::new(content.into(), Level::Error)
+    }
+
+    /// Build a [`FlashMessage`] whose [`Level`] reflects the class of an HTTP status code - e.g.
+    /// to report the outcome of a call to another service.
+    ///
+    /// - `2xx` maps to [`Level::Success`];
+    /// - `4xx` maps to [`Level::Warning`];
+    /// - `5xx` maps to [`Level::Error`];
+    /// - all other statuses (`1xx`, `3xx`) map to [`Level::Info`].
+    ///
+    /// ```rust
+    /// use actix_web::http::StatusCode;
+    /// use actix_web_flash_messages::{FlashMessage, Level};
+    ///
+    /// let message = FlashMessage::from_status(StatusCode::CONFLICT, "This username is taken");
+    /// assert_eq!(message.level(), Level::Warning);
+    /// ```
+    pub fn from_status<S: Into<String>>(status: StatusCode, content: S) -> Self {
+        let level = if status.is_success() {
+            Level::Success
+        } else if status.is_client_error() {
+            Level::Warning
+        } else if status.is_server_error() {
+            Level::Error
+        } else {
+            Level::Info
+        };
+        Self::new(content.into(), level)
     }
 
     /// Attach this [`FlashMessage`] to the outgoing request.
     ///
     /// The message will be dropped if its [`Level`] is below the minimum level
-    /// specified when configuring [`FlashMessagesFramework`] via [`FlashMessagesFrameworkBuilder::minimum_level`].
+    /// specified when configuring [`FlashMessagesFramework`] via [`FlashMessagesFrameworkBuilder::minimum_level`],
+    /// or if it is empty and [`FlashMessagesFrameworkBuilder::skip_empty`] is enabled.
     ///
-    /// This method will **panic** if [`FlashMessagesFramework`] has not been registered as a middleware.
+    /// This method will **panic** if [`FlashMessagesFramework`] has not been registered as a middleware.  
+    /// It is a no-op if the `disabled` feature flag is enabled.
     ///
     /// [`FlashMessagesFramework`]: crate::FlashMessagesFramework
     /// [`FlashMessagesFrameworkBuilder::minimum_level`]: crate::FlashMessagesFrameworkBuilder::minimum_level
+    /// [`FlashMessagesFrameworkBuilder::skip_empty`]: crate::FlashMessagesFrameworkBuilder::skip_empty
     pub fn send(self) {
+        self.dispatch(|messages, message| messages.push(message))
+    }
+
+    /// Attach this [`FlashMessage`] to the outgoing request, if possible.
+    ///
+    /// It behaves like [`FlashMessage::send`], but it does not panic if [`FlashMessagesFramework`]
+    /// has not been registered as a middleware - e.g. when called from a spawned task or a
+    /// background job. The message is dropped instead and `false` is returned.  
+    /// A warning is logged as well, if the `tracing` feature flag is enabled.
+    ///
+    /// ```rust
+    /// use actix_web_flash_messages::FlashMessage;
+    ///
+    /// // Outside of a request handler: there is no outgoing response to attach the message to.
+    /// assert!(!FlashMessage::info("Hey there!").try_send());
+    /// ```
+    ///
+    /// It is a no-op, returning `true`, if the `disabled` feature flag is enabled.
+    ///
+    /// [`FlashMessagesFramework`]: crate::FlashMessagesFramework
+    pub fn try_send(self) -> bool {
+        let sent = self.try_dispatch(|messages, message| messages.push(message));
+        #[cfg(feature = "tracing")]
+        if !sent {
+            tracing::warn!(
+                "Dropping a flash message: `FlashMessagesFramework` has not been registered as a middleware"
+            );
+        }
+        sent
+    }
+
+    /// Attach this [`FlashMessage`] to the outgoing request, **ahead** of all messages
+    /// that have already been queued.
+    ///
+    /// Messages queued via [`FlashMessage::send`] are delivered in the order they were sent.
+    /// Messages queued via `send_front` jump the queue instead: the last message queued via
+    /// `send_front` is the first one to be delivered.  
+    /// This is useful for "most important first" experiences - e.g. surfacing a late error
+    /// above the informational messages queued earlier in the request.
+    ///
+    /// The message will be dropped if its [`Level`] is below the minimum level
+    /// specified when configuring [`FlashMessagesFramework`] via [`FlashMessagesFrameworkBuilder::minimum_level`],
+    /// or if it is empty and [`FlashMessagesFrameworkBuilder::skip_empty`] is enabled.
+    ///
+    /// `send_front` is still subject to [`FlashMessagesFrameworkBuilder::max_messages_per_request`]:
+    /// if the budget has already been exhausted, the message you are pushing to the front is the
+    /// one that gets dropped - it does not evict the messages queued earlier to make room.
+    ///
+    /// This method will **panic** if [`FlashMessagesFramework`] has not been registered as a middleware.  
+    /// It is a no-op if the `disabled` feature flag is enabled.
+    ///
+    /// [`FlashMessagesFramework`]: crate::FlashMessagesFramework
+    /// [`FlashMessagesFrameworkBuilder::minimum_level`]: crate::FlashMessagesFrameworkBuilder::minimum_level
+    /// [`FlashMessagesFrameworkBuilder::skip_empty`]: crate::FlashMessagesFrameworkBuilder::skip_empty
+    /// [`FlashMessagesFrameworkBuilder::max_messages_per_request`]: crate::FlashMessagesFrameworkBuilder::max_messages_per_request
+    pub fn send_front(self) {
+        self.dispatch(|messages, message| messages.insert(0, message))
+    }
+
+    /// Attach this [`FlashMessage`] to the outgoing request if `condition` is `true`.
+    ///
+    /// ```rust
+    /// use actix_web::{Responder, HttpResponse, get};
+    /// use actix_web_flash_messages::FlashMessage;
+    ///
+    /// #[get("/login")]
+    /// async fn login() -> impl Responder {
+    ///     let first_login = true; // [...]
+    ///     FlashMessage::info("Welcome aboard!").send_if(first_login);
+    ///     HttpResponse::Ok()
+    /// }
+    /// ```
+    ///
+    /// See [`FlashMessage::send`] for more details.
+    pub fn send_if(self, condition: bool) {
+        if condition {
+            self.send()
+        }
+    }
+
+    /// Build a [`FlashMessage`] out of `value`, using `f`, and attach it to the outgoing request -
+    /// if `value` is `Some`.
+    ///
+    /// ```rust
+    /// use actix_web::{Responder, HttpResponse, get};
+    /// use actix_web_flash_messages::FlashMessage;
+    ///
+    /// #[get("/dashboard")]
+    /// async fn dashboard() -> impl Responder {
+    ///     let expiring_in_days: Option<u32> = Some(3); // [...]
+    ///     FlashMessage::send_if_some(expiring_in_days, |days| {
+    ///         FlashMessage::warning(format!("Your subscription expires in {} days", days))
+    ///     });
+    ///     HttpResponse::Ok()
+    /// }
+    /// ```
+    ///
+    /// See [`FlashMessage::send`] for more details.
+    pub fn send_if_some<T, F>(value: Option<T>, f: F)
+    where
+        F: FnOnce(T) -> FlashMessage,
+    {
+        if let Some(value) = value {
+            f(value).send()
+        }
+    }
+
+    /// Build a [`FlashMessage`] out of the error in `result`, using `f`, and attach it to the
+    /// outgoing request - if `result` is `Err`. The result is returned as is.
+    ///
+    /// ```rust
+    /// use actix_web::{Responder, HttpResponse, get};
+    /// use actix_web_flash_messages::FlashMessage;
+    ///
+    /// #[get("/profile")]
+    /// async fn profile() -> impl Responder {
+    ///     let avatar: Result<Vec<u8>, std::io::Error> = std::fs::read("avatar.png");
+    ///     let avatar = FlashMessage::send_if_err(avatar, |e| {
+    ///         FlashMessage::error(format!("Failed to load your avatar: {}", e))
+    ///     });
+    ///     // [...]
+    ///     HttpResponse::Ok()
+    /// }
+    /// ```
+    ///
+    /// See [`FlashMessage::send`] for more details.
+    pub fn send_if_err<T, E, F>(result: Result<T, E>, f: F) -> Result<T, E>
+    where
+        F: FnOnce(&E) -> FlashMessage,
+    {
+        if let Err(e) = &result {
+            f(e).send()
+        }
+        result
+    }
+
+    /// Replace all the [`FlashMessage`]s queued so far for the outgoing request with `messages`.
+    ///
+    /// ```rust
+    /// use actix_web::{Responder, HttpResponse, post};
+    /// use actix_web_flash_messages::FlashMessage;
+    ///
+    /// #[post("/profile")]
+    /// async fn update_profile() -> impl Responder {
+    ///     FlashMessage::error("The username is already taken").send();
+    ///     let retry_succeeded = true; // [...]
+    ///     if retry_succeeded {
+    ///         // Drop the draft errors.
+    ///         FlashMessage::set_outgoing(vec![FlashMessage::success("Profile updated")]);
+    ///     }
+    ///     HttpResponse::Ok()
+    /// }
+    /// ```
+    ///
+    /// `messages` go through the same checks as [`FlashMessage::send`]: a message is dropped if
+    /// its [`Level`] is below the minimum level or if it is empty and
+    /// [`FlashMessagesFrameworkBuilder::skip_empty`] is enabled.  
+    /// Pass an empty `Vec` to discard all outgoing messages.
+    ///
+    /// This method will **panic** if [`FlashMessagesFramework`] has not been registered as a middleware.  
+    /// It is a no-op if the `disabled` feature flag is enabled.
+    ///
+    /// [`FlashMessagesFramework`]: crate::FlashMessagesFramework
+    /// [`FlashMessagesFrameworkBuilder::skip_empty`]: crate::FlashMessagesFrameworkBuilder::skip_empty
+    pub fn set_outgoing(messages: Vec<FlashMessage>) {
+        if cfg!(feature = "disabled") {
+            return;
+        }
         let result = OUTGOING_MAILBOX.try_with(|mailbox| {
-            if self.level as u8 >= mailbox.minimum_level as u8 {
-                mailbox.messages.borrow_mut().push(self);
+            let mut outgoing = mailbox.messages.borrow_mut();
+            outgoing.clear();
+            for message in messages.into_iter().filter_map(|m| mailbox.admit(m)) {
+                if mailbox.has_room(outgoing.len()) {
+                    outgoing.push(message);
+                }
             }
         });
 
         if result.is_err() {
+            panic!("Failed to set outgoing flash messages!\n\
+                To use `FlashMessage::set_outgoing` you need to add `FlashMessageFramework` as a middleware \
+                on your `actix-web` application using `wrap`. Check out `actix-web-flash-messages`'s documentation for more details.")
+        }
+    }
+
+    fn dispatch<F: FnOnce(&mut Vec<FlashMessage>, FlashMessage)>(self, enqueue: F) {
+        if !self.try_dispatch(enqueue) {
             panic!("Failed to send flash message!\n\
                 To use `FlashMessages::send` you need to add `FlashMessageFramework` as a middleware \
                 on your `actix-web` application using `wrap`. Check out `actix-web-flash-messages`'s documentation for more details.")
         }
     }
+
+    /// Returns `false` if [`FlashMessagesFramework`](crate::FlashMessagesFramework) has not been
+    /// registered as a middleware.
+    fn try_dispatch<F: FnOnce(&mut Vec<FlashMessage>, FlashMessage)>(self, enqueue: F) -> bool {
+        if cfg!(feature = "disabled") {
+            return true;
+        }
+        OUTGOING_MAILBOX
+            .try_with(|mailbox| {
+                if let Some(message) = mailbox.admit(self) {
+                    let mut outgoing = mailbox.messages.borrow_mut();
+                    if mailbox.has_room(outgoing.len()) {
+                        enqueue(&mut outgoing, message);
+                    }
+                }
+            })
+            .is_ok()
+    }
 }
 
-#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, PartialEq, PartialOrd, Eq)]
+/// A fluent builder to construct a [`FlashMessage`] instance.
+///
+/// Use [`FlashMessage::builder`] to get started.  
+/// By default, the message content is empty and its level is [`Level::Info`].
+#[must_use = "You must call `.build()` or `.send()` on a `FlashMessageBuilder` for it to have an effect"]
+pub struct FlashMessageBuilder {
+    message: FlashMessage,
+}
+
+impl FlashMessageBuilder {
+    /// Set the string content of the message.
+    pub fn content<S: Into<String>>(mut self, content: S) -> Self {
+        self.message.content = Cow::Owned(content.into());
+        self
+    }
+
+    /// Set the [`Level`] of the message.
+    pub fn level(mut self, level: Level) -> Self {
+        self.message.level = level;
+        self
+    }
+
+    /// Set the title of the message.
+    pub fn title<S: Into<String>>(mut self, title: S) -> Self {
+        self.message.title = Some(title.into());
+        self
+    }
+
+    /// Send the message to a named channel - e.g. `"banner"`.
+    ///
+    /// See [`FlashMessage::channel`] for more details.
+    pub fn channel<S: Into<String>>(mut self, channel: S) -> Self {
+        self.message.channel = Some(channel.into());
+        self
+    }
+
+    /// Set an absolute expiry time for the message, as a unix timestamp in seconds.
+    ///
+    /// See [`FlashMessage::with_expires_at`] for more details.
+    pub fn expires_at(mut self, expires_at: u64) -> Self {
+        self.message.expires_at = Some(expires_at);
+        self
+    }
+
+    /// Attach a string key-value pair to the message.
+    ///
+    /// If the key was already set, its value is overwritten.
+    pub fn metadata<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        self.message.metadata.insert(key.into(), value.into());
+        self
+    }
+
+    /// Finalise the builder and return a [`FlashMessage`] instance.
+    pub fn build(self) -> FlashMessage {
+        self.message
+    }
+
+    /// Finalise the builder and send the resulting [`FlashMessage`].
+    ///
+    /// It is a shortcut for `.build().send()` - see [`FlashMessage::send`] for more details.
+    pub fn send(self) {
+        self.build().send()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, PartialOrd, Eq, Ord, Hash)]
 /// The severity level of a [`FlashMessage`].
 ///
 /// Levels can be used for filtering and rendering - for example:
 ///
 /// - Only show flash messages at `info` level or above in a production environment, while retaining `debug` level messages for local development;
 /// - Use different colours, in the UI, to display messages (e.g. red for errors, orange for warnings, etc.).
+///
+/// # Serialization
+///
+/// By default, [`Level`] is serialized using its lowercase name (e.g. `"error"`).  
+/// If you enable the `numeric-levels` feature flag, it is serialized as its numeric severity
+/// instead (e.g. `4`) - useful if your frontend sorts messages by severity.
+///
+/// Deserialization accepts capitalised names (e.g. `"Error"`) as well, the representation used by
+/// earlier versions.  
+/// Numeric severities are only accepted if the `numeric-levels` feature flag is enabled: accepting
+/// both forms requires a self-describing format (e.g. JSON), while names can be deserialized from
+/// any format - including non-self-describing ones like `bincode` or `postcard`.
 pub enum Level {
     /// Development-related messages. Often ignored in a production environment.
     Debug = 0,
@@ -118,6 +758,46 @@ pub enum Level {
     Error = 4,
 }
 
+impl Level {
+    /// Returns `true` if this level is as severe as `other`, or more severe.
+    ///
+    /// ```rust
+    /// use actix_web_flash_messages::Level;
+    ///
+    /// assert!(Level::Error.is_at_least(Level::Warning));
+    /// assert!(Level::Info.is_at_least(Level::Info));
+    /// assert!(!Level::Debug.is_at_least(Level::Info));
+    /// ```
+    pub fn is_at_least(&self, other: Level) -> bool {
+        *self as u8 >= other as u8
+    }
+
+    /// Returns `true` if this level is less severe than `other`.
+    ///
+    /// It is the opposite of [`Level::is_at_least`].
+    pub fn is_below(&self, other: Level) -> bool {
+        !self.is_at_least(other)
+    }
+
+    /// An emoji representing this level - e.g. to prefix messages on surfaces without styling
+    /// (plain text, terminals, chat messages).
+    ///
+    /// ```rust
+    /// use actix_web_flash_messages::Level;
+    ///
+    /// assert_eq!(Level::Error.emoji(), "❌");
+    /// ```
+    pub fn emoji(&self) -> &'static str {
+        match self {
+            Level::Debug => "🐛",
+            Level::Info => "ℹ️",
+            Level::Success => "✅",
+            Level::Warning => "⚠️",
+            Level::Error => "❌",
+        }
+    }
+}
+
 impl Debug for Level {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", level_to_str(self))
@@ -130,6 +810,119 @@ impl Display for Level {
     }
 }
 
+/// Map a flash message [`Level`] to the corresponding [`tracing::Level`] - e.g. to echo
+/// user-facing messages into your logs with a matching severity.
+///
+/// [`Level::Success`] maps to [`tracing::Level::INFO`].
+///
+/// ```rust
+/// use actix_web_flash_messages::FlashMessage;
+///
+/// let message = FlashMessage::warning("Your password expires in 3 days.");
+/// let level: tracing::Level = message.level().into();
+/// assert_eq!(level, tracing::Level::WARN);
+/// ```
+#[cfg(feature = "tracing")]
+impl From<Level> for tracing::Level {
+    fn from(level: Level) -> Self {
+        match level {
+            Level::Debug => tracing::Level::DEBUG,
+            Level::Info | Level::Success => tracing::Level::INFO,
+            Level::Warning => tracing::Level::WARN,
+            Level::Error => tracing::Level::ERROR,
+        }
+    }
+}
+
+impl serde::Serialize for Level {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if cfg!(feature = "numeric-levels") {
+            serializer.serialize_u8(*self as u8)
+        } else {
+            serializer.serialize_unit_variant("Level", *self as u32, level_to_str(self))
+        }
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Level {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if cfg!(feature = "numeric-levels") {
+            deserializer.deserialize_any(LevelVisitor)
+        } else {
+            deserializer.deserialize_enum("Level", LEVEL_NAMES, LevelVisitor)
+        }
+    }
+}
+
+const LEVEL_NAMES: &[&str] = &["debug", "info", "success", "warning", "error"];
+
+/// The variant of a [`Level`] deserialized as an enum - either its name or its index, depending
+/// on the format.
+struct LevelVariant(Level);
+
+impl<'de> serde::Deserialize<'de> for LevelVariant {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer
+            .deserialize_identifier(LevelVisitor)
+            .map(LevelVariant)
+    }
+}
+
+struct LevelVisitor;
+
+impl<'de> serde::de::Visitor<'de> for LevelVisitor {
+    type Value = Level;
+
+    fn expecting(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "a level name (e.g. \"Error\") or a numeric severity between 0 and 4"
+        )
+    }
+
+    fn visit_u64<E: serde::de::Error>(self, v: u64) -> Result<Self::Value, E> {
+        match v {
+            0 => Ok(Level::Debug),
+            1 => Ok(Level::Info),
+            2 => Ok(Level::Success),
+            3 => Ok(Level::Warning),
+            4 => Ok(Level::Error),
+            _ => Err(E::invalid_value(serde::de::Unexpected::Unsigned(v), &self)),
+        }
+    }
+
+    fn visit_i64<E: serde::de::Error>(self, v: i64) -> Result<Self::Value, E> {
+        if v < 0 {
+            return Err(E::invalid_value(serde::de::Unexpected::Signed(v), &self));
+        }
+        self.visit_u64(v as u64)
+    }
+
+    fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        level_from_str(v).ok_or_else(|| E::invalid_value(serde::de::Unexpected::Str(v), &self))
+    }
+
+    fn visit_enum<A: serde::de::EnumAccess<'de>>(self, data: A) -> Result<Self::Value, A::Error> {
+        use serde::de::VariantAccess;
+
+        let (LevelVariant(level), variant) = data.variant()?;
+        variant.unit_variant()?;
+        Ok(level)
+    }
+}
+
+/// Parse a [`Level`] from either its variant name (e.g. `Error`) or its lowercase name (e.g. `error`).
+pub(crate) fn level_from_str(s: &str) -> Option<Level> {
+    match s {
+        "Debug" | "debug" => Some(Level::Debug),
+        "Info" | "info" => Some(Level::Info),
+        "Success" | "success" => Some(Level::Success),
+        "Warning" | "warning" => Some(Level::Warning),
+        "Error" | "error" => Some(Level::Error),
+        _ => None,
+    }
+}
+
 fn level_to_str(l: &Level) -> &'static str {
     match l {
         Level::Debug => "debug",
@@ -0,0 +1,123 @@
+use crate::middleware::OutgoingMailbox;
+use crate::{FlashMessage, Level};
+use actix_web::{FromRequest, HttpMessage, HttpRequest};
+
+/// An explicit handle to send [`FlashMessage`]s, as an alternative to [`FlashMessage::send`].
+///
+/// [`FlashScope`] is an `actix-web` extractor: it accumulates messages and dispatches them
+/// when it is dropped or when [`FlashScope::commit`] is called.
+///
+/// ```rust
+/// use actix_web::{Responder, HttpResponse, get};
+/// use actix_web_flash_messages::FlashScope;
+///
+/// #[get("/set")]
+/// async fn set(mut flash: FlashScope) -> impl Responder {
+///     flash.info("Hey there!");
+///     flash.debug("How is it going?");
+///     flash.commit();
+///     HttpResponse::Ok()
+/// }
+/// ```
+///
+/// [`FlashScope`] shares its mailbox with [`FlashMessage::send`]: you can mix and match the two APIs
+/// in the same request. Messages are dispatched in the order they reach the mailbox - i.e. messages
+/// accumulated in a [`FlashScope`] are queued after the messages sent via [`FlashMessage::send`]
+/// before the scope is committed.  
+/// The minimum level configured via [`FlashMessagesFrameworkBuilder::minimum_level`] applies to both,
+/// as well as [`FlashMessagesFrameworkBuilder::skip_empty`].
+///
+/// Extracting [`FlashScope`] will **panic** if [`FlashMessagesFramework`] has not been registered as a middleware.
+///
+/// [`FlashMessagesFramework`]: crate::FlashMessagesFramework
+/// [`FlashMessagesFrameworkBuilder::minimum_level`]: crate::FlashMessagesFrameworkBuilder::minimum_level
+/// [`FlashMessagesFrameworkBuilder::skip_empty`]: crate::FlashMessagesFrameworkBuilder::skip_empty
+pub struct FlashScope {
+    mailbox: OutgoingMailbox,
+    pending: Vec<FlashMessage>,
+}
+
+impl FlashScope {
+    /// Add a [`FlashMessage`] to this scope.
+    pub fn push(&mut self, message: FlashMessage) {
+        self.pending.push(message);
+    }
+
+    /// Add an info-level [`FlashMessage`] to this scope.
+    pub fn info<S: Into<String>>(&mut self, content: S) {
+        self.push(FlashMessage::info(content))
+    }
+
+    /// Add a debug-level [`FlashMessage`] to this scope.
+    pub fn debug<S: Into<String>>(&mut self, content: S) {
+        self.push(FlashMessage::debug(content))
+    }
+
+    /// Add a success-level [`FlashMessage`] to this scope.
+    pub fn success<S: Into<String>>(&mut self, content: S) {
+        self.push(FlashMessage::success(content))
+    }
+
+    /// Add a warning-level [`FlashMessage`] to this scope.
+    pub fn warning<S: Into<String>>(&mut self, content: S) {
+        self.push(FlashMessage::warning(content))
+    }
+
+    /// Add an error-level [`FlashMessage`] to this scope.
+    pub fn error<S: Into<String>>(&mut self, content: S) {
+        self.push(FlashMessage::error(content))
+    }
+
+    /// The messages accumulated in this scope that have not been dispatched yet.
+    pub fn pending(&self) -> &[FlashMessage] {
+        &self.pending
+    }
+
+    /// Dispatch the accumulated messages.
+    ///
+    /// Messages whose [`Level`] is below the configured minimum level are dropped.
+    ///
+    /// [`Level`]: crate::Level
+    pub fn commit(mut self) {
+        self.flush();
+    }
+
+    fn flush(&mut self) {
+        let mailbox = &self.mailbox;
+        let mut outgoing = mailbox.messages.borrow_mut();
+        for message in self.pending.drain(..).filter_map(|m| mailbox.admit(m)) {
+            if mailbox.has_room(outgoing.len()) {
+                outgoing.push(message);
+            }
+        }
+    }
+}
+
+impl Drop for FlashScope {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+impl FromRequest for FlashScope {
+    type Error = actix_web::Error;
+    type Future = std::future::Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut actix_web::dev::Payload) -> Self::Future {
+        let mailbox = if cfg!(feature = "disabled") {
+            // Messages pushed to a detached mailbox are never stored.
+            OutgoingMailbox::new(Level::Debug, false, None, None, 0, None, 0)
+        } else {
+            req.extensions()
+                .get::<OutgoingMailbox>()
+                .expect("Failed to build a flash scope!\n\
+                    To use the `FlashScope` extractor you need to add `FlashMessageFramework` as a middleware \
+                    on your `actix-web` application using `wrap`. Check out `actix-web-flash-messages`'s documentation for more details.")
+                .clone()
+        };
+        std::future::ready(Ok(FlashScope {
+            mailbox,
+            pending: vec![],
+        }))
+    }
+}
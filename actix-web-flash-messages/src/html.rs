@@ -0,0 +1,148 @@
+use crate::{FlashMessage, IncomingFlashMessages};
+use std::fmt::Write;
+
+/// Render [`FlashMessage`]s as accessible HTML markup.
+///
+/// Each message is rendered as a separate element, announced to assistive technologies via
+/// `role="alert"`, with its [`Level`](crate::Level) as a CSS class:
+///
+/// ```rust
+/// use actix_web_flash_messages::{FlashMessage, HtmlRenderer};
+///
+/// let renderer = HtmlRenderer::new()
+///     .element("p")
+///     .class_prefix("alert-")
+///     .dismissible(true);
+/// assert_eq!(
+///     renderer.render_message(&FlashMessage::error("<b>Oops</b>")),
+///     "<p role=\"alert\" class=\"alert-error\">&lt;b&gt;Oops&lt;/b&gt;\
+///     <button type=\"button\" class=\"alert-dismiss\" aria-label=\"Dismiss\">&times;</button></p>"
+/// );
+/// ```
+///
+/// The title and the content of each message are HTML-escaped: it is safe to render messages
+/// that embed user input.
+/// The renderer configured via [`FlashMessagesFrameworkBuilder::renderer`] is **not** applied -
+/// use [`IncomingFlashMessages::rendered_iter`] if your renderer produces trusted markup.
+///
+/// It is only available if the `html` feature flag is enabled.
+///
+/// [`FlashMessagesFrameworkBuilder::renderer`]: crate::FlashMessagesFrameworkBuilder::renderer
+#[derive(Clone, Debug)]
+pub struct HtmlRenderer {
+    element: String,
+    class_prefix: String,
+    dismissible: bool,
+}
+
+impl HtmlRenderer {
+    /// Build a new [`HtmlRenderer`] with the default configuration.
+    pub fn new() -> Self {
+        Self {
+            element: "div".into(),
+            class_prefix: "flash-".into(),
+            dismissible: false,
+        }
+    }
+
+    /// By default, each message is rendered as a `<div>`.
+    /// Use `element` to pick a different HTML element - e.g. `p` or `li`.
+    ///
+    /// This method will **panic** if `element` is not a valid HTML element name - i.e. if it is
+    /// empty, does not start with an ASCII letter or contains characters other than ASCII letters,
+    /// digits and `-`.
+    pub fn element<S: Into<String>>(mut self, element: S) -> Self {
+        let element = element.into();
+        assert!(
+            element.starts_with(|c: char| c.is_ascii_alphabetic())
+                && element
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-'),
+            "Invalid HTML element name, `{}`: it must start with an ASCII letter and only contain \
+            ASCII letters, digits and `-`.",
+            element
+        );
+        self.element = element;
+        self
+    }
+
+    /// By default, CSS classes are prefixed with `flash-` - e.g. `flash-error` for an error
+    /// message.
+    /// Use `class_prefix` to match the conventions of your CSS framework - e.g. `alert-`.
+    pub fn class_prefix<S: Into<String>>(mut self, class_prefix: S) -> Self {
+        self.class_prefix = class_prefix.into();
+        self
+    }
+
+    /// By default, messages are rendered without a dismiss button.
+    ///
+    /// If you enable `dismissible`, a `<button>` with the `<prefix>dismiss` class is appended to
+    /// each message. Wiring it up (e.g. hiding the message on click) is up to your frontend.
+    pub fn dismissible(mut self, dismissible: bool) -> Self {
+        self.dismissible = dismissible;
+        self
+    }
+
+    /// Render all incoming messages, one per line.
+    pub fn render(&self, messages: &IncomingFlashMessages) -> String {
+        messages
+            .iter()
+            .map(|message| self.render_message(message))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Render a single message.
+    pub fn render_message(&self, message: &FlashMessage) -> String {
+        let class_prefix = escape(&self.class_prefix);
+        let mut html = String::new();
+        // Writing to a `String` never fails.
+        let _ = write!(
+            html,
+            "<{} role=\"alert\" class=\"{}{}\">",
+            self.element,
+            class_prefix,
+            message.level_str()
+        );
+        if let Some(title) = message.title() {
+            let _ = write!(
+                html,
+                "<strong class=\"{}title\">{}</strong> ",
+                class_prefix,
+                escape(title)
+            );
+        }
+        html.push_str(&escape(message.content()));
+        if self.dismissible {
+            let _ = write!(
+                html,
+                "<button type=\"button\" class=\"{}dismiss\" aria-label=\"Dismiss\">&times;</button>",
+                class_prefix
+            );
+        }
+        let _ = write!(html, "</{}>", self.element);
+        html
+    }
+}
+
+impl Default for HtmlRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Escape the characters that are significant in HTML text and attribute values.
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#x27;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
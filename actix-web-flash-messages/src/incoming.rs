@@ -1,7 +1,11 @@
-use crate::{storage::FlashMessageStore, FlashMessage};
+use crate::builder::{MessageRenderer, OnLoad};
+use crate::middleware::{OutgoingMailbox, OUTGOING_MAILBOX};
+use crate::storage::{FlashMessageStore, LoadError, LoadOutcome};
+use crate::{FlashMessage, Level};
 use actix_web::http::StatusCode;
 use actix_web::HttpMessage;
 use actix_web::{FromRequest, HttpRequest};
+use std::collections::{BTreeMap, HashSet};
 use std::sync::Arc;
 
 #[derive(serde::Deserialize, serde::Serialize)]
@@ -20,11 +24,24 @@ use std::sync::Arc;
 /// }
 /// ```
 ///
+/// Incoming flash messages are loaded **lazily**: [`FlashMessageStore::load`] is only invoked
+/// if a handler (or another extractor) actually asks for [`IncomingFlashMessages`] - requests
+/// that never extract them (e.g. static assets) do not pay the loading cost.  
+/// The loaded messages are cached for the lifetime of the request: extracting
+/// [`IncomingFlashMessages`] multiple times only hits the store once.  
+/// One-time semantics are unaffected - previous messages are still cleared when outgoing
+/// messages are flushed at the end of the request.
+///
+/// [`IncomingFlashMessages`] serializes as a bare array of [`FlashMessage`]s.
+///
 /// This method will **panic** if [`FlashMessagesFramework`] has not been registered as a middleware.
 ///
 /// [`FlashMessagesFramework`]: crate::FlashMessagesFramework
+#[serde(transparent)]
 pub struct IncomingFlashMessages {
     messages: Vec<FlashMessage>,
+    #[serde(skip)]
+    renderer: Option<MessageRenderer>,
 }
 
 impl IncomingFlashMessages {
@@ -32,6 +49,280 @@ impl IncomingFlashMessages {
     pub fn iter(&self) -> impl ExactSizeIterator<Item = &FlashMessage> {
         self.messages.iter()
     }
+
+    /// Return an iterator over the incoming [`FlashMessage`]s that were sent to the `name` channel.
+    ///
+    /// Messages that were not sent to a specific channel are returned for
+    /// [`FlashMessage::DEFAULT_CHANNEL`].
+    ///
+    /// ```rust
+    /// use actix_web::{Responder, HttpResponse, get};
+    /// use actix_web_flash_messages::IncomingFlashMessages;
+    ///
+    /// #[get("/show")]
+    /// async fn show(messages: IncomingFlashMessages) -> impl Responder {
+    ///     for message in messages.channel("banner") {
+    ///         println!("[banner] {}", message.content());
+    ///     }
+    ///     for message in messages.channel("form-errors") {
+    ///         println!("[form] {}", message.content());
+    ///     }
+    ///     HttpResponse::Ok()
+    /// }
+    /// ```
+    pub fn channel<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a FlashMessage> {
+        self.messages
+            .iter()
+            .filter(move |m| m.channel().unwrap_or(FlashMessage::DEFAULT_CHANNEL) == name)
+    }
+
+    /// Return the incoming [`FlashMessage`]s sorted by severity, most severe first - e.g. errors
+    /// before warnings.
+    ///
+    /// The sort is stable: messages with the same [`Level`] retain the order they were sent in.
+    ///
+    /// ```rust
+    /// use actix_web::{Responder, HttpResponse, get};
+    /// use actix_web_flash_messages::IncomingFlashMessages;
+    ///
+    /// #[get("/show")]
+    /// async fn show(messages: IncomingFlashMessages) -> impl Responder {
+    ///     for message in messages.sorted_by_severity() {
+    ///         println!("{} - {}", message.content(), message.level());
+    ///     }
+    ///     HttpResponse::Ok()
+    /// }
+    /// ```
+    pub fn sorted_by_severity(&self) -> Vec<&FlashMessage> {
+        let mut messages: Vec<_> = self.messages.iter().collect();
+        messages.sort_by_key(|m| std::cmp::Reverse(m.level()));
+        messages
+    }
+
+    /// Render each incoming [`FlashMessage`] using `f`.
+    ///
+    /// ```rust
+    /// use actix_web::{Responder, HttpResponse, get};
+    /// use actix_web_flash_messages::IncomingFlashMessages;
+    ///
+    /// #[get("/show")]
+    /// async fn show(messages: IncomingFlashMessages) -> impl Responder {
+    ///     let rendered = messages.render_with(|m| format!("<p class=\"{}\">{}</p>", m.level(), m.content()));
+    ///     HttpResponse::Ok().body(rendered.join("\n"))
+    /// }
+    /// ```
+    pub fn render_with<F: Fn(&FlashMessage) -> String>(&self, f: F) -> Vec<String> {
+        self.messages.iter().map(f).collect()
+    }
+
+    /// Return an iterator over incoming [`FlashMessage`]s, rendered using the default renderer
+    /// configured via [`FlashMessagesFrameworkBuilder::renderer`].
+    ///
+    /// If no renderer has been configured, it yields the content of each message as is.
+    ///
+    /// [`FlashMessagesFrameworkBuilder::renderer`]: crate::FlashMessagesFrameworkBuilder::renderer
+    pub fn rendered_iter(&self) -> impl ExactSizeIterator<Item = String> + '_ {
+        self.messages.iter().map(move |m| match &self.renderer {
+            Some(MessageRenderer(renderer)) => renderer(m),
+            None => m.content().to_owned(),
+        })
+    }
+
+    /// Render incoming [`FlashMessage`]s as accessible HTML markup - one `<div role="alert">` per
+    /// message, with the level as a CSS class and HTML-escaped content:
+    ///
+    /// ```rust
+    /// use actix_web::{Responder, HttpResponse, get};
+    /// use actix_web_flash_messages::IncomingFlashMessages;
+    ///
+    /// #[get("/show")]
+    /// async fn show(messages: IncomingFlashMessages) -> impl Responder {
+    ///     // E.g. `<div role="alert" class="flash-info">Hey there!</div>`
+    ///     HttpResponse::Ok().body(messages.render_html())
+    /// }
+    /// ```
+    ///
+    /// Use [`HtmlRenderer`](crate::HtmlRenderer) to customise the markup - e.g. the element or
+    /// the prefix of the CSS classes.
+    ///
+    /// It is only available if the `html` feature flag is enabled.
+    #[cfg(feature = "html")]
+    pub fn render_html(&self) -> String {
+        crate::HtmlRenderer::default().render(self)
+    }
+
+    /// Take ownership of the incoming [`FlashMessage`]s - e.g. to move them into a response.
+    ///
+    /// Incoming messages are marked as read as soon as [`IncomingFlashMessages`] is extracted:
+    /// taking them has no additional effect on their storage - they are cleared once, on the
+    /// outgoing response.
+    pub fn take(self) -> Vec<FlashMessage> {
+        self.messages
+    }
+
+    /// Send all incoming [`FlashMessage`]s again, appending them to the outgoing messages - e.g.
+    /// to persist form errors across another redirect:
+    ///
+    /// ```rust
+    /// use actix_web::{Responder, HttpResponse, get};
+    /// use actix_web_flash_messages::IncomingFlashMessages;
+    ///
+    /// #[get("/form")]
+    /// async fn form(messages: IncomingFlashMessages) -> impl Responder {
+    ///     let must_log_in = true; // [...]
+    ///     if must_log_in {
+    ///         // The errors will be shown once the user comes back to the form.
+    ///         messages.resend();
+    ///         return HttpResponse::SeeOther()
+    ///             .insert_header(("Location", "/login"))
+    ///             .finish();
+    ///     }
+    ///     // [...] Render the form with `messages`
+    ///     HttpResponse::Ok().finish()
+    /// }
+    /// ```
+    ///
+    /// Reading incoming messages never touches the outgoing mailbox: incoming messages are only
+    /// delivered again if you explicitly re-send them, exactly once per call.  
+    /// Messages are re-sent as they were received: the minimum level, the content prefix and
+    /// the truncation configured on [`FlashMessagesFramework`] are not applied a second time.
+    ///
+    /// This method will **panic** if [`FlashMessagesFramework`] has not been registered as a middleware.
+    ///
+    /// [`FlashMessagesFramework`]: crate::FlashMessagesFramework
+    pub fn resend(self) {
+        if cfg!(feature = "disabled") {
+            return;
+        }
+        let result = OUTGOING_MAILBOX.try_with(|mailbox| {
+            let mut outgoing = mailbox.messages.borrow_mut();
+            for message in self.messages {
+                if mailbox.has_room(outgoing.len()) {
+                    outgoing.push(message);
+                }
+            }
+        });
+
+        if result.is_err() {
+            panic!("Failed to re-send incoming flash messages!\n\
+                To use `IncomingFlashMessages::resend` you need to add `FlashMessageFramework` as a middleware \
+                on your `actix-web` application using `wrap`. Check out `actix-web-flash-messages`'s documentation for more details.")
+        }
+    }
+
+    /// Move all incoming [`FlashMessage`]s into an existing collection, without cloning them.
+    ///
+    /// It comes in handy when populating a strongly-typed template context:
+    ///
+    /// ```rust
+    /// use actix_web::{Responder, HttpResponse, get};
+    /// use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+    ///
+    /// struct TemplateContext {
+    ///     flash: Vec<FlashMessage>,
+    /// }
+    ///
+    /// #[get("/show")]
+    /// async fn show(messages: IncomingFlashMessages) -> impl Responder {
+    ///     let mut ctx = TemplateContext { flash: vec![] };
+    ///     messages.drain_into(&mut ctx.flash);
+    ///     // [...] Render your template using `ctx`
+    ///     HttpResponse::Ok()
+    /// }
+    /// ```
+    pub fn drain_into<C: Extend<FlashMessage>>(self, collection: &mut C) {
+        collection.extend(self.messages)
+    }
+
+    /// Combine two sets of incoming [`FlashMessage`]s - e.g. loaded from different stores - for
+    /// unified rendering.
+    ///
+    /// The messages in `other` follow the messages in `self`. Call
+    /// [`IncomingFlashMessages::dedup`] on the result to drop duplicates.
+    pub fn merge(mut self, other: IncomingFlashMessages) -> Self {
+        self.messages.extend(other.messages);
+        Self {
+            messages: self.messages,
+            renderer: self.renderer.or(other.renderer),
+        }
+    }
+
+    /// Remove duplicate messages - i.e. with the same content and [`Level`] - keeping the first
+    /// occurrence of each.
+    ///
+    /// Duplicates can slip in when messages are merged from several sources - e.g. via
+    /// [`TeeStore`](crate::storage::TeeStore) or legacy cookies.
+    ///
+    /// ```rust
+    /// use actix_web::{Responder, HttpResponse, get};
+    /// use actix_web_flash_messages::IncomingFlashMessages;
+    ///
+    /// #[get("/show")]
+    /// async fn show(messages: IncomingFlashMessages) -> impl Responder {
+    ///     for message in messages.dedup().iter() {
+    ///         println!("{} - {}", message.content(), message.level());
+    ///     }
+    ///     HttpResponse::Ok()
+    /// }
+    /// ```
+    pub fn dedup(self) -> Self {
+        let mut seen = HashSet::new();
+        let messages = self
+            .messages
+            .into_iter()
+            .filter(|m| seen.insert((m.content().to_owned(), m.level())))
+            .collect();
+        Self {
+            messages,
+            renderer: self.renderer,
+        }
+    }
+
+    /// Summarise the incoming [`FlashMessage`]s - how many there are, per [`Level`] and overall.
+    ///
+    /// [`FlashSummary`] implements `Serialize`: it can be returned as JSON straight away - e.g.
+    /// from a debugging endpoint.
+    ///
+    /// ```rust
+    /// use actix_web::{HttpResponse, get};
+    /// use actix_web_flash_messages::IncomingFlashMessages;
+    ///
+    /// #[get("/metrics/flash")]
+    /// async fn flash_metrics(messages: IncomingFlashMessages) -> HttpResponse {
+    ///     HttpResponse::Ok().json(messages.summary())
+    /// }
+    /// ```
+    pub fn summary(&self) -> FlashSummary {
+        let mut by_level = BTreeMap::new();
+        for message in &self.messages {
+            *by_level.entry(message.level()).or_insert(0) += 1;
+        }
+        FlashSummary {
+            total: self.messages.len(),
+            max_level: by_level.keys().next_back().copied(),
+            by_level,
+        }
+    }
+}
+
+/// An overview of a set of incoming flash messages, returned by [`IncomingFlashMessages::summary`].
+#[derive(serde::Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct FlashSummary {
+    /// The total number of messages.
+    pub total: usize,
+    /// The number of messages for each [`Level`]. Levels without messages are omitted.
+    pub by_level: BTreeMap<Level, usize>,
+    /// The most severe [`Level`] across all messages - `None` if there are no messages.
+    pub max_level: Option<Level>,
+}
+
+impl IntoIterator for IncomingFlashMessages {
+    type Item = FlashMessage;
+    type IntoIter = std::vec::IntoIter<FlashMessage>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.messages.into_iter()
+    }
 }
 
 impl FromRequest for IncomingFlashMessages {
@@ -39,11 +330,267 @@ impl FromRequest for IncomingFlashMessages {
     type Future = std::future::Ready<Result<Self, Self::Error>>;
 
     fn from_request(req: &HttpRequest, _: &mut actix_web::dev::Payload) -> Self::Future {
-        std::future::ready(extract_flash_messages(req))
+        let renderer = req.extensions().get::<MessageRenderer>().cloned();
+        std::future::ready(
+            load_and_convert_errors(req, true)
+                .map(|messages| IncomingFlashMessages { messages, renderer }),
+        )
     }
 }
 
-fn extract_flash_messages(req: &HttpRequest) -> Result<IncomingFlashMessages, actix_web::Error> {
+/// An `actix-web` extractor to retrieve [`FlashMessage`]s attached to an incoming request,
+/// alongside the non-fatal failures encountered while loading them.
+///
+/// [`IncomingFlashMessages`] returns a `400 Bad Request` if any of the incoming messages cannot
+/// be loaded. [`LenientFlashMessages`] relies on [`FlashMessageStore::load_lenient`] instead:
+/// the request handler gets the messages that were loaded successfully and can log the rest.
+///
+/// ```rust
+/// use actix_web::{Responder, HttpResponse, get};
+/// use actix_web_flash_messages::LenientFlashMessages;
+///
+/// #[get("/show")]
+/// async fn show(incoming: LenientFlashMessages) -> impl Responder {
+///     for warning in &incoming.warnings {
+///         tracing::warn!(error = ?warning, "Failed to load some incoming flash messages");
+///     }
+///     let mut body = String::new();
+///     for message in incoming.messages.iter() {
+///         body.push_str(&format!("{}\n", message.content()));
+///     }
+///     HttpResponse::Ok().body(body)
+/// }
+/// ```
+///
+/// Failures that prevent loading any message at all still result in a `400 Bad Request`.  
+/// Warnings are only reported by the extractor that hits the store: if incoming messages have
+/// already been loaded for the current request (e.g. via [`FlashMessageCount`]), `warnings` is
+/// empty.
+///
+/// Messages that failed to load are not carried over: when the response is flushed, the built-in
+/// stores overwrite them with the outgoing messages or clear them if there are none - e.g.
+/// [`CookieMessageStore`] sends a removal cookie for a flash cookie signed with a stale key. They
+/// won't keep failing on every request.
+///
+/// This method will **panic** if [`FlashMessagesFramework`] has not been registered as a middleware.
+///
+/// [`FlashMessagesFramework`]: crate::FlashMessagesFramework
+/// [`CookieMessageStore`]: crate::storage::CookieMessageStore
+pub struct LenientFlashMessages {
+    /// The incoming messages that were loaded successfully.
+    pub messages: IncomingFlashMessages,
+    /// The failures encountered while loading incoming messages - e.g. the signed flash cookie
+    /// failing to decode. They do not fail the request.
+    pub warnings: Vec<LoadError>,
+}
+
+impl FromRequest for LenientFlashMessages {
+    type Error = actix_web::Error;
+    type Future = std::future::Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut actix_web::dev::Payload) -> Self::Future {
+        let renderer = req.extensions().get::<MessageRenderer>().cloned();
+        std::future::ready(
+            try_load_flash_messages_with(req, true, true)
+                .map(|outcome| LenientFlashMessages {
+                    messages: IncomingFlashMessages {
+                        messages: outcome.messages,
+                        renderer,
+                    },
+                    warnings: outcome.warnings,
+                })
+                .map_err(convert_load_error),
+        )
+    }
+}
+
+/// An `actix-web` extractor to retrieve the number of [`FlashMessage`]s attached to an incoming
+/// request **without** consuming them.
+///
+/// It comes in handy to render a badge (e.g. in a navigation bar) on every page:
+///
+/// ```rust
+/// use actix_web::{Responder, HttpResponse, get};
+/// use actix_web_flash_messages::FlashMessageCount;
+///
+/// #[get("/badge")]
+/// async fn badge(FlashMessageCount(count): FlashMessageCount) -> impl Responder {
+///     HttpResponse::Ok().body(format!("You have {} unread notifications", count))
+/// }
+/// ```
+///
+/// Peeking does not count as reading: if a request extracts [`FlashMessageCount`] but does not
+/// extract [`IncomingFlashMessages`], incoming messages are carried over to the outgoing response
+/// instead of being cleared - they will still be available to the next request.
+///
+/// This method will **panic** if [`FlashMessagesFramework`] has not been registered as a middleware.
+///
+/// [`FlashMessagesFramework`]: crate::FlashMessagesFramework
+pub struct FlashMessageCount(pub usize);
+
+impl FromRequest for FlashMessageCount {
+    type Error = actix_web::Error;
+    type Future = std::future::Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut actix_web::dev::Payload) -> Self::Future {
+        std::future::ready(load_and_convert_errors(req, false).map(|m| FlashMessageCount(m.len())))
+    }
+}
+
+/// Incoming flash messages, cached in the request extensions after the first successful load.
+#[derive(Clone)]
+pub(crate) struct LoadedFlashMessages {
+    /// The identifier of the [`FlashMessagesFramework`] instance whose store loaded the messages.
+    ///
+    /// [`FlashMessagesFramework`]: crate::FlashMessagesFramework
+    pub(crate) framework_id: usize,
+    /// The messages handed over to the extractors, i.e. after the `on_load` hook.
+    pub(crate) messages: Vec<FlashMessage>,
+    /// The messages as loaded from the store, before the `on_load` hook - they are the ones
+    /// carried over to the outgoing response if they are not consumed.
+    pub(crate) raw: Vec<FlashMessage>,
+    /// `false` if incoming messages have only been peeked at (e.g. via [`FlashMessageCount`]).
+    pub(crate) consumed: bool,
+}
+
+/// The incoming messages, as loaded from the store, if the request handler has read them -
+/// `None` if they have not been loaded or have only been peeked at.
+pub(crate) fn consumed_flash_messages(req: &HttpRequest) -> Option<Vec<FlashMessage>> {
+    req.extensions()
+        .get::<LoadedFlashMessages>()
+        .filter(|loaded| loaded.consumed)
+        .map(|loaded| loaded.raw.clone())
+}
+
+/// Load the [`FlashMessage`]s attached to an incoming request outside of a request handler -
+/// e.g. in a middleware that needs them to make a routing decision.
+///
+/// ```rust
+/// use actix_web::dev::{Service, ServiceRequest};
+/// use actix_web::{web, App, HttpResponse};
+/// use actix_web_flash_messages::{load_flash_messages, FlashMessagesFramework, Level, storage::CookieMessageStore};
+///
+/// # let message_store = CookieMessageStore::builder(actix_web::cookie::Key::generate()).build();
+/// let app = App::new()
+///     .wrap_fn(|req: ServiceRequest, srv| {
+///         let has_errors = load_flash_messages(req.request())
+///             .map(|messages| messages.iter().any(|m| m.level() == Level::Error))
+///             .unwrap_or(false);
+///         // [...] Route based on `has_errors`
+///         srv.call(req)
+///     })
+///     // `FlashMessagesFramework` must be registered **after** your middleware - i.e. it must wrap it.
+///     .wrap(FlashMessagesFramework::builder(message_store).build())
+///     .route("/", web::get().to(HttpResponse::Ok));
+/// ```
+///
+/// Loading messages via `load_flash_messages` does not count as reading them - just like
+/// [`FlashMessageCount`]: if no handler extracts [`IncomingFlashMessages`], they are carried over
+/// to the next request.  
+/// Messages are cached in the request: the store is not hit again when the request handler
+/// extracts [`IncomingFlashMessages`].
+///
+/// This method will **panic** if [`FlashMessagesFramework`] has not been registered as a middleware
+/// wrapping the caller.
+///
+/// [`FlashMessagesFramework`]: crate::FlashMessagesFramework
+pub fn load_flash_messages(req: &HttpRequest) -> Result<Vec<FlashMessage>, LoadError> {
+    try_load_flash_messages(req, false)
+}
+
+fn load_and_convert_errors(
+    req: &HttpRequest,
+    consume: bool,
+) -> Result<Vec<FlashMessage>, actix_web::Error> {
+    try_load_flash_messages(req, consume).map_err(convert_load_error)
+}
+
+fn convert_load_error(e: LoadError) -> actix_web::Error {
+    actix_web::error::InternalError::new(
+        anyhow::Error::new(e).context("Invalid flash cookie"),
+        StatusCode::BAD_REQUEST,
+    )
+    .into()
+}
+
+/// The minimum level of the incoming messages returned to the request handler.
+///
+/// Only set if messages below the minimum level are stored - see
+/// [`FlashMessagesFrameworkBuilder::debug_param`](crate::FlashMessagesFrameworkBuilder::debug_param).
+#[derive(Clone, Copy)]
+pub(crate) struct ReadMinimumLevel(pub(crate) Level);
+
+fn try_load_flash_messages(
+    req: &HttpRequest,
+    consume: bool,
+) -> Result<Vec<FlashMessage>, LoadError> {
+    try_load_flash_messages_with(req, consume, false).map(|outcome| outcome.messages)
+}
+
+/// Load the incoming messages that have not been read by the request handler, marking them as
+/// read. Returns no message if they have already been read.
+#[cfg(feature = "html")]
+pub(crate) fn take_unread_flash_messages(
+    req: &HttpRequest,
+) -> Result<Vec<FlashMessage>, LoadError> {
+    let framework_id = req
+        .extensions()
+        .get::<OutgoingMailbox>()
+        .map(|mailbox| mailbox.framework_id)
+        .unwrap_or_default();
+    let already_read = req
+        .extensions()
+        .get::<LoadedFlashMessages>()
+        .filter(|loaded| loaded.framework_id == framework_id)
+        .is_some_and(|loaded| loaded.consumed);
+    if already_read {
+        return Ok(vec![]);
+    }
+    try_load_flash_messages(req, true)
+}
+
+/// Load incoming messages, via [`FlashMessageStore::load_lenient`] if `lenient` is `true`.
+fn try_load_flash_messages_with(
+    req: &HttpRequest,
+    consume: bool,
+    lenient: bool,
+) -> Result<LoadOutcome, LoadError> {
+    let read_minimum_level = req.extensions().get::<ReadMinimumLevel>().copied();
+    let mut outcome = try_load_all_flash_messages(req, consume, lenient)?;
+    if let Some(ReadMinimumLevel(minimum_level)) = read_minimum_level {
+        outcome
+            .messages
+            .retain(|m| m.level().is_at_least(minimum_level));
+    }
+    Ok(outcome)
+}
+
+/// Load all incoming messages, ignoring [`ReadMinimumLevel`].
+fn try_load_all_flash_messages(
+    req: &HttpRequest,
+    consume: bool,
+    lenient: bool,
+) -> Result<LoadOutcome, LoadError> {
+    if cfg!(feature = "disabled") {
+        return Ok(LoadOutcome::default());
+    }
+    // Messages are loaded from the store of the innermost framework.
+    let framework_id = req
+        .extensions()
+        .get::<OutgoingMailbox>()
+        .map(|mailbox| mailbox.framework_id)
+        .unwrap_or_default();
+    if let Some(loaded) = req
+        .extensions_mut()
+        .get_mut::<LoadedFlashMessages>()
+        .filter(|loaded| loaded.framework_id == framework_id)
+    {
+        loaded.consumed |= consume;
+        return Ok(LoadOutcome {
+            messages: loaded.messages.clone(),
+            warnings: vec![],
+        });
+    }
     let message_store = req.extensions()
         .get::<Arc<dyn FlashMessageStore>>()
         .expect("Failed to retrieve flash messages!\n\
@@ -53,14 +600,25 @@ fn extract_flash_messages(req: &HttpRequest) -> Result<IncomingFlashMessages, ac
         // Some of the methods on `req` will in turn try to use `req.extensions_mut()`, leading to a borrow
         // panic at runtime due to the usage of interior mutability.
         .to_owned();
-    message_store
-        .load(req)
-        .map(|m| IncomingFlashMessages { messages: m })
-        .map_err(|e| {
-            actix_web::error::InternalError::new(
-                anyhow::Error::new(e).context("Invalid flash cookie"),
-                StatusCode::BAD_REQUEST,
-            )
-            .into()
-        })
+    let mut outcome = if lenient {
+        message_store.load_lenient(req)?
+    } else {
+        LoadOutcome {
+            messages: message_store.load(req)?,
+            warnings: vec![],
+        }
+    };
+    outcome.messages.retain(|m| !m.is_expired());
+    let raw = outcome.messages.clone();
+    let on_load = req.extensions().get::<OnLoad>().cloned();
+    if let Some(OnLoad(on_load)) = on_load {
+        outcome.messages = on_load(outcome.messages);
+    }
+    req.extensions_mut().insert(LoadedFlashMessages {
+        framework_id,
+        messages: outcome.messages.clone(),
+        raw,
+        consumed: consume,
+    });
+    Ok(outcome)
 }
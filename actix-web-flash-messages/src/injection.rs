@@ -0,0 +1,177 @@
+use crate::incoming::take_unread_flash_messages;
+use crate::HtmlRenderer;
+use actix_web::body::{BoxBody, EitherBody, MessageBody};
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header;
+use actix_web::web::Bytes;
+use actix_web::HttpResponse;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+
+/// `actix-web` middleware that injects incoming flash messages into HTML pages, right before the
+/// closing `</body>` tag.
+///
+/// It comes in handy for legacy server-rendered templates that do not have a partial to render
+/// flash messages: incoming messages that have not been read by the request handler are rendered
+/// via [`HtmlRenderer`] (HTML-escaped, one `<div role="alert">` per message) and marked as read.
+///
+/// ```rust
+/// use actix_web::{App, cookie::Key};
+/// use actix_web_flash_messages::{BodyInjection, FlashMessagesFramework, storage::CookieMessageStore};
+///
+/// let message_store = CookieMessageStore::builder(Key::generate()).build();
+/// let app = App::new()
+///     // Registered **before** `FlashMessagesFramework`, i.e. it is wrapped by it.
+///     .wrap(BodyInjection::new())
+///     .wrap(FlashMessagesFramework::builder(message_store).build());
+/// ```
+///
+/// [`BodyInjection`] must be registered **before** [`FlashMessagesFramework`]: the injected
+/// messages must be marked as read before [`FlashMessagesFramework`] flushes outgoing messages,
+/// otherwise they are shown again on the next page.
+///
+/// Only `text/html` responses are rewritten, as long as they are not compressed (i.e. they don't
+/// have a `Content-Encoding` header) and they contain a closing `</body>` tag. Messages are left
+/// untouched for all other responses.
+///
+/// # Buffering
+///
+/// Injecting messages requires rewriting the response body: every `text/html` response is
+/// **buffered in memory**, in its entirety, before being sent to the client - streaming HTML
+/// responses lose their streaming behaviour.
+/// Prefer rendering [`IncomingFlashMessages`] in your templates whenever you can, and register
+/// [`BodyInjection`] only on the scopes that serve legacy templates.
+///
+/// It is only available if the `html` feature flag is enabled.
+///
+/// [`FlashMessagesFramework`]: crate::FlashMessagesFramework
+/// [`IncomingFlashMessages`]: crate::IncomingFlashMessages
+#[derive(Clone, Default)]
+pub struct BodyInjection {
+    renderer: HtmlRenderer,
+}
+
+impl BodyInjection {
+    /// Build a new [`BodyInjection`] middleware, rendering messages using the default
+    /// [`HtmlRenderer`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// By default, messages are rendered using [`HtmlRenderer::default`].
+    /// Use `renderer` to customise the injected markup.
+    pub fn renderer(mut self, renderer: HtmlRenderer) -> Self {
+        self.renderer = renderer;
+        self
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for BodyInjection
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Transform = BodyInjectionMiddleware<S>;
+    type InitError = ();
+    type Future = std::future::Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        std::future::ready(Ok(BodyInjectionMiddleware {
+            service,
+            renderer: Rc::new(self.renderer.clone()),
+        }))
+    }
+}
+
+#[non_exhaustive]
+#[doc(hidden)]
+pub struct BodyInjectionMiddleware<S> {
+    service: S,
+    renderer: Rc<HtmlRenderer>,
+}
+
+#[allow(clippy::type_complexity)]
+impl<S, B> Service<ServiceRequest> for BodyInjectionMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    actix_web::dev::forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let future = self.service.call(req);
+        let renderer = self.renderer.clone();
+        Box::pin(async move {
+            let response = future.await?;
+            if !is_injectable(response.response()) {
+                return Ok(response.map_into_left_body());
+            }
+            let (request, response) = response.into_parts();
+            let (mut response, body) = response.into_parts();
+            let body = actix_web::body::to_bytes(body).await.map_err(|e| {
+                let e: Box<dyn std::error::Error> = e.into();
+                actix_web::error::ErrorInternalServerError(e.to_string())
+            })?;
+            let body = match find_closing_body_tag(&body) {
+                Some(position) => {
+                    // Injection is best-effort: incoming messages that fail to load are not shown,
+                    // as if there were none.
+                    let messages = take_unread_flash_messages(&request).unwrap_or_default();
+                    if messages.is_empty() {
+                        body
+                    } else {
+                        let mut snippet = messages
+                            .iter()
+                            .map(|message| renderer.render_message(message))
+                            .collect::<Vec<_>>()
+                            .join("\n");
+                        snippet.push('\n');
+                        let mut injected = Vec::with_capacity(body.len() + snippet.len());
+                        injected.extend_from_slice(&body[..position]);
+                        injected.extend_from_slice(snippet.as_bytes());
+                        injected.extend_from_slice(&body[position..]);
+                        Bytes::from(injected)
+                    }
+                }
+                None => body,
+            };
+            // The length is recomputed from the new body.
+            response.headers_mut().remove(header::CONTENT_LENGTH);
+            let response: HttpResponse<EitherBody<B>> = response
+                .set_body(body)
+                .map_body(|_, body| EitherBody::right(BoxBody::new(body)));
+            Ok(ServiceResponse::new(request, response))
+        })
+    }
+}
+
+/// Returns `true` for uncompressed `text/html` responses.
+fn is_injectable<B>(response: &HttpResponse<B>) -> bool {
+    let headers = response.headers();
+    let is_html = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| {
+            value
+                .trim_start()
+                .to_ascii_lowercase()
+                .starts_with("text/html")
+        });
+    is_html && !headers.contains_key(header::CONTENT_ENCODING)
+}
+
+/// The position of the last closing `</body` tag in `html`, compared case-insensitively.
+fn find_closing_body_tag(html: &[u8]) -> Option<usize> {
+    const TAG: &[u8] = b"</body";
+    html.windows(TAG.len())
+        .rposition(|window| window.eq_ignore_ascii_case(TAG))
+}
@@ -0,0 +1,142 @@
+//! Shortcuts to integrate flash messages with other parts of an `actix-web` application.
+use crate::{FlashMessage, FlashMessagesFramework, IncomingFlashMessages};
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceFactory, ServiceRequest, ServiceResponse};
+use actix_web::http::{header, Uri};
+use actix_web::{web, App, Error, HttpRequest, HttpResponse};
+
+/// An extension trait to set up flash messages on an `actix-web` [`App`] with a single call.
+///
+/// ```rust
+/// use actix_web_flash_messages::{FlashMessagesFramework, interop::FlashAppExt, storage::CookieMessageStore};
+/// use actix_web::{HttpServer, App, cookie::Key};
+///
+/// #[actix_web::main]
+/// async fn main() {
+///     let message_store = CookieMessageStore::builder(Key::generate()).build();
+///     let message_framework = FlashMessagesFramework::builder(message_store).build();
+///
+///     HttpServer::new(move || {
+///         App::new()
+///             // [...] your endpoints
+///             .with_flash_messages(message_framework.clone())
+///     })
+///     # ;
+/// }
+/// ```
+pub trait FlashAppExt<B> {
+    /// Register `framework` as a middleware and mount a `GET /flash.json` endpoint,
+    /// returning the incoming flash messages as JSON - e.g.
+    /// `{"messages": [{"content": "Hey there!", "level": "info"}]}`.
+    ///
+    /// It is equivalent to:
+    ///
+    /// ```rust,ignore
+    /// app.route("/flash.json", web::get().to(flash_json))
+    ///     .wrap(framework)
+    /// ```
+    fn with_flash_messages(
+        self,
+        framework: FlashMessagesFramework,
+    ) -> App<
+        impl ServiceFactory<
+            ServiceRequest,
+            Config = (),
+            Response = ServiceResponse<B>,
+            Error = Error,
+            InitError = (),
+        >,
+    >;
+}
+
+impl<T, B> FlashAppExt<B> for App<T>
+where
+    T: ServiceFactory<
+        ServiceRequest,
+        Config = (),
+        Response = ServiceResponse<B>,
+        Error = Error,
+        InitError = (),
+    >,
+    T::Service: 'static,
+    <T::Service as actix_web::dev::Service<ServiceRequest>>::Future: 'static,
+    B: MessageBody + 'static,
+{
+    fn with_flash_messages(
+        self,
+        framework: FlashMessagesFramework,
+    ) -> App<
+        impl ServiceFactory<
+            ServiceRequest,
+            Config = (),
+            Response = ServiceResponse<B>,
+            Error = Error,
+            InitError = (),
+        >,
+    > {
+        self.route("/flash.json", web::get().to(flash_json))
+            .wrap(framework)
+    }
+}
+
+async fn flash_json(messages: IncomingFlashMessages) -> HttpResponse {
+    HttpResponse::Ok().json(serde_json::json!({ "messages": messages }))
+}
+
+/// Send flash messages and redirect the user back to the page they came from - the "flash and go back" pattern.
+///
+/// It returns a `303 See Other` response pointing at the URL in the `Referer` header of `req`,
+/// preserved as is (query string included).  
+/// To prevent open redirects, the `Referer` is only honoured if it points at the same origin
+/// as `req` (or if it is a relative path): it falls back to `/` otherwise, as well as when the header
+/// is missing.
+///
+/// ```rust
+/// use actix_web::{HttpRequest, HttpResponse, post};
+/// use actix_web_flash_messages::{FlashMessage, interop::flash_back};
+///
+/// #[post("/comments")]
+/// async fn add_comment(req: HttpRequest) -> HttpResponse {
+///     // [...]
+///     flash_back(&req, [FlashMessage::success("Your comment has been posted!")])
+/// }
+/// ```
+///
+/// This method will **panic** if [`FlashMessagesFramework`] has not been registered as a middleware.
+pub fn flash_back<I>(req: &HttpRequest, messages: I) -> HttpResponse
+where
+    I: IntoIterator<Item = FlashMessage>,
+{
+    for message in messages {
+        message.send();
+    }
+    let location = req
+        .headers()
+        .get(header::REFERER)
+        .and_then(|referer| referer.to_str().ok())
+        .filter(|referer| is_same_origin(req, referer))
+        .unwrap_or("/");
+    HttpResponse::SeeOther()
+        .insert_header((header::LOCATION, location))
+        .finish()
+}
+
+fn is_same_origin(req: &HttpRequest, referer: &str) -> bool {
+    let uri = match referer.parse::<Uri>() {
+        Ok(uri) => uri,
+        Err(_) => return false,
+    };
+    match (uri.scheme_str(), uri.authority()) {
+        // A relative reference - e.g. `/posts?page=2`.
+        // Protocol-relative references (e.g. `//evil.com`) are parsed as having an authority.
+        (None, None) => referer.starts_with('/') && !referer.starts_with("//"),
+        (Some(scheme), Some(authority)) => {
+            let connection_info = req.connection_info();
+            scheme.eq_ignore_ascii_case(connection_info.scheme())
+                && authority
+                    .as_str()
+                    .eq_ignore_ascii_case(connection_info.host())
+        }
+        _ => false,
+    }
+}
@@ -1,11 +1,36 @@
 #![doc = include_str!("../crate_readme.md")]
 mod builder;
+#[cfg(any(feature = "cookies", feature = "sessions"))]
+mod config;
 mod flash_message;
+mod flash_scope;
+#[cfg(feature = "html")]
+mod html;
 mod incoming;
+#[cfg(feature = "html")]
+mod injection;
+pub mod interop;
+mod macros;
 mod middleware;
 pub mod storage;
+#[cfg(feature = "test-util")]
+pub mod test_util;
 
 pub use builder::{FlashMessagesFramework, FlashMessagesFrameworkBuilder};
-pub use flash_message::{FlashMessage, Level};
-pub use incoming::IncomingFlashMessages;
-pub use middleware::FlashMessagesMiddleware;
+#[cfg(feature = "cookies")]
+pub use config::CookieConfig;
+#[cfg(feature = "sessions")]
+pub use config::SessionConfig;
+#[cfg(any(feature = "cookies", feature = "sessions"))]
+pub use config::{ConfigError, FlashConfig};
+pub use flash_message::{FlashMessage, FlashMessageBuilder, Level};
+pub use flash_scope::FlashScope;
+#[cfg(feature = "html")]
+pub use html::HtmlRenderer;
+pub use incoming::{
+    load_flash_messages, FlashMessageCount, FlashSummary, IncomingFlashMessages,
+    LenientFlashMessages,
+};
+#[cfg(feature = "html")]
+pub use injection::{BodyInjection, BodyInjectionMiddleware};
+pub use middleware::{FlashMessagesMiddleware, FlashStored};
@@ -0,0 +1,108 @@
+/// Format and send a debug-level [`FlashMessage`](crate::FlashMessage) in one go.
+///
+/// It accepts the same arguments as [`format!`]:
+///
+/// ```rust
+/// use actix_web::{Responder, HttpResponse, get};
+/// use actix_web_flash_messages::flash_debug;
+///
+/// #[get("/sync")]
+/// async fn sync() -> impl Responder {
+///     let elapsed_ms = 42; // [...]
+///     flash_debug!("Sync completed in {}ms", elapsed_ms);
+///     HttpResponse::Ok()
+/// }
+/// ```
+///
+/// It is a shortcut for `FlashMessage::debug(format!(..)).try_send()` - see
+/// [`FlashMessage::try_send`](crate::FlashMessage::try_send) for more details.  
+/// It never panics: if [`FlashMessagesFramework`](crate::FlashMessagesFramework) has not been
+/// registered as a middleware (e.g. in a spawned task), the message is dropped.
+#[macro_export]
+macro_rules! flash_debug {
+    ($($arg:tt)+) => {
+        {
+            let _ = $crate::FlashMessage::debug(::std::format!($($arg)+)).try_send();
+        }
+    };
+}
+
+/// Format and send an info-level [`FlashMessage`](crate::FlashMessage) in one go.
+///
+/// It accepts the same arguments as [`format!`]:
+///
+/// ```rust
+/// use actix_web::{Responder, HttpResponse, get};
+/// use actix_web_flash_messages::flash_info;
+///
+/// #[get("/users/save")]
+/// async fn save() -> impl Responder {
+///     let user_id = 7; // [...]
+///     flash_info!("User {} saved", user_id);
+///     HttpResponse::Ok()
+/// }
+/// ```
+///
+/// It is a shortcut for `FlashMessage::info(format!(..)).try_send()` - see
+/// [`FlashMessage::try_send`](crate::FlashMessage::try_send) for more details.  
+/// It never panics: if [`FlashMessagesFramework`](crate::FlashMessagesFramework) has not been
+/// registered as a middleware (e.g. in a spawned task), the message is dropped.
+#[macro_export]
+macro_rules! flash_info {
+    ($($arg:tt)+) => {
+        {
+            let _ = $crate::FlashMessage::info(::std::format!($($arg)+)).try_send();
+        }
+    };
+}
+
+/// Format and send a success-level [`FlashMessage`](crate::FlashMessage) in one go.
+///
+/// It accepts the same arguments as [`format!`] - see [`flash_info!`] for an example.
+///
+/// It is a shortcut for `FlashMessage::success(format!(..)).try_send()` - see
+/// [`FlashMessage::try_send`](crate::FlashMessage::try_send) for more details.  
+/// It never panics: if [`FlashMessagesFramework`](crate::FlashMessagesFramework) has not been
+/// registered as a middleware (e.g. in a spawned task), the message is dropped.
+#[macro_export]
+macro_rules! flash_success {
+    ($($arg:tt)+) => {
+        {
+            let _ = $crate::FlashMessage::success(::std::format!($($arg)+)).try_send();
+        }
+    };
+}
+
+/// Format and send a warning-level [`FlashMessage`](crate::FlashMessage) in one go.
+///
+/// It accepts the same arguments as [`format!`] - see [`flash_info!`] for an example.
+///
+/// It is a shortcut for `FlashMessage::warning(format!(..)).try_send()` - see
+/// [`FlashMessage::try_send`](crate::FlashMessage::try_send) for more details.  
+/// It never panics: if [`FlashMessagesFramework`](crate::FlashMessagesFramework) has not been
+/// registered as a middleware (e.g. in a spawned task), the message is dropped.
+#[macro_export]
+macro_rules! flash_warning {
+    ($($arg:tt)+) => {
+        {
+            let _ = $crate::FlashMessage::warning(::std::format!($($arg)+)).try_send();
+        }
+    };
+}
+
+/// Format and send an error-level [`FlashMessage`](crate::FlashMessage) in one go.
+///
+/// It accepts the same arguments as [`format!`] - see [`flash_info!`] for an example.
+///
+/// It is a shortcut for `FlashMessage::error(format!(..)).try_send()` - see
+/// [`FlashMessage::try_send`](crate::FlashMessage::try_send) for more details.  
+/// It never panics: if [`FlashMessagesFramework`](crate::FlashMessagesFramework) has not been
+/// registered as a middleware (e.g. in a spawned task), the message is dropped.
+#[macro_export]
+macro_rules! flash_error {
+    ($($arg:tt)+) => {
+        {
+            let _ = $crate::FlashMessage::error(::std::format!($($arg)+)).try_send();
+        }
+    };
+}
@@ -1,34 +1,137 @@
 use std::cell::RefCell;
 use std::future::Future;
 use std::pin::Pin;
+use std::rc::Rc;
 
 use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
 
-use crate::builder::FlashMessagesFramework;
+use crate::builder::{
+    DebugParam, DynamicMinimumLevel, FlashMessagesFramework, MessageRenderer, OnLoad,
+};
+use crate::incoming::{LoadedFlashMessages, ReadMinimumLevel};
 use crate::{storage::FlashMessageStore, FlashMessage, Level};
 use actix_web::body::MessageBody;
 use actix_web::HttpMessage;
+use std::collections::BTreeMap;
 use std::sync::Arc;
 
 tokio::task_local! {
     pub(crate) static OUTGOING_MAILBOX: OutgoingMailbox;
 }
 
+/// Cloning an [`OutgoingMailbox`] is cheap - all clones share the same messages.
 #[derive(Clone)]
 pub(crate) struct OutgoingMailbox {
-    pub(crate) messages: RefCell<Vec<FlashMessage>>,
+    pub(crate) messages: Rc<RefCell<Vec<FlashMessage>>>,
     pub(crate) minimum_level: Level,
+    pub(crate) skip_empty: bool,
+    pub(crate) truncate_content: Option<usize>,
+    pub(crate) content_prefix: Option<Arc<str>>,
+    pub(crate) max_messages: Option<usize>,
+    /// The identifier of the [`FlashMessagesFramework`] instance that owns this mailbox.
+    pub(crate) framework_id: usize,
 }
 
 impl OutgoingMailbox {
-    pub(crate) fn new(minimum_level: Level) -> Self {
+    pub(crate) fn new(
+        minimum_level: Level,
+        skip_empty: bool,
+        truncate_content: Option<usize>,
+        content_prefix: Option<Arc<str>>,
+        initial_capacity: usize,
+        max_messages: Option<usize>,
+        framework_id: usize,
+    ) -> Self {
         Self {
-            messages: RefCell::new(vec![]),
+            messages: Rc::new(RefCell::new(Vec::with_capacity(initial_capacity))),
             minimum_level,
+            skip_empty,
+            truncate_content,
+            content_prefix,
+            max_messages,
+            framework_id,
+        }
+    }
+
+    /// Returns `true` if another message can be queued on top of the `queued` ones.
+    ///
+    /// It logs a warning if the per-request budget has been exhausted.
+    pub(crate) fn has_room(&self, queued: usize) -> bool {
+        let Some(max_messages) = self.max_messages else {
+            return true;
+        };
+        if queued < max_messages {
+            return true;
+        }
+        #[cfg(feature = "tracing")]
+        tracing::warn!(
+            max_messages,
+            "Dropping an outgoing flash message: the maximum number of messages per request has been reached"
+        );
+        false
+    }
+
+    /// Prepare `message` for dispatch - e.g. truncating its content.
+    ///
+    /// Returns `None` if `message` must be dropped instead of being dispatched.
+    pub(crate) fn admit(&self, message: FlashMessage) -> Option<FlashMessage> {
+        if !message.level().is_at_least(self.minimum_level)
+            || (self.skip_empty && message.is_empty())
+        {
+            return None;
+        }
+        let message = match &self.content_prefix {
+            Some(prefix) => {
+                let content = format!("{}{}", prefix, message.content());
+                message.with_content(content)
+            }
+            None => message,
+        };
+        match self.truncate_content {
+            Some(max) if message.content().len() > max => {
+                let content = message.content_truncated(max).into_owned();
+                Some(message.with_content(content))
+            }
+            _ => Some(message),
         }
     }
 }
 
+/// A marker inserted by [`FlashMessagesFramework`] in the extensions of every outgoing response,
+/// once outgoing flash messages have been successfully stored.
+///
+/// It lets outer middlewares (e.g. for logging) observe flash activity without parsing
+/// `Set-Cookie` headers:
+///
+/// ```rust
+/// use actix_web::dev::Service;
+/// use actix_web::{web, App, HttpResponse};
+/// use actix_web_flash_messages::{FlashMessagesFramework, FlashStored, storage::CookieMessageStore};
+///
+/// # let message_store = CookieMessageStore::builder(actix_web::cookie::Key::generate()).build();
+/// let app = App::new()
+///     .wrap(FlashMessagesFramework::builder(message_store).build())
+///     // Registered **after** `FlashMessagesFramework`, i.e. it wraps it.
+///     .wrap_fn(|req, srv| {
+///         let fut = srv.call(req);
+///         async move {
+///             let res = fut.await?;
+///             if let Some(FlashStored { count }) = res.response().extensions().get::<FlashStored>() {
+///                 println!("{} flash messages stored", count);
+///             }
+///             Ok(res)
+///         }
+///     })
+///     .route("/", web::get().to(HttpResponse::Ok));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlashStored {
+    /// The number of flash messages that were handed over to the store - outgoing messages and
+    /// incoming messages carried over.  
+    /// `0` if there was nothing to store - previous messages, if any, have been cleared.
+    pub count: usize,
+}
+
 impl<S, B> Transform<S, ServiceRequest> for FlashMessagesFramework
 where
     S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
@@ -46,6 +149,19 @@ where
             service,
             storage_backend: self.storage_backend.clone(),
             minimum_level: self.minimum_level,
+            dynamic_minimum_level: self.dynamic_minimum_level.clone(),
+            debug_param: self.debug_param.clone(),
+            skip_empty: self.skip_empty,
+            truncate_content: self.truncate_content,
+            content_prefix: self.content_prefix.clone(),
+            max_per_level: self.max_per_level.clone(),
+            initial_capacity: self.initial_capacity,
+            max_messages_per_request: self.max_messages_per_request,
+            clear_on_read: self.clear_on_read,
+            record_source_path: self.record_source_path,
+            renderer: self.renderer.clone(),
+            on_load: self.on_load.clone(),
+            framework_id: self.id,
         }))
     }
 }
@@ -56,6 +172,19 @@ pub struct FlashMessagesMiddleware<S> {
     service: S,
     storage_backend: Arc<dyn FlashMessageStore>,
     minimum_level: Level,
+    dynamic_minimum_level: Option<DynamicMinimumLevel>,
+    debug_param: Option<DebugParam>,
+    skip_empty: bool,
+    truncate_content: Option<usize>,
+    content_prefix: Option<Arc<str>>,
+    max_per_level: Arc<BTreeMap<Level, usize>>,
+    initial_capacity: usize,
+    max_messages_per_request: Option<usize>,
+    clear_on_read: bool,
+    record_source_path: bool,
+    renderer: Option<MessageRenderer>,
+    on_load: Option<OnLoad>,
+    framework_id: usize,
 }
 
 #[allow(clippy::type_complexity)]
@@ -72,8 +201,56 @@ where
     actix_web::dev::forward_ready!(service);
 
     fn call(&self, req: ServiceRequest) -> Self::Future {
+        if cfg!(feature = "disabled") {
+            return Box::pin(self.service.call(req));
+        }
         req.extensions_mut().insert(self.storage_backend.clone());
-        let outgoing_mailbox = OutgoingMailbox::new(self.minimum_level);
+        self.storage_backend.on_request(req.request());
+        // The settings of an outer framework must not leak into a nested one that doesn't
+        // configure them.
+        match &self.renderer {
+            Some(renderer) => {
+                req.extensions_mut().insert(renderer.clone());
+            }
+            None => {
+                req.extensions_mut().remove::<MessageRenderer>();
+            }
+        }
+        match &self.on_load {
+            Some(on_load) => {
+                req.extensions_mut().insert(on_load.clone());
+            }
+            None => {
+                req.extensions_mut().remove::<OnLoad>();
+            }
+        }
+        req.extensions_mut().remove::<ReadMinimumLevel>();
+        let minimum_level = match &self.dynamic_minimum_level {
+            Some(DynamicMinimumLevel(f)) => f(&req),
+            None => self.minimum_level,
+        };
+        // With a debug parameter, messages are filtered when they are read rather than when they
+        // are sent.
+        let minimum_level = match &self.debug_param {
+            Some(debug_param) => {
+                if !debug_param.matches(&req) {
+                    req.extensions_mut().insert(ReadMinimumLevel(minimum_level));
+                }
+                Level::Debug
+            }
+            None => minimum_level,
+        };
+        let outgoing_mailbox = OutgoingMailbox::new(
+            minimum_level,
+            self.skip_empty,
+            self.truncate_content,
+            self.content_prefix.clone(),
+            self.initial_capacity,
+            self.max_messages_per_request,
+            self.framework_id,
+        );
+        // The mailbox is also made available via the request extensions, for `FlashScope`.
+        req.extensions_mut().insert(outgoing_mailbox.clone());
         // Working with task-locals in actix-web middlewares is a bit annoying.
         // We need to make the task local value available to the rest of the middleware chain, which
         // generates the `future` which will in turn return us a response.
@@ -84,22 +261,82 @@ where
         // using `scope` without losing the messages that might have been recorded by the middleware
         // chain.
         let storage_backend = self.storage_backend.clone();
+        let clear_on_read = self.clear_on_read;
+        let record_source_path = self.record_source_path;
+        let max_per_level = self.max_per_level.clone();
+        let framework_id = self.framework_id;
         Box::pin(OUTGOING_MAILBOX.scope(outgoing_mailbox, async move {
-            let response: Result<Self::Response, Self::Error> = future.await;
-            response.map(|mut response| {
-                OUTGOING_MAILBOX
-                    .with(|m| {
-                        storage_backend.store(
-                            &m.messages.borrow(),
-                            // This `.clone()` is cheap because `HttpRequest` is just an `Rc` pointer
-                            // around the actual request data.
-                            response.request().clone(),
-                            response.response_mut().head_mut(),
-                        )
-                    })
-                    .unwrap();
-                response
-            })
+            let mut response: Self::Response = future.await?;
+            // Incoming messages that have not been consumed by this request must survive it:
+            // they are carried over, ahead of the outgoing messages.
+            let incoming = response
+                .request()
+                .extensions()
+                .get::<LoadedFlashMessages>()
+                // Ignore messages loaded by a nested framework.
+                .filter(|loaded| loaded.framework_id == framework_id)
+                .map(|loaded| (!loaded.consumed).then(|| loaded.raw.clone()));
+            let carried_over = match incoming {
+                // Stores that are not one-time do not remove incoming messages: there is
+                // nothing to carry over.
+                _ if !storage_backend.is_one_time() => vec![],
+                Some(unconsumed) => unconsumed.unwrap_or_default(),
+                // Incoming messages have not been loaded at all.
+                None if clear_on_read => {
+                    let mut messages = storage_backend.load(response.request()).unwrap_or_default();
+                    messages.retain(|m| !m.is_expired());
+                    messages
+                }
+                None => vec![],
+            };
+            // The mailbox can't stay borrowed while the store is awaited.
+            let mut messages =
+                OUTGOING_MAILBOX.with(|m| std::mem::take(&mut *m.messages.borrow_mut()));
+            if record_source_path {
+                let path = response.request().path();
+                for message in messages.iter_mut() {
+                    message.set_source_path_if_missing(path);
+                }
+            }
+            messages.splice(0..0, carried_over);
+            if !max_per_level.is_empty() {
+                enforce_max_per_level(&mut messages, &max_per_level);
+            }
+            // This `.clone()` is cheap because `HttpRequest` is just an `Rc` pointer
+            // around the actual request data.
+            let request = response.request().clone();
+            storage_backend
+                .store_async(&messages, request, response.response_mut().head_mut())
+                .await
+                .unwrap();
+            response
+                .response_mut()
+                .extensions_mut()
+                .insert(FlashStored {
+                    count: messages.len(),
+                });
+            Ok(response)
         }))
     }
 }
+
+/// Drop the earliest messages of each level in excess of the configured maximum.
+fn enforce_max_per_level(messages: &mut Vec<FlashMessage>, max_per_level: &BTreeMap<Level, usize>) {
+    let mut remaining = max_per_level.clone();
+    // Walk backwards: the last messages, in delivery order, are kept.
+    let mut keep: Vec<bool> = messages
+        .iter()
+        .rev()
+        .map(|m| match remaining.get_mut(&m.level()) {
+            Some(0) => false,
+            Some(n) => {
+                *n -= 1;
+                true
+            }
+            None => true,
+        })
+        .collect();
+    keep.reverse();
+    let mut keep = keep.into_iter();
+    messages.retain(|_| keep.next().unwrap());
+}
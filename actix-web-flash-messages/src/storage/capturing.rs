@@ -0,0 +1,64 @@
+use crate::storage::{FlashMessageStore, LoadError, StoreError};
+use crate::FlashMessage;
+use actix_web::dev::ResponseHead;
+use actix_web::HttpRequest;
+use std::sync::{Arc, Mutex};
+
+/// An in-memory message store to test flashing behaviour without inspecting cookies or sessions.
+///
+/// [`CapturingMessageStore`] records the outgoing messages it receives in
+/// [`CapturingMessageStore::captured`] and returns them, as incoming messages, on the next load.
+/// Clone it before handing it over to [`FlashMessagesFramework`]: all clones share the same
+/// captured messages.
+///
+/// ```rust
+/// use actix_web_flash_messages::{FlashMessage, FlashMessagesFramework, storage::CapturingMessageStore};
+/// use actix_web::{web, App, HttpResponse};
+///
+/// #[actix_web::main]
+/// async fn main() {
+///     let store = CapturingMessageStore::default();
+///     let app = actix_web::test::init_service(
+///         App::new()
+///             .wrap(FlashMessagesFramework::builder(store.clone()).build())
+///             .route("/", web::get().to(|| async {
+///                 FlashMessage::info("Hey there!").send();
+///                 HttpResponse::Ok()
+///             })),
+///     )
+///     .await;
+///     actix_web::test::call_service(&app, actix_web::test::TestRequest::get().to_request()).await;
+///
+/// #   #[cfg(not(feature = "disabled"))]
+/// #   {
+///     let captured = store.captured.lock().unwrap();
+///     assert_eq!(captured.len(), 1);
+///     assert_eq!(captured[0].content(), "Hey there!");
+/// #   }
+/// }
+/// ```
+///
+/// It is only available if the `test-util` feature flag is enabled.
+///
+/// [`FlashMessagesFramework`]: crate::FlashMessagesFramework
+#[derive(Clone, Default)]
+pub struct CapturingMessageStore {
+    /// The messages passed to the last invocation of [`FlashMessageStore::store`].
+    pub captured: Arc<Mutex<Vec<FlashMessage>>>,
+}
+
+impl FlashMessageStore for CapturingMessageStore {
+    fn load(&self, _request: &HttpRequest) -> Result<Vec<FlashMessage>, LoadError> {
+        Ok(self.captured.lock().unwrap().clone())
+    }
+
+    fn store(
+        &self,
+        messages: &[FlashMessage],
+        _request: HttpRequest,
+        _response: &mut ResponseHead,
+    ) -> Result<(), StoreError> {
+        *self.captured.lock().unwrap() = messages.to_vec();
+        Ok(())
+    }
+}
@@ -1,13 +1,20 @@
-use crate::storage::interface::{FlashMessageStore, LoadError, StoreError};
+use crate::storage::envelope::{Envelope, StoredPayload};
+use crate::storage::interface::{FlashMessageStore, LoadError, LoadOutcome, StoreError};
+use crate::storage::{DerivedKeyProvider, ReplayGuard};
 use crate::FlashMessage;
-use actix_web::cookie::{Cookie, SameSite};
+use actix_web::cookie::{Cookie, CookieBuilder, SameSite};
 use actix_web::cookie::{CookieJar, Key};
 use actix_web::dev::ResponseHead;
 use actix_web::http::header;
 use actix_web::http::header::HeaderValue;
-use actix_web::HttpRequest;
+use actix_web::{web, HttpRequest};
 use anyhow::Context;
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
 use percent_encoding::{percent_encode, AsciiSet};
+use std::borrow::Cow;
+use std::sync::Arc;
+use time::OffsetDateTime;
 
 /// A cookie-based implementation of flash messages.
 ///
@@ -17,25 +24,53 @@ use percent_encoding::{percent_encode, AsciiSet};
 ///
 /// You can find an example of an application using [`CookieMessageStore`]
 /// [on GitHub](https://github.com/LukeMathWalker/actix-web-flash-messages/tree/main/examples/cookies).
+///
+/// # Key rotation
+///
+/// You can rotate the signing key without invalidating the flash messages that are currently
+/// in flight: register your previous keys via [`CookieMessageStoreBuilder::verification_key`].  
+/// If you also give your signing key an identifier, via [`CookieMessageStoreBuilder::key_id`],
+/// the identifier is embedded in the flash cookie: incoming cookies are then verified using the
+/// matching key directly, instead of trying all of them in turn.
+///
+/// Alternatively, you can rotate keys automatically - every day - by deriving them from a master
+/// key: see [`DerivedKeyProvider`].
 pub struct CookieMessageStore {
     cookie_name: String,
-    signing_key: Key,
+    signing_key: SigningKey,
+    key_id: Option<String>,
+    verification_keys: Vec<(String, Key)>,
     bytes_size_limit: u32,
+    max_chunks: usize,
     same_site: SameSite,
     path: String,
     domain: Option<String>,
+    expires: Option<OffsetDateTime>,
+    replay_guard: Option<Arc<dyn ReplayGuard>>,
+    configure_cookie: Option<CookieConfigurator>,
+    legacy_cookie_prefix: Option<String>,
 }
 
 /// A fluent builder to construct a [`CookieMessageStore`] instance.
 pub struct CookieMessageStoreBuilder {
     cookie_name: Option<String>,
-    signing_key: Key,
+    signing_key: SigningKey,
+    key_id: Option<String>,
+    verification_keys: Vec<(String, Key)>,
     bytes_size_limit: Option<u32>,
+    max_chunks: Option<usize>,
     same_site: Option<SameSite>,
     path: Option<String>,
     domain: Option<String>,
+    expires: Option<OffsetDateTime>,
+    replay_guard: Option<Arc<dyn ReplayGuard>>,
+    configure_cookie: Option<CookieConfigurator>,
+    legacy_cookie_prefix: Option<String>,
 }
 
+type CookieConfigurator =
+    Arc<dyn Fn(CookieBuilder<'static>) -> CookieBuilder<'static> + Send + Sync>;
+
 impl CookieMessageStore {
     /// A fluent API to configure [`CookieMessageStore`].
     ///
@@ -43,14 +78,145 @@ impl CookieMessageStore {
     /// The cookie used to store flash messages is signed - this ensures that flash messages
     /// were authored by the application and were not tampered with.  
     pub fn builder(signing_key: Key) -> CookieMessageStoreBuilder {
+        Self::builder_with_signing_key(SigningKey::Owned(signing_key))
+    }
+
+    /// Build a [`CookieMessageStoreBuilder`] that resolves its signing key, at request time,
+    /// from the application data - i.e. a `web::Data<Key>` registered via [`App::app_data`].
+    ///
+    /// ```rust
+    /// use actix_web_flash_messages::{FlashMessagesFramework, storage::CookieMessageStore};
+    /// use actix_web::{web, App, HttpServer, cookie::Key};
+    ///
+    /// #[actix_web::main]
+    /// async fn main() {
+    ///     let signing_key = web::Data::new(Key::generate());
+    ///     let message_store = CookieMessageStore::builder_from_app_data().build();
+    ///     let message_framework = FlashMessagesFramework::builder(message_store).build();
+    ///
+    ///     HttpServer::new(move || {
+    ///         App::new()
+    ///             .app_data(signing_key.clone())
+    ///             .wrap(message_framework.clone())
+    ///             // [...] your endpoints
+    ///     })
+    ///     # ;
+    /// }
+    /// ```
+    ///
+    /// The key is looked up on every request: you don't need to pass it around when building the
+    /// store and the key can differ across the `App` instances of your server.  
+    /// The trade-off: the key **must** be registered as application data before requests arrive.
+    /// If it is missing, loading fails with [`LoadError::GenericError`] and storing fails with
+    /// [`StoreError::GenericError`] - which causes a panic when outgoing messages are flushed.
+    ///
+    /// [`App::app_data`]: actix_web::App::app_data
+    pub fn builder_from_app_data() -> CookieMessageStoreBuilder {
+        Self::builder_with_signing_key(SigningKey::AppData)
+    }
+
+    /// Build a [`CookieMessageStoreBuilder`] that signs outgoing flash cookies using a key derived,
+    /// every day, from a master key - see [`DerivedKeyProvider`] for more details.
+    ///
+    /// Keys derived for previous days are used to verify incoming cookies, within the window
+    /// configured via [`DerivedKeyProvider::previous_days`]. Keys registered via
+    /// [`CookieMessageStoreBuilder::verification_key`] are accepted as well - e.g. the static key you
+    /// used before switching to derived keys.
+    ///
+    /// [`CookieMessageStoreBuilder::build`] will **panic** if you also set a key identifier via
+    /// [`CookieMessageStoreBuilder::key_id`]: the date is used as key identifier.
+    pub fn builder_with_derived_keys(provider: DerivedKeyProvider) -> CookieMessageStoreBuilder {
+        Self::builder_with_signing_key(SigningKey::Derived(provider))
+    }
+
+    fn builder_with_signing_key(signing_key: SigningKey) -> CookieMessageStoreBuilder {
         CookieMessageStoreBuilder {
             cookie_name: None,
             signing_key,
+            key_id: None,
+            verification_keys: vec![],
             bytes_size_limit: None,
+            max_chunks: None,
             same_site: None,
             path: None,
             domain: None,
+            expires: None,
+            replay_guard: None,
+            configure_cookie: None,
+            legacy_cookie_prefix: None,
+        }
+    }
+
+    /// Build a [`CookieMessageStoreBuilder`] using a signing key loaded from an environment variable.
+    ///
+    /// The environment variable must contain a base64-encoded (standard alphabet, with padding)
+    /// 64-byte key - e.g. the output of `openssl rand -base64 64 | tr -d '\n'`.
+    ///
+    /// ```rust,no_run
+    /// use actix_web_flash_messages::storage::CookieMessageStore;
+    ///
+    /// let message_store = CookieMessageStore::builder_from_env("FLASH_SIGNING_KEY")
+    ///     .expect("Failed to load the flash messages signing key")
+    ///     .build();
+    /// ```
+    pub fn builder_from_env(var: &str) -> Result<CookieMessageStoreBuilder, KeyError> {
+        let encoded = std::env::var(var)
+            .with_context(|| format!("Failed to read the `{}` environment variable", var))
+            .map_err(KeyError::MissingEnvVar)?;
+        let key = STANDARD
+            .decode(encoded.trim())
+            .with_context(|| {
+                format!(
+                    "The `{}` environment variable does not contain valid base64",
+                    var
+                )
+            })
+            .map_err(KeyError::DecodingError)?;
+        if key.len() != 64 {
+            return Err(KeyError::InvalidLength(anyhow::anyhow!(
+                "The key stored in the `{}` environment variable is {} bytes long, expected 64 bytes",
+                var,
+                key.len()
+            )));
         }
+        Ok(Self::builder(Key::from(&key)))
+    }
+
+    /// Decode a raw flash cookie value - i.e. signed and percent-encoded, as found in a
+    /// `Set-Cookie` or `Cookie` header - outside of a request context.
+    ///
+    /// It comes in handy for tooling - e.g. a CLI to inspect flash cookies captured from logs:
+    ///
+    /// ```rust
+    /// use actix_web::cookie::Key;
+    /// use actix_web_flash_messages::storage::CookieMessageStore;
+    ///
+    /// let store = CookieMessageStore::builder(Key::generate()).build();
+    /// // A value that was not signed with the store key fails to decode.
+    /// let error = store.decode_value("%5B%5D").err().unwrap();
+    /// assert!(error.is_integrity_failure());
+    /// ```
+    ///
+    /// The same checks performed when loading incoming messages apply: the value must be signed
+    /// by one of the keys known to the store.  
+    /// The only exception is the [`ReplayGuard`], if you configured one: it is not consulted.
+    /// Decoding a value does not use it up - the client can still send it - and values that
+    /// have already been used can still be inspected.
+    ///
+    /// It returns [`LoadError::GenericError`] if the signing key is loaded from the application
+    /// data (see [`CookieMessageStore::builder_from_app_data`]) - there is no application to
+    /// look it up from.
+    pub fn decode_value(&self, raw: &str) -> Result<Vec<FlashMessage>, LoadError> {
+        let value = percent_encoding::percent_decode_str(raw)
+            .decode_utf8()
+            .context("The flash cookie value is not valid percent-encoded UTF-8")
+            .map_err(LoadError::DeserializationError)?;
+        let keys = self.resolve_keys(None).map_err(LoadError::GenericError)?;
+        self.decode(
+            &keys,
+            Cookie::new(self.cookie_name.clone(), value.into_owned()),
+            false,
+        )
     }
 
     /// Serialise and percent-encode outgoing flash messages.
@@ -60,57 +226,236 @@ impl CookieMessageStore {
     /// This is extremely wasteful in terms of storage space - quite problematic given that:
     /// - this payload is sent over the wire;
     /// - cookies cannot be bigger than 4096 bytes.
-    fn encode(&self, messages: &[FlashMessage]) -> Result<Cookie<'_>, StoreError> {
-        let serialised = serde_json::to_string(messages)
+    fn encode(
+        &self,
+        keys: &ResolvedKeys<'_>,
+        messages: &[FlashMessage],
+    ) -> Result<Cookie<'_>, StoreError> {
+        let (key_id, signing_key) = &keys.signing;
+        // Pre-size the buffer to avoid repeated re-allocations while serialising.
+        let mut serialised = Vec::with_capacity(estimate_json_size(messages));
+        let nonce = self.replay_guard.as_ref().map(|_| generate_nonce());
+        serde_json::to_writer(&mut serialised, &Envelope::new(messages).with_nonce(nonce))
             .context("Failed to serialise flash messages to JSON.")
             .map_err(StoreError::SerializationError)?;
+        // `serde_json` only ever emits valid UTF-8.
+        let serialised = String::from_utf8(serialised).unwrap();
 
         // Sign the payload **before** doing percent-encoding
         let mut cookie_jar = CookieJar::new();
-        cookie_jar.signed_mut(&self.signing_key).add(
+        cookie_jar.signed_mut(signing_key).add(
             Cookie::build(self.cookie_name.to_owned(), serialised)
                 .same_site(self.same_site)
                 .finish(),
         );
         let signed_cookie = cookie_jar.get(&self.cookie_name).unwrap();
 
-        // Then percent-encode the value and set all relevant cookie properties.
-        let encoded_value =
-            percent_encode(signed_cookie.value().as_bytes(), USERINFO_ENCODE_SET).to_string();
+        // Prefix the signed value with the key identifier, if any, then percent-encode it
+        // straight into the final buffer.
+        let mut encoded_value = String::with_capacity(self.bytes_size_limit as usize);
+        if let Some(key_id) = key_id {
+            encoded_value.extend(percent_encode(key_id.as_bytes(), USERINFO_ENCODE_SET));
+            encoded_value.push(KEY_ID_SEPARATOR);
+        }
+        encoded_value.extend(percent_encode(
+            signed_cookie.value().as_bytes(),
+            USERINFO_ENCODE_SET,
+        ));
         if encoded_value.len() > self.bytes_size_limit as usize {
+            // Point at the biggest offender to make the failure actionable.
+            let (largest_index, largest_length) = messages
+                .iter()
+                .map(|m| m.content().len())
+                .enumerate()
+                .max_by_key(|(_, length)| *length)
+                .unwrap_or_default();
             Err(StoreError::SizeLimitExceeded(anyhow::anyhow!(
-                "The configured maximum cookie size, in bytes, is {}. The serialised and signed outgoing flash messages are {} bytes long.",
+                "The configured maximum cookie size, in bytes, is {}. The serialised and signed outgoing flash messages are {} bytes long. \
+                There are {} outgoing flash messages - the largest one is at index {}, with a content length of {} bytes.",
                 self.bytes_size_limit,
-                encoded_value.len()
+                encoded_value.len(),
+                messages.len(),
+                largest_index,
+                largest_length
             )))
         } else {
-            let mut signed_cookie = Cookie::build(&self.cookie_name, encoded_value)
-                .secure(true)
-                .http_only(true)
-                .same_site(self.same_site)
-                .path(&self.path)
-                .finish();
+            Ok(self.flash_cookie(encoded_value))
+        }
+    }
+
+    /// Build the flash cookie, with all its attributes, around an already-encoded value.
+    fn flash_cookie(&self, encoded_value: String) -> Cookie<'static> {
+        let mut signed_cookie = Cookie::build(self.cookie_name.clone(), encoded_value)
+            .secure(true)
+            .http_only(true)
+            .same_site(self.same_site)
+            .path(self.path.clone());
 
-            if let Some(domain) = &self.domain {
-                signed_cookie.set_domain(domain);
+        if let Some(domain) = &self.domain {
+            signed_cookie = signed_cookie.domain(domain.clone());
+        }
+        if let Some(expires) = self.expires {
+            signed_cookie = signed_cookie.expires(expires);
+        }
+
+        self.configure(signed_cookie).finish()
+    }
+
+    /// Verify and deserialise an incoming flash cookie.
+    ///
+    /// Its nonce is checked against the [`ReplayGuard`], if any, only if `check_replay` is `true`.
+    fn decode(
+        &self,
+        keys: &ResolvedKeys<'_>,
+        cookie: Cookie<'static>,
+        check_replay: bool,
+    ) -> Result<Vec<FlashMessage>, LoadError> {
+        let (candidate_keys, signed_value) = keys.verification_candidates(cookie.value());
+        for key in candidate_keys {
+            let mut cookie_jar = CookieJar::new();
+            cookie_jar.add_original(Cookie::new(
+                self.cookie_name.clone(),
+                signed_value.to_owned(),
+            ));
+            if let Some(cookie) = cookie_jar.signed(key).get(&self.cookie_name) {
+                let payload = serde_json::from_str::<StoredPayload>(cookie.value())
+                    .context("Failed to deserialise the URL-decoded flash messages according to the JSON format")
+                    .map_err(LoadError::DeserializationError)?;
+                if let Some(replay_guard) = self.replay_guard.as_ref().filter(|_| check_replay) {
+                    match payload.nonce() {
+                        Some(nonce) if replay_guard.first_use(nonce) => {}
+                        Some(_) => {
+                            return Err(LoadError::IntegrityCheckFailed(anyhow::anyhow!(
+                                "The incoming flash cookie has already been used"
+                            )))
+                        }
+                        None => {
+                            return Err(LoadError::IntegrityCheckFailed(anyhow::anyhow!(
+                                "The incoming flash cookie does not carry a nonce"
+                            )))
+                        }
+                    }
+                }
+                return payload
+                    .into_messages()
+                    .map_err(LoadError::DeserializationError);
             }
+        }
+        Err(LoadError::IntegrityCheckFailed(anyhow::anyhow!(
+            "Signature validation failed for the cookie storing incoming flash messages"
+        )))
+    }
 
-            Ok(signed_cookie)
+    /// Apply the custom configuration, if any, to a flash cookie.
+    fn configure(&self, cookie: CookieBuilder<'static>) -> CookieBuilder<'static> {
+        match &self.configure_cookie {
+            Some(configure_cookie) => configure_cookie(cookie),
+            None => cookie,
         }
     }
 
-    fn decode(&self, cookie: Cookie<'static>) -> Result<Vec<FlashMessage>, LoadError> {
-        let mut cookie_jar = CookieJar::new();
-        cookie_jar.add_original(cookie);
-        if let Some(cookie) = cookie_jar.signed(&self.signing_key).get(&self.cookie_name) {
-            let messages = serde_json::from_str(cookie.value()).context(
-                "Failed to deserialise the URL-decoded flash messages according to the JSON format",
-            ).map_err(LoadError::DeserializationError)?;
-            Ok(messages)
-        } else {
-            Err(LoadError::IntegrityCheckFailed(anyhow::anyhow!(
-                "Signature validation failed for the cookie storing incoming flash messages"
-            )))
+    /// Collect the legacy cookies attached to `request` - i.e. `<legacy prefix><index>` -
+    /// sorted by index.
+    fn legacy_cookies(&self, request: &HttpRequest) -> Vec<Cookie<'static>> {
+        let (Some(prefix), Ok(cookies)) = (&self.legacy_cookie_prefix, request.cookies()) else {
+            return vec![];
+        };
+        let mut legacy_cookies: Vec<(u64, Cookie<'static>)> = cookies
+            .iter()
+            .filter_map(|cookie| {
+                let index = cookie.name().strip_prefix(prefix.as_str())?.parse().ok()?;
+                Some((index, cookie.clone()))
+            })
+            .collect();
+        legacy_cookies.sort_by_key(|(index, _)| *index);
+        legacy_cookies
+            .into_iter()
+            .map(|(_, cookie)| cookie)
+            .collect()
+    }
+
+    /// Build a cookie that instructs the browser to delete the cookie named `name`.
+    fn removal_cookie(&self, name: String) -> Cookie<'static> {
+        // Browsers ignore `__Secure-` and `__Host-` cookies - removal ones included - unless they
+        // are marked as `Secure`.
+        let secure = has_cookie_prefix(&name);
+        self.configure(
+            Cookie::build(name, "")
+                .same_site(self.same_site)
+                .secure(secure),
+        )
+        // In the future, consider making the `path` configurable - either globally or on a per-endpoint basis
+        .path("/")
+        // Applied after the custom configuration: it must not turn the removal cookie
+        // into a persistent one.
+        .max_age(time::Duration::seconds(0))
+        // Older browsers do not support `Max-Age`: set an `Expires` date in the past as well.
+        .expires(OffsetDateTime::UNIX_EPOCH)
+        .finish()
+    }
+
+    /// Resolve the keys used to sign and verify flash cookies for the current request.
+    fn resolve_keys<'a>(
+        &'a self,
+        request: Option<&'a HttpRequest>,
+    ) -> Result<ResolvedKeys<'a>, anyhow::Error> {
+        let static_keys = self
+            .verification_keys
+            .iter()
+            .map(|(key_id, key)| (Cow::Borrowed(key_id.as_str()), Cow::Borrowed(key)));
+        let signing_key = match &self.signing_key {
+            SigningKey::Owned(key) => key,
+            SigningKey::AppData => request
+                .and_then(|request| request.app_data::<web::Data<Key>>())
+                .map(|key| key.get_ref())
+                .context(
+                    "The flash messages signing key was not found in the application data. \
+                    Register it via `App::app_data(web::Data::new(key))`.",
+                )?,
+            SigningKey::Derived(provider) => {
+                let mut window = provider
+                    .window()
+                    .into_iter()
+                    .map(|(key_id, key)| (Cow::Owned(key_id), Cow::Owned(key)));
+                // The window always starts with today's key.
+                let (key_id, signing_key) = window.next().unwrap();
+                return Ok(ResolvedKeys {
+                    signing: (Some(key_id), signing_key),
+                    verification: window.chain(static_keys).collect(),
+                });
+            }
+        };
+        Ok(ResolvedKeys {
+            signing: (
+                self.key_id.as_deref().map(Cow::Borrowed),
+                Cow::Borrowed(signing_key),
+            ),
+            verification: static_keys.collect(),
+        })
+    }
+
+    /// The names of the `<cookie_name>.<chunk index>` cookies sent by the client.
+    fn chunk_cookie_names(&self, request: &HttpRequest) -> Vec<String> {
+        let Ok(cookies) = request.cookies() else {
+            return vec![];
+        };
+        cookies
+            .iter()
+            .map(|cookie| cookie.name())
+            .filter(|name| *name != self.cookie_name && self.is_flash_cookie_name(name))
+            .map(|name| name.to_owned())
+            .collect()
+    }
+
+    /// Returns `true` for `<cookie_name>` and `<cookie_name>.<chunk index>`.
+    fn is_flash_cookie_name(&self, name: &str) -> bool {
+        match name.strip_prefix(self.cookie_name.as_str()) {
+            Some("") => true,
+            Some(suffix) => suffix
+                .strip_prefix('.')
+                .map(|index| !index.is_empty() && index.bytes().all(|b| b.is_ascii_digit()))
+                .unwrap_or(false),
+            None => false,
         }
     }
 }
@@ -118,11 +463,44 @@ impl CookieMessageStore {
 impl CookieMessageStoreBuilder {
     /// By default, the cookie used to store messages is named `_flash`.  
     /// You can use `cookie_name` to set the name to a custom value.
+    ///
+    /// This method will **panic** if `name` is not a valid cookie name - i.e. if it is empty or
+    /// contains spaces, control characters, non-ASCII characters or separators (e.g. `;`, `=`, `,`).
     pub fn cookie_name(mut self, name: String) -> Self {
+        assert_valid_cookie_name(&name);
         self.cookie_name = Some(name);
         self
     }
 
+    /// Assign an identifier to the signing key.
+    ///
+    /// The identifier is embedded in the flash cookie, allowing [`CookieMessageStore`] to pick the
+    /// right verification key directly when several keys are configured via
+    /// [`CookieMessageStoreBuilder::verification_key`].  
+    /// Keep it short: it counts towards the cookie size limit.
+    ///
+    /// This method will **panic** if `key_id` is empty or contains a `.`.
+    pub fn key_id(mut self, key_id: String) -> Self {
+        assert_valid_key_id(&key_id);
+        self.key_id = Some(key_id);
+        self
+    }
+
+    /// Register an additional key, alongside its identifier, to verify incoming flash cookies.
+    ///
+    /// Outgoing flash cookies are always signed using the key passed to [`CookieMessageStore::builder`]
+    /// (or resolved from the application data, see [`CookieMessageStore::builder_from_app_data`]).
+    /// Use this method to keep accepting cookies signed with a previous key after rotating it.  
+    /// Incoming cookies without a key identifier (e.g. set before you configured
+    /// [`CookieMessageStoreBuilder::key_id`]) are checked against all keys.
+    ///
+    /// This method will **panic** if `key_id` is empty or contains a `.`.
+    pub fn verification_key(mut self, key_id: String, key: Key) -> Self {
+        assert_valid_key_id(&key_id);
+        self.verification_keys.push((key_id, key));
+        self
+    }
+
     /// By default, the cookie used to store flash messages is capped at
     /// 2048 bytes.
     ///
@@ -136,6 +514,17 @@ impl CookieMessageStoreBuilder {
         self
     }
 
+    /// By default, [`CookieMessageStore`] rejects incoming requests carrying more than 8 flash cookies -
+    /// i.e. cookies named after the flash cookie (`_flash`), optionally followed by a `.<chunk index>`
+    /// suffix (`_flash.0`, `_flash.1`, etc.).
+    ///
+    /// This guards against pathological inputs - e.g. a client sending hundreds of chunk cookies.  
+    /// Loading fails with [`LoadError::IntegrityCheckFailed`] when the limit is exceeded.
+    pub fn max_chunks(mut self, max_chunks: usize) -> Self {
+        self.max_chunks = Some(max_chunks);
+        self
+    }
+
     /// By default, the [`Path` attribute](https://developer.mozilla.org/en-US/docs/Web/HTTP/Cookies#define_where_cookies_are_sent) is set to "/".
     pub fn path(mut self, path: String) -> Self {
         self.path = Some(path);
@@ -148,66 +537,417 @@ impl CookieMessageStoreBuilder {
         self
     }
 
+    /// By default, the [`Expires` attribute](https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Set-Cookie#expiresdate)
+    /// is left unset: the flash cookie is a session cookie, removed when the browser is closed.
+    ///
+    /// Use `expires` to set an absolute expiry date for the flash cookie.  
+    /// [`CookieMessageStore`] never sets the `Max-Age` attribute on the flash cookie, which would
+    /// otherwise take precedence over `Expires`.
+    pub fn expires(mut self, expires: OffsetDateTime) -> Self {
+        self.expires = Some(expires);
+        self
+    }
+
+    /// By default, [`CookieMessageStore`] only reads the flash cookie it writes.
+    ///
+    /// Use `legacy_cookie_prefix` when migrating from a framework that stored each flash message
+    /// in its own numbered cookie - e.g. `flash_1`, `flash_2` for a `"flash_"` prefix.  
+    /// Legacy cookies are read alongside the flash cookie: their values become the content of
+    /// [`Level::Info`] messages, in index order, ahead of the messages found in the flash cookie.
+    /// They are never written to: any legacy cookie attached to a request is cleared by the response.
+    ///
+    /// ⚠️ Legacy cookies are **not signed**: their content is untrusted user input and can be
+    /// forged by the client. Don't render it as HTML and remove this option once in-flight
+    /// messages have drained.
+    ///
+    /// This method will **panic** if `prefix` is not a valid cookie name.
+    ///
+    /// [`Level::Info`]: crate::Level::Info
+    pub fn legacy_cookie_prefix(mut self, prefix: String) -> Self {
+        assert_valid_cookie_name(&prefix);
+        self.legacy_cookie_prefix = Some(prefix);
+        self
+    }
+
+    /// By default, flash cookies can be replayed: a client can send the same flash cookie again
+    /// to re-trigger the messages it carries.
+    ///
+    /// Register a [`ReplayGuard`] to prevent replays: each outgoing flash cookie embeds a random,
+    /// single-use nonce and incoming cookies carrying an already-seen nonce are rejected with
+    /// [`LoadError::IntegrityCheckFailed`].
+    ///
+    /// ```rust
+    /// use actix_web_flash_messages::storage::{CookieMessageStore, InMemoryReplayGuard};
+    /// use actix_web::cookie::Key;
+    ///
+    /// let message_store = CookieMessageStore::builder(Key::generate())
+    ///     .replay_guard(InMemoryReplayGuard::default())
+    ///     .build();
+    /// ```
+    ///
+    /// Be mindful of the rollout: flash cookies issued before the guard was registered do not
+    /// carry a nonce and are rejected as well.  
+    /// Incoming messages are loaded at most once per request - peeking at them (e.g. via
+    /// [`FlashMessageCount`]) uses up the nonce: messages that are carried over are stored
+    /// again, with a fresh nonce.
+    ///
+    /// [`FlashMessageCount`]: crate::FlashMessageCount
+    pub fn replay_guard<G: ReplayGuard + 'static>(mut self, replay_guard: G) -> Self {
+        self.replay_guard = Some(Arc::new(replay_guard));
+        self
+    }
+
+    /// By default, the attributes of the flash cookie are determined by the other builder
+    /// methods - e.g. [`CookieMessageStoreBuilder::path`] or [`CookieMessageStoreBuilder::same_site`].
+    ///
+    /// Use `configure_cookie` to customise any attribute supported by the `cookie` crate, including
+    /// those without a dedicated builder method - e.g. dropping `Secure` for local development
+    /// over plain HTTP:
+    ///
+    /// ```rust
+    /// use actix_web_flash_messages::storage::CookieMessageStore;
+    /// use actix_web::cookie::Key;
+    ///
+    /// let message_store = CookieMessageStore::builder(Key::generate())
+    ///     .configure_cookie(|cookie| cookie.secure(false))
+    ///     .build();
+    /// ```
+    ///
+    /// `configure_cookie` is invoked after the other attributes have been set, therefore it can
+    /// override them.  
+    /// It is applied to the removal cookie as well, but the attributes that make it a removal
+    /// cookie (i.e. `Path`, `Max-Age` and `Expires`) take precedence.
+    pub fn configure_cookie<F>(mut self, configure_cookie: F) -> Self
+    where
+        F: Fn(CookieBuilder<'static>) -> CookieBuilder<'static> + Send + Sync + 'static,
+    {
+        self.configure_cookie = Some(Arc::new(configure_cookie));
+        self
+    }
+
     /// By default, the [`SameSite` attribute](https://developer.mozilla.org/en-US/docs/Web/HTTP/Cookies#controlling_third-party_cookies_with_samesite) is `Lax`.
+    ///
+    /// With `Lax`, browsers only attach the flash cookie to cross-site requests if they are
+    /// top-level navigations using a safe method (e.g. `GET`).  
+    /// This matters for `307 Temporary Redirect` and `308 Permanent Redirect`: the browser re-sends
+    /// the original method and body to the new location. If the redirect target lives on a
+    /// different site and the re-issued request is a `POST`, the flash cookie is dropped and the
+    /// messages are lost. Use [`SameSite::None`] in that scenario - the flash cookie is always
+    /// marked as `Secure`, as required by browsers for `SameSite=None` cookies.  
+    /// Same-site redirects are not affected.
     pub fn same_site(mut self, same_site: SameSite) -> Self {
         self.same_site = Some(same_site);
         self
     }
 
     /// Finalise the builder and return a [`CookieMessageStore`] instance.
+    ///
+    /// This method will **panic** if the cookie name starts with one of the
+    /// [cookie prefixes](https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Set-Cookie#cookie_prefixes)
+    /// but the flash cookie does not meet the requirements that browsers enforce for it:
+    ///
+    /// - `__Secure-` cookies must be marked as `Secure`;
+    /// - `__Host-` cookies must be marked as `Secure`, must have `Path=/` and must not have a
+    ///   `Domain` attribute.
+    ///
+    /// Browsers silently reject cookies that violate these rules - your flash messages would
+    /// never be delivered.
     pub fn build(self) -> CookieMessageStore {
-        CookieMessageStore {
+        match self.try_build() {
+            Ok(store) => store,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Like [`CookieMessageStoreBuilder::build`], but it returns an error instead of panicking
+    /// if the configuration is invalid.
+    pub(crate) fn try_build(self) -> Result<CookieMessageStore, anyhow::Error> {
+        if self.key_id.is_some() && matches!(self.signing_key, SigningKey::Derived(_)) {
+            anyhow::bail!(
+                "A key identifier cannot be set when using derived keys: the date is used as key identifier."
+            );
+        }
+        let store = CookieMessageStore {
             cookie_name: self.cookie_name.unwrap_or_else(|| "_flash".to_string()),
             signing_key: self.signing_key,
+            key_id: self.key_id,
+            verification_keys: self.verification_keys,
             bytes_size_limit: self.bytes_size_limit.unwrap_or(2048),
+            max_chunks: self.max_chunks.unwrap_or(8),
             same_site: self.same_site.unwrap_or(SameSite::Lax),
             path: self.path.unwrap_or_else(|| "/".to_string()),
             domain: self.domain,
-        }
+            expires: self.expires,
+            replay_guard: self.replay_guard,
+            configure_cookie: self.configure_cookie,
+            legacy_cookie_prefix: self.legacy_cookie_prefix,
+        };
+        check_cookie_prefix(&store.flash_cookie(String::new()))?;
+        Ok(store)
     }
 }
 
 impl FlashMessageStore for CookieMessageStore {
     fn load(&self, request: &HttpRequest) -> Result<Vec<FlashMessage>, LoadError> {
+        let mut outcome = self.load_lenient(request)?;
+        match outcome.warnings.pop() {
+            Some(e) => Err(e),
+            None => Ok(outcome.messages),
+        }
+    }
+
+    /// A signed flash cookie that fails to decode is reported as a warning, next to the messages
+    /// read from legacy cookies.
+    fn load_lenient(&self, request: &HttpRequest) -> Result<LoadOutcome, LoadError> {
+        let n_flash_cookies = request
+            .cookies()
+            .map(|cookies| {
+                cookies
+                    .iter()
+                    .filter(|c| self.is_flash_cookie_name(c.name()))
+                    .count()
+            })
+            .unwrap_or_default();
+        if n_flash_cookies > self.max_chunks {
+            return Err(LoadError::IntegrityCheckFailed(anyhow::anyhow!(
+                "The incoming request carries {} flash cookies, more than the configured maximum ({})",
+                n_flash_cookies,
+                self.max_chunks
+            )));
+        }
+        let mut messages: Vec<FlashMessage> = self
+            .legacy_cookies(request)
+            .iter()
+            .map(|cookie| FlashMessage::info(cookie.value()))
+            .collect();
+        let mut warnings = vec![];
         if let Some(cookie) = request.cookie(&self.cookie_name) {
-            Ok(self.decode(cookie)?)
-        } else {
-            Ok(vec![])
+            let keys = self
+                .resolve_keys(Some(request))
+                .map_err(LoadError::GenericError)?;
+            match self.decode(&keys, cookie, true) {
+                Ok(signed_messages) => messages.extend(signed_messages),
+                Err(e) => warnings.push(e),
+            }
         }
+        Ok(LoadOutcome { messages, warnings })
     }
 
     fn store(
         &self,
         messages: &[FlashMessage],
-        _request: HttpRequest,
+        request: HttpRequest,
         response_head: &mut ResponseHead,
     ) -> Result<(), StoreError> {
         if !messages.is_empty() {
-            let cookie = self.encode(messages)?;
+            let keys = self
+                .resolve_keys(Some(&request))
+                .map_err(StoreError::GenericError)?;
+            let cookie = self.encode(&keys, messages)?;
 
             response_head
                 .add_cookie(&cookie)
                 .context("Failed to add the flash message cookie to the response")
                 .map_err(StoreError::GenericError)?;
-        } else {
+        } else if request.cookie(&self.cookie_name).is_some() {
             // Make sure to clear up previous flash messages!
             // No need to do this on the other if-branch because we are overwriting
             // any pre-existing cookie with a new value.
-            let removal_cookie = Cookie::build(self.cookie_name.clone(), "")
-                .same_site(self.same_site)
-                .max_age(time::Duration::seconds(0))
-                // In the future, consider making the `path` configurable - either globally or on a per-endpoint basis
-                .path("/")
-                .finish();
+            // We skip it if the client did not send a flash cookie in the first place, to avoid
+            // adding a `Set-Cookie` header to every single response.
+            let removal_cookie = self.removal_cookie(self.cookie_name.clone());
             response_head
                 .add_cookie(&removal_cookie)
                 .context("Failed to add 'removal cookie' for flash message storage to the response")
                 .map_err(StoreError::GenericError)?;
         }
+        // Chunk cookies (`<cookie_name>.<chunk index>`) are never written to either: clear them,
+        // otherwise a client holding more than `max_chunks` of them would be stuck with failing
+        // loads.
+        for chunk_cookie in self.chunk_cookie_names(&request) {
+            let removal_cookie = self.removal_cookie(chunk_cookie);
+            response_head
+                .add_cookie(&removal_cookie)
+                .context("Failed to add 'removal cookie' for a flash cookie chunk to the response")
+                .map_err(StoreError::GenericError)?;
+        }
+        // Legacy cookies are never written to: clear them as soon as we see them.
+        for legacy_cookie in self.legacy_cookies(&request) {
+            let removal_cookie = self.removal_cookie(legacy_cookie.name().to_owned());
+            response_head
+                .add_cookie(&removal_cookie)
+                .context("Failed to add 'removal cookie' for a legacy flash cookie to the response")
+                .map_err(StoreError::GenericError)?;
+        }
         Ok(())
     }
 }
 
+/// Where [`CookieMessageStore`] gets the key used to sign outgoing flash cookies.
+enum SigningKey {
+    Owned(Key),
+    /// Resolved, at request time, from a `web::Data<Key>` registered as application data.
+    AppData,
+    /// Derived, at request time, from a master key and the current date.
+    Derived(DerivedKeyProvider),
+}
+
+/// The keys used by [`CookieMessageStore`] for the current request.
+struct ResolvedKeys<'a> {
+    /// The key used to sign outgoing flash cookies, alongside its identifier (if any).
+    signing: (Option<Cow<'a, str>>, Cow<'a, Key>),
+    /// The other keys accepted when verifying incoming flash cookies, alongside their identifiers.
+    verification: Vec<(Cow<'a, str>, Cow<'a, Key>)>,
+}
+
+impl ResolvedKeys<'_> {
+    /// Determine which keys should be used to verify the signature of an incoming cookie value.
+    ///
+    /// If the value starts with a known key identifier, only the matching key is returned.
+    /// Otherwise (e.g. cookies set before key identifiers were configured) all keys are returned.
+    fn verification_candidates<'a, 'b>(&'a self, value: &'b str) -> (Vec<&'a Key>, &'b str) {
+        let (signing_key_id, signing_key) = &self.signing;
+        let known_keys = signing_key_id
+            .iter()
+            .map(|key_id| (key_id, signing_key))
+            .chain(self.verification.iter().map(|(key_id, key)| (key_id, key)));
+        for (key_id, key) in known_keys {
+            if let Some(signed_value) = value
+                .strip_prefix(key_id.as_ref())
+                .and_then(|v| v.strip_prefix(KEY_ID_SEPARATOR))
+            {
+                return (vec![key.as_ref()], signed_value);
+            }
+        }
+        let all_keys = std::iter::once(signing_key.as_ref())
+            .chain(self.verification.iter().map(|(_, key)| key.as_ref()))
+            .collect();
+        (all_keys, value)
+    }
+}
+
+/// Possible failures modes for [`CookieMessageStore::builder_from_env`].
+#[derive(thiserror::Error, Debug)]
+pub enum KeyError {
+    #[error("The environment variable holding the signing key is missing")]
+    MissingEnvVar(#[source] anyhow::Error),
+    #[error("Failed to base64-decode the signing key")]
+    DecodingError(#[source] anyhow::Error),
+    #[error("The signing key must be exactly 64 bytes long")]
+    InvalidLength(#[source] anyhow::Error),
+}
+
+/// A random, URL-safe, single-use token.
+fn generate_nonce() -> String {
+    let bytes: [u8; 16] = rand::random();
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// A rough estimate of the size of the JSON representation of `messages`,
+/// used to pre-size the serialisation buffer.
+fn estimate_json_size(messages: &[FlashMessage]) -> usize {
+    messages
+        .iter()
+        .map(|m| m.content().len() + m.title().map(str::len).unwrap_or_default() + 32)
+        .sum::<usize>()
+        // The envelope - i.e. `{"v":1,"messages":[]}`.
+        + 22
+}
+
+/// Separates the key identifier from the signed value in the flash cookie.
+///
+/// It never appears in the base64-encoded signature that prefixes a signed value.
+const KEY_ID_SEPARATOR: char = '.';
+
+fn assert_valid_key_id(key_id: &str) {
+    if let Err(e) = check_key_id(key_id) {
+        panic!("{}", e);
+    }
+}
+
+pub(crate) fn check_key_id(key_id: &str) -> Result<(), anyhow::Error> {
+    if key_id.is_empty() || key_id.contains(KEY_ID_SEPARATOR) {
+        anyhow::bail!(
+            "Invalid key identifier, `{}`: it must not be empty and it must not contain a `{}`.",
+            key_id,
+            KEY_ID_SEPARATOR
+        );
+    }
+    Ok(())
+}
+
+fn assert_valid_cookie_name(name: &str) {
+    if let Err(e) = check_cookie_name(name) {
+        panic!("{}", e);
+    }
+}
+
+/// Cookie names must be [tokens](https://datatracker.ietf.org/doc/html/rfc6265#section-4.1.1).
+pub(crate) fn check_cookie_name(name: &str) -> Result<(), anyhow::Error> {
+    const SEPARATORS: &[u8] = b"()<>@,;:\\\"/[]?={}";
+    if name.is_empty() {
+        anyhow::bail!("Invalid cookie name: it must not be empty.");
+    }
+    if let Some(c) = name
+        .chars()
+        .find(|&c| !c.is_ascii_graphic() || SEPARATORS.contains(&(c as u8)))
+    {
+        anyhow::bail!(
+            "Invalid cookie name, `{}`: it contains {:?}. Cookie names must only contain visible \
+            ASCII characters other than separators - e.g. `;`, `=`, `,`.",
+            name,
+            c
+        );
+    }
+    Ok(())
+}
+
+const SECURE_PREFIX: &str = "__Secure-";
+const HOST_PREFIX: &str = "__Host-";
+
+fn has_cookie_prefix(name: &str) -> bool {
+    name.starts_with(SECURE_PREFIX) || name.starts_with(HOST_PREFIX)
+}
+
+/// Browsers only accept `__Secure-` and `__Host-` cookies if they satisfy a set of
+/// [constraints](https://datatracker.ietf.org/doc/html/draft-ietf-httpbis-rfc6265bis#section-4.1.3).
+fn check_cookie_prefix(cookie: &Cookie<'_>) -> Result<(), anyhow::Error> {
+    let name = cookie.name();
+    if !has_cookie_prefix(name) {
+        return Ok(());
+    }
+    if cookie.secure() != Some(true) {
+        anyhow::bail!(
+            "Invalid configuration for the `{}` cookie: cookies using the `{}` or `{}` prefix must be \
+            marked as `Secure`.",
+            name,
+            SECURE_PREFIX,
+            HOST_PREFIX
+        );
+    }
+    if name.starts_with(HOST_PREFIX) {
+        if cookie.path() != Some("/") {
+            anyhow::bail!(
+                "Invalid configuration for the `{}` cookie: cookies using the `{}` prefix must have \
+                `Path=/`, found {:?}.",
+                name,
+                HOST_PREFIX,
+                cookie.path()
+            );
+        }
+        if cookie.domain().is_some() {
+            anyhow::bail!(
+                "Invalid configuration for the `{}` cookie: cookies using the `{}` prefix must not \
+                have a `Domain` attribute, found {:?}.",
+                name,
+                HOST_PREFIX,
+                cookie.domain()
+            );
+        }
+    }
+    Ok(())
+}
+
 /// [Spec](https://url.spec.whatwg.org/#fragment-percent-encode-set)
 const FRAGMENT_ENCODE_SET: &AsciiSet = &percent_encoding::CONTROLS
     .add(b' ')
@@ -0,0 +1,90 @@
+use actix_web::cookie::Key;
+use std::sync::Arc;
+use time::{Date, Duration, OffsetDateTime};
+
+/// Derives a fresh signing key for every day from a single master key, for automatic daily
+/// key rotation.
+///
+/// The key for a given day is derived via HKDF-SHA256 from the master key and the date (UTC).
+/// Register it via [`CookieMessageStore::builder_with_derived_keys`]: outgoing flash cookies are
+/// signed using today's key, while incoming ones are verified using the key of the day they were
+/// issued on - as long as it falls within the verification window.
+///
+/// ```rust
+/// use actix_web_flash_messages::{FlashMessagesFramework, storage::{CookieMessageStore, DerivedKeyProvider}};
+/// use actix_web::cookie::Key;
+///
+/// let master_key = Key::generate();
+/// let message_store = CookieMessageStore::builder_with_derived_keys(
+///     // Accept cookies issued today or over the previous two days.
+///     DerivedKeyProvider::new(master_key).previous_days(2),
+/// )
+/// .build();
+/// let message_framework = FlashMessagesFramework::builder(message_store).build();
+/// ```
+///
+/// The date a cookie was issued on is embedded in the cookie, as its key identifier.
+///
+/// [`CookieMessageStore::builder_with_derived_keys`]: crate::storage::CookieMessageStore::builder_with_derived_keys
+#[derive(Clone)]
+pub struct DerivedKeyProvider {
+    master_key: Key,
+    previous_days: u16,
+    today: Option<Arc<TodayFn>>,
+}
+
+type TodayFn = dyn Fn() -> Date + Send + Sync;
+
+impl DerivedKeyProvider {
+    /// Build a new [`DerivedKeyProvider`] on top of `master_key`.
+    pub fn new(master_key: Key) -> Self {
+        Self {
+            master_key,
+            previous_days: 1,
+            today: None,
+        }
+    }
+
+    /// By default, incoming flash cookies are accepted if they were signed today or yesterday -
+    /// i.e. a cookie issued right before midnight (UTC) can still be read right after it.
+    ///
+    /// Use `previous_days` to change how many previous days are accepted. `0` only accepts
+    /// cookies signed today: cookies in flight at midnight are rejected.
+    pub fn previous_days(mut self, previous_days: u16) -> Self {
+        self.previous_days = previous_days;
+        self
+    }
+
+    /// Override how the current date is determined - it defaults to today's date, in UTC.
+    ///
+    /// It lets you test how your application behaves around the day boundary, deterministically.
+    ///
+    /// It is only available if the `test-util` feature flag is enabled.
+    #[cfg(feature = "test-util")]
+    pub fn today<F>(mut self, today: F) -> Self
+    where
+        F: Fn() -> Date + Send + Sync + 'static,
+    {
+        self.today = Some(Arc::new(today));
+        self
+    }
+
+    /// The signing key for `date`.
+    pub fn key_for(&self, date: Date) -> Key {
+        let date = date.to_string();
+        let input_key_material = [self.master_key.master(), b":", date.as_bytes()].concat();
+        Key::derive_from(&input_key_material)
+    }
+
+    /// The keys accepted today, alongside their identifiers - today's key comes first.
+    pub(crate) fn window(&self) -> Vec<(String, Key)> {
+        let today = match &self.today {
+            Some(today) => today(),
+            None => OffsetDateTime::now_utc().date(),
+        };
+        (0..=i64::from(self.previous_days))
+            .map_while(|days_ago| today.checked_sub(Duration::days(days_ago)))
+            .map(|date| (date.to_string(), self.key_for(date)))
+            .collect()
+    }
+}
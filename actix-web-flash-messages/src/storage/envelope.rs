@@ -0,0 +1,74 @@
+//! The versioned representation of the flash messages persisted by the built-in stores.
+use crate::FlashMessage;
+
+/// The version of the payload written by the current version of this crate.
+pub(crate) const CURRENT_VERSION: u32 = 1;
+
+/// Stored payloads are wrapped in a versioned envelope - e.g. `{"v":1,"messages":[...]}` -
+/// to allow future versions of this crate to change the format without misreading old payloads.
+#[derive(serde::Serialize)]
+pub(crate) struct Envelope<'a> {
+    v: u32,
+    messages: &'a [FlashMessage],
+    /// A single-use token, for stores that guard against replays.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    nonce: Option<String>,
+}
+
+impl<'a> Envelope<'a> {
+    pub(crate) fn new(messages: &'a [FlashMessage]) -> Self {
+        Self {
+            v: CURRENT_VERSION,
+            messages,
+            nonce: None,
+        }
+    }
+
+    #[cfg(feature = "cookies")]
+    pub(crate) fn with_nonce(mut self, nonce: Option<String>) -> Self {
+        self.nonce = nonce;
+        self
+    }
+}
+
+/// All the payload formats we know how to read.
+///
+/// Unknown fields - in the envelope or in the messages - are ignored, so that payloads written by
+/// a newer version of this crate, within the same format version, can still be read.
+/// Do not add `#[serde(deny_unknown_fields)]` here or on [`FlashMessage`].
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+pub(crate) enum StoredPayload {
+    Versioned {
+        v: u32,
+        messages: Vec<FlashMessage>,
+        #[serde(default)]
+        // Only checked by `CookieMessageStore`.
+        #[cfg_attr(not(feature = "cookies"), allow(dead_code))]
+        nonce: Option<String>,
+    },
+    /// A bare sequence of messages, written before the envelope was introduced.
+    Legacy(Vec<FlashMessage>),
+}
+
+impl StoredPayload {
+    #[cfg(feature = "cookies")]
+    pub(crate) fn nonce(&self) -> Option<&str> {
+        match self {
+            StoredPayload::Versioned { nonce, .. } => nonce.as_deref(),
+            StoredPayload::Legacy(_) => None,
+        }
+    }
+
+    pub(crate) fn into_messages(self) -> Result<Vec<FlashMessage>, anyhow::Error> {
+        match self {
+            StoredPayload::Versioned { v, messages, .. } if v == CURRENT_VERSION => Ok(messages),
+            StoredPayload::Versioned { v, .. } => Err(anyhow::anyhow!(
+                "Unsupported flash messages payload version: {} (expected {})",
+                v,
+                CURRENT_VERSION
+            )),
+            StoredPayload::Legacy(messages) => Ok(messages),
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use crate::flash_message::level_from_str;
+use crate::storage::interface::{FlashMessageStore, LoadError, StoreError};
+use crate::FlashMessage;
+use actix_web::dev::ResponseHead;
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::HttpRequest;
+use anyhow::Context;
+use percent_encoding::{percent_decode_str, percent_encode, NON_ALPHANUMERIC};
+
+/// A header-based implementation of flash messages, for APIs.
+///
+/// [`HeaderMessageStore`] attaches each outgoing [`FlashMessage`] to the response as a separate
+/// `X-Flash` header - there is no page to render them on, so clients (e.g. an SDK) are expected to
+/// surface them.  
+/// If you opt in via [`HeaderMessageStore::trust_incoming`], it reads incoming messages from the
+/// `X-Flash` headers of the request, which comes in handy when a service forwards the notices it
+/// received to another one (e.g. in a proxy chain).
+///
+/// # Header format
+///
+/// Each header value has the form `<level>;<content>`:
+///
+/// - `<level>` is one of `debug`, `info`, `success`, `warning` or `error`;
+/// - `<content>` is the UTF-8 message content, percent-encoded - every byte that is not an ASCII
+///   letter or digit is encoded.
+///
+/// E.g. `FlashMessage::info("Hey there!")` is sent as `X-Flash: info;Hey%20there%21`.  
+/// Message order is preserved. Optional fields (e.g. the title) are not transmitted.
+///
+/// # Security
+///
+/// Incoming headers are not signed: any client can set them and have arbitrary notices (e.g. a
+/// fake `error` asking to call a phone number) rendered as if they came from your application.  
+/// Incoming headers are therefore ignored by default. Only enable
+/// [`HeaderMessageStore::trust_incoming`] if your application sits behind a trusted proxy that
+/// strips the `X-Flash` headers sent by clients.
+///
+/// Use either [`HeaderMessageStore::default`] or [`HeaderMessageStore::new`]
+/// to build an instance of [`HeaderMessageStore`].
+#[derive(Clone)]
+pub struct HeaderMessageStore {
+    header_name: HeaderName,
+    trust_incoming: bool,
+}
+
+impl HeaderMessageStore {
+    /// Build a new [`HeaderMessageStore`] and specify which header should be used
+    /// to carry flash messages.
+    pub fn new(header_name: HeaderName) -> Self {
+        Self {
+            header_name,
+            trust_incoming: false,
+        }
+    }
+
+    /// By default, the flash message headers of incoming requests are ignored - no incoming
+    /// message is ever loaded.
+    ///
+    /// Set `trust_incoming` to `true` to read them. Check out the security implications in the
+    /// documentation of [`HeaderMessageStore`] first.
+    pub fn trust_incoming(mut self, trust_incoming: bool) -> Self {
+        self.trust_incoming = trust_incoming;
+        self
+    }
+}
+
+impl Default for HeaderMessageStore {
+    fn default() -> Self {
+        Self::new(HeaderName::from_static("x-flash"))
+    }
+}
+
+impl FlashMessageStore for HeaderMessageStore {
+    fn load(&self, request: &HttpRequest) -> Result<Vec<FlashMessage>, LoadError> {
+        if !self.trust_incoming {
+            return Ok(vec![]);
+        }
+        request
+            .headers()
+            .get_all(&self.header_name)
+            .map(|value| decode(value).map_err(LoadError::DeserializationError))
+            .collect()
+    }
+
+    fn is_one_time(&self) -> bool {
+        false
+    }
+
+    fn store(
+        &self,
+        messages: &[FlashMessage],
+        _request: HttpRequest,
+        response: &mut ResponseHead,
+    ) -> Result<(), StoreError> {
+        for message in messages {
+            let value = format!(
+                "{};{}",
+                message.level(),
+                percent_encode(message.content().as_bytes(), NON_ALPHANUMERIC)
+            );
+            let value = HeaderValue::from_str(&value)
+                .context("Failed to build a flash message header value")
+                .map_err(StoreError::SerializationError)?;
+            response
+                .headers_mut()
+                .append(self.header_name.clone(), value);
+        }
+        Ok(())
+    }
+}
+
+fn decode(value: &HeaderValue) -> Result<FlashMessage, anyhow::Error> {
+    let value = value
+        .to_str()
+        .context("The flash message header value is not valid ASCII")?;
+    let (level, content) = value
+        .split_once(';')
+        .context("The flash message header value is not in the `<level>;<content>` format")?;
+    let level = level_from_str(level)
+        .with_context(|| format!("`{}` is not a valid flash message level", level))?;
+    let content = percent_decode_str(content)
+        .decode_utf8()
+        .context("The flash message content is not valid percent-encoded UTF-8")?;
+    Ok(FlashMessage::new(content.into_owned(), level))
+}
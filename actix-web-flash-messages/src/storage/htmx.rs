@@ -0,0 +1,189 @@
+use crate::storage::interface::{FlashMessageStore, LoadError, StoreError};
+use crate::FlashMessage;
+use actix_web::dev::ResponseHead;
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::HttpRequest;
+use anyhow::Context;
+
+/// An implementation of flash messages for [HTMX](https://htmx.org) applications.
+///
+/// Responses to HTMX requests usually swap a fragment of the page rather than rendering a new one -
+/// there is no layout to render flash messages in.
+/// [`HtmxMessageStore`] attaches outgoing [`FlashMessage`]s to the response as an HTMX trigger
+/// header instead: HTMX fires a client-side event, which you can handle to show a toast.
+///
+/// ```rust
+/// use actix_web_flash_messages::{FlashMessagesFramework, storage::{HtmxMessageStore, HxTriggerTiming}};
+///
+/// let message_store = HtmxMessageStore::default().timing(HxTriggerTiming::AfterSettle);
+/// let message_framework = FlashMessagesFramework::builder(message_store).build();
+/// ```
+///
+/// # Event payload
+///
+/// All the outgoing messages of a response are sent as a single event, named `flash` by default -
+/// see [`HtmxMessageStore::new`]. Each message is a `{level, content}` object:
+///
+/// ```text
+/// HX-Trigger: {"flash":{"messages":[{"level":"info","content":"Hey there!"}]}}
+/// ```
+///
+/// `level` is one of `debug`, `info`, `success`, `warning` or `error`. Optional fields (e.g. the
+/// title) are not transmitted. No header is added if there are no outgoing messages.  
+/// Events set by your request handler on the same header are preserved: the flash event is
+/// merged into them.
+///
+/// HTMX dispatches the event on the element that issued the request, with the payload as the
+/// event `detail` - it bubbles up to `body`:
+///
+/// ```js
+/// document.body.addEventListener("flash", (event) => {
+///     for (const message of event.detail.messages) {
+///         showToast(message.level, message.content);
+///     }
+/// });
+/// ```
+///
+/// # Loading
+///
+/// HTMX events only travel from the server to the client: [`HtmxMessageStore`] never finds
+/// incoming messages.
+///
+/// Use either [`HtmxMessageStore::default`] or [`HtmxMessageStore::new`]
+/// to build an instance of [`HtmxMessageStore`].
+#[derive(Clone)]
+pub struct HtmxMessageStore {
+    event_name: String,
+    timing: HxTriggerTiming,
+}
+
+/// When HTMX fires the client-side event carrying flash messages - see
+/// [`HtmxMessageStore::timing`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum HxTriggerTiming {
+    /// As soon as the response is received, via the `HX-Trigger` header.
+    #[default]
+    Immediate,
+    /// After the new content has been swapped into the page, via the `HX-Trigger-After-Swap`
+    /// header.
+    AfterSwap,
+    /// After the new content has settled (e.g. CSS transitions have been applied), via the
+    /// `HX-Trigger-After-Settle` header.
+    AfterSettle,
+}
+
+impl HxTriggerTiming {
+    /// The response header HTMX reads the event from.
+    pub fn header_name(self) -> HeaderName {
+        match self {
+            HxTriggerTiming::Immediate => HeaderName::from_static("hx-trigger"),
+            HxTriggerTiming::AfterSwap => HeaderName::from_static("hx-trigger-after-swap"),
+            HxTriggerTiming::AfterSettle => HeaderName::from_static("hx-trigger-after-settle"),
+        }
+    }
+}
+
+impl HtmxMessageStore {
+    /// Build a new [`HtmxMessageStore`] and specify the name of the client-side event that
+    /// carries flash messages.
+    pub fn new(event_name: String) -> Self {
+        Self {
+            event_name,
+            timing: HxTriggerTiming::default(),
+        }
+    }
+
+    /// By default, the event is fired as soon as the response is received
+    /// ([`HxTriggerTiming::Immediate`]).
+    ///
+    /// Use `timing` to fire it later in the HTMX lifecycle - e.g. [`HxTriggerTiming::AfterSettle`]
+    /// for toasts that target elements which are part of the swapped content.
+    pub fn timing(mut self, timing: HxTriggerTiming) -> Self {
+        self.timing = timing;
+        self
+    }
+}
+
+impl Default for HtmxMessageStore {
+    fn default() -> Self {
+        Self::new("flash".into())
+    }
+}
+
+impl FlashMessageStore for HtmxMessageStore {
+    fn load(&self, _request: &HttpRequest) -> Result<Vec<FlashMessage>, LoadError> {
+        Ok(vec![])
+    }
+
+    fn is_one_time(&self) -> bool {
+        false
+    }
+
+    fn store(
+        &self,
+        messages: &[FlashMessage],
+        _request: HttpRequest,
+        response: &mut ResponseHead,
+    ) -> Result<(), StoreError> {
+        if messages.is_empty() {
+            return Ok(());
+        }
+        let messages: Vec<_> = messages
+            .iter()
+            .map(|message| {
+                serde_json::json!({
+                    "level": message.level().to_string(),
+                    "content": message.content(),
+                })
+            })
+            .collect();
+        let header_name = self.timing.header_name();
+        let mut events = match response.headers().get(&header_name) {
+            Some(existing) => parse_events(existing),
+            None => serde_json::Map::new(),
+        };
+        events.insert(
+            self.event_name.clone(),
+            serde_json::json!({ "messages": messages }),
+        );
+        let value = serde_json::to_string(&events)
+            .context("Failed to serialise flash messages to JSON.")
+            .map_err(StoreError::SerializationError)?;
+        let value = HeaderValue::from_str(&escape_non_ascii(&value))
+            .context("Failed to build the HTMX trigger header value")
+            .map_err(StoreError::SerializationError)?;
+        response.headers_mut().insert(header_name, value);
+        Ok(())
+    }
+}
+
+/// Parse the events already set by the request handler, either as a JSON object or as a
+/// comma-separated list of event names.
+fn parse_events(value: &HeaderValue) -> serde_json::Map<String, serde_json::Value> {
+    let value = value.to_str().unwrap_or_default().trim();
+    if let Ok(serde_json::Value::Object(events)) = serde_json::from_str(value) {
+        return events;
+    }
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| (name.to_owned(), serde_json::Value::Null))
+        .collect()
+}
+
+/// Header values must be ASCII: non-ASCII characters, which can only appear within JSON strings,
+/// are replaced with `\uXXXX` escape sequences.
+fn escape_non_ascii(json: &str) -> String {
+    let mut escaped = String::with_capacity(json.len());
+    for c in json.chars() {
+        if c.is_ascii() {
+            escaped.push(c);
+        } else {
+            for unit in c.encode_utf16(&mut [0; 2]) {
+                escaped.push_str(&format!("\\u{:04x}", unit));
+            }
+        }
+    }
+    escaped
+}
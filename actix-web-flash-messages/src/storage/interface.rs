@@ -1,6 +1,8 @@
 use crate::FlashMessage;
 use actix_web::dev::ResponseHead;
 use actix_web::HttpRequest;
+use std::future::Future;
+use std::pin::Pin;
 
 /// The interface to retrieve and dispatch flash messages.
 ///
@@ -13,19 +15,215 @@ use actix_web::HttpRequest;
 ///
 /// You can provide your own custom message store backend by implementing this trait.
 ///
+/// # Limitations
+///
+/// A store can only attach messages to the head of the outgoing response - its status and
+/// headers. It has no access to the response body.
+///
+/// As a consequence, HTTP trailers (e.g. gRPC-style trailing metadata) cannot be used as a
+/// transport for flash messages. There is no way to set trailers on a [`ResponseHead`] and
+/// changing the signature of `store` would not help either: `actix-web` 4 (and `actix-http` 3
+/// underneath it) never writes response trailers, for HTTP/1.1 or HTTP/2.
+/// Supporting them would require, on `actix-http`'s side, a way for a `MessageBody` to yield
+/// trailers once its data frames are exhausted. On our side, a second `store` hook that
+/// receives the response body (e.g. `&mut ServiceResponse<B>`) so that it can be wrapped.
+///
 /// [`CookieMessageStore`]: crate::storage::CookieMessageStore
 /// [`SessionMessageStore`]: crate::storage::SessionMessageStore
 pub trait FlashMessageStore: Send + Sync {
     /// Extract flash messages from an incoming request.
     fn load(&self, request: &HttpRequest) -> Result<Vec<FlashMessage>, LoadError>;
 
+    /// Extract flash messages from an incoming request, tolerating non-fatal failures.
+    ///
+    /// Stores that read from several sources (e.g. [`CookieMessageStore`] with a legacy cookie
+    /// prefix, or [`RedirectAwareStore`]) can return the messages they managed to decode
+    /// alongside the errors encountered for the other sources, instead of failing altogether.  
+    /// An `Err` is still returned for failures that prevent loading anything at all.
+    ///
+    /// By default, it delegates to [`FlashMessageStore::load`] and never reports any warning.
+    ///
+    /// [`CookieMessageStore`]: crate::storage::CookieMessageStore
+    /// [`RedirectAwareStore`]: crate::storage::RedirectAwareStore
+    fn load_lenient(&self, request: &HttpRequest) -> Result<LoadOutcome, LoadError> {
+        self.load(request).map(|messages| LoadOutcome {
+            messages,
+            warnings: vec![],
+        })
+    }
+
+    /// Returns `true` if incoming flash messages are removed by [`FlashMessageStore::store`] -
+    /// i.e. each message is shown once.
+    ///
+    /// Some stores are inherently not one-time - e.g. [`QueryMessageStore`] reads messages from
+    /// the URL: they are shown every time the URL is visited. Such stores should override this
+    /// method to return `false`.
+    ///
+    /// It affects how outgoing messages are flushed by [`FlashMessagesFramework`]: incoming
+    /// messages that were not read by the request handler are carried over to the outgoing
+    /// response only for one-time stores. Stores that are not one-time never removed them in the
+    /// first place - carrying them over would deliver them twice.  
+    /// As a consequence, [`FlashMessagesFrameworkBuilder::clear_on_read`] has no effect for
+    /// stores that are not one-time.
+    ///
+    /// By default, it returns `true`.
+    ///
+    /// [`QueryMessageStore`]: crate::storage::QueryMessageStore
+    /// [`FlashMessagesFramework`]: crate::FlashMessagesFramework
+    /// [`FlashMessagesFrameworkBuilder::clear_on_read`]: crate::FlashMessagesFrameworkBuilder::clear_on_read
+    fn is_one_time(&self) -> bool {
+        true
+    }
+
+    /// Invoked by [`FlashMessagesFramework`] when a request comes in, before it reaches the
+    /// request handler - whether or not incoming messages are going to be loaded.
+    ///
+    /// Stores can use it to remember the state they found when the request arrived - e.g.
+    /// [`SessionMessageStore::merge`] records which messages were already in the session.
+    ///
+    /// By default, it does nothing.
+    ///
+    /// [`FlashMessagesFramework`]: crate::FlashMessagesFramework
+    /// [`SessionMessageStore::merge`]: crate::storage::SessionMessageStore::merge
+    fn on_request(&self, request: &HttpRequest) {
+        let _ = request;
+    }
+
+    /// The name of the type of this store - e.g. to check which store has been configured, via the
+    /// `Debug` implementation of [`FlashMessagesFramework`].
+    ///
+    /// By default, it returns the [`std::any::type_name`] of the implementing type. It is resolved
+    /// on the concrete type, therefore it works through an `Arc<dyn FlashMessageStore>` as well.
+    ///
+    /// [`FlashMessagesFramework`]: crate::FlashMessagesFramework
+    fn type_name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
+
     /// Attach flash messages to an outgoing response.
+    ///
+    /// `store` is invoked once the request handler has returned: the status of the outgoing
+    /// response is available as `response.status`.  
+    /// You can rely on it to store messages conditionally - e.g. to skip storage for server errors:
+    ///
+    /// ```rust
+    /// use actix_web::dev::ResponseHead;
+    /// use actix_web::http::StatusCode;
+    /// use actix_web::HttpRequest;
+    /// use actix_web_flash_messages::FlashMessage;
+    /// use actix_web_flash_messages::storage::{FlashMessageStore, HeaderMessageStore, LoadError, StoreError};
+    ///
+    /// struct SkipOnServerError<S>(S);
+    ///
+    /// impl<S: FlashMessageStore> FlashMessageStore for SkipOnServerError<S> {
+    ///     fn load(&self, request: &HttpRequest) -> Result<Vec<FlashMessage>, LoadError> {
+    ///         self.0.load(request)
+    ///     }
+    ///
+    ///     fn store(
+    ///         &self,
+    ///         messages: &[FlashMessage],
+    ///         request: HttpRequest,
+    ///         response: &mut ResponseHead,
+    ///     ) -> Result<(), StoreError> {
+    ///         if response.status.is_server_error() {
+    ///             return Ok(());
+    ///         }
+    ///         self.0.store(messages, request, response)
+    ///     }
+    /// }
+    ///
+    /// let store = SkipOnServerError(HeaderMessageStore::default());
+    /// let mut response = ResponseHead::new(StatusCode::INTERNAL_SERVER_ERROR);
+    /// let request = actix_web::test::TestRequest::default().to_http_request();
+    /// store.store(&[FlashMessage::info("Hey there!")], request, &mut response).unwrap();
+    /// assert!(response.headers().get("x-flash").is_none());
+    /// ```
     fn store(
         &self,
         messages: &[FlashMessage],
         request: HttpRequest,
         response: &mut ResponseHead,
     ) -> Result<(), StoreError>;
+
+    /// Attach flash messages to an outgoing response, asynchronously.
+    ///
+    /// [`FlashMessagesFramework`] awaits the returned future before handing the response over
+    /// to the client. Stores backed by an asynchronous client (e.g. a database or Redis) should
+    /// override this method to write outgoing messages without blocking the executor:
+    ///
+    /// ```rust
+    /// use actix_web::dev::ResponseHead;
+    /// use actix_web::HttpRequest;
+    /// use actix_web_flash_messages::FlashMessage;
+    /// use actix_web_flash_messages::storage::{FlashMessageStore, LoadError, StoreError, StoreFuture};
+    ///
+    /// struct RemoteStore;
+    ///
+    /// impl FlashMessageStore for RemoteStore {
+    ///     fn load(&self, request: &HttpRequest) -> Result<Vec<FlashMessage>, LoadError> {
+    ///         // [...] Read messages that have been prefetched, e.g. by a middleware
+    ///         # Ok(vec![])
+    ///     }
+    ///
+    ///     fn store(
+    ///         &self,
+    ///         _messages: &[FlashMessage],
+    ///         _request: HttpRequest,
+    ///         _response: &mut ResponseHead,
+    ///     ) -> Result<(), StoreError> {
+    ///         Err(StoreError::GenericError(anyhow::anyhow!(
+    ///             "`RemoteStore` only supports asynchronous writes"
+    ///         )))
+    ///     }
+    ///
+    ///     fn store_async<'a>(
+    ///         &'a self,
+    ///         messages: &'a [FlashMessage],
+    ///         request: HttpRequest,
+    ///         response: &'a mut ResponseHead,
+    ///     ) -> StoreFuture<'a> {
+    ///         Box::pin(async move {
+    ///             // [...] e.g. `client.set(session_id, messages).await`
+    ///             Ok(())
+    ///         })
+    ///     }
+    /// }
+    /// ```
+    ///
+    /// By default, it wraps [`FlashMessageStore::store`] in a future that is immediately ready.
+    ///
+    /// [`FlashMessageStore::load`] is still synchronous: extractors load incoming messages
+    /// without yielding. Asynchronous stores have to fetch incoming messages ahead of time -
+    /// e.g. in a middleware wrapped by [`FlashMessagesFramework`] - and read them from the
+    /// request in `load`.  
+    /// The built-in combinators (e.g. [`RedirectAwareStore`]) forward `store_async` to the stores
+    /// they wrap.
+    ///
+    /// [`FlashMessagesFramework`]: crate::FlashMessagesFramework
+    /// [`RedirectAwareStore`]: crate::storage::RedirectAwareStore
+    fn store_async<'a>(
+        &'a self,
+        messages: &'a [FlashMessage],
+        request: HttpRequest,
+        response: &'a mut ResponseHead,
+    ) -> StoreFuture<'a> {
+        Box::pin(std::future::ready(self.store(messages, request, response)))
+    }
+}
+
+/// The future returned by [`FlashMessageStore::store_async`].
+pub type StoreFuture<'a> = Pin<Box<dyn Future<Output = Result<(), StoreError>> + 'a>>;
+
+/// The result of [`FlashMessageStore::load_lenient`]: the messages that were loaded successfully
+/// and the non-fatal failures encountered along the way.
+#[derive(Default)]
+pub struct LoadOutcome {
+    /// The messages that were loaded successfully.
+    pub messages: Vec<FlashMessage>,
+    /// The failures that did not prevent loading `messages` - e.g. a signed flash cookie that
+    /// failed to decode, next to legacy cookies that were read.
+    pub warnings: Vec<LoadError>,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -39,6 +237,24 @@ pub enum LoadError {
     GenericError(#[source] anyhow::Error),
 }
 
+impl LoadError {
+    /// Returns `true` if incoming flash messages could not be deserialized.
+    pub fn is_deserialization(&self) -> bool {
+        matches!(self, LoadError::DeserializationError(_))
+    }
+
+    /// Returns `true` if incoming flash messages failed a cryptographic integrity check -
+    /// e.g. they were tampered with or signed using an unknown key.
+    pub fn is_integrity_failure(&self) -> bool {
+        matches!(self, LoadError::IntegrityCheckFailed(_))
+    }
+
+    /// Returns `true` if loading failed for any other reason.
+    pub fn is_generic(&self) -> bool {
+        matches!(self, LoadError::GenericError(_))
+    }
+}
+
 /// Possible failures modes for [`FlashMessageStore::store`].
 #[derive(thiserror::Error, Debug)]
 pub enum StoreError {
@@ -49,3 +265,20 @@ pub enum StoreError {
     #[error("Something went wrong when flushing outgoing flash messages")]
     GenericError(#[source] anyhow::Error),
 }
+
+impl StoreError {
+    /// Returns `true` if outgoing flash messages could not be serialized.
+    pub fn is_serialization(&self) -> bool {
+        matches!(self, StoreError::SerializationError(_))
+    }
+
+    /// Returns `true` if outgoing flash messages, once serialized, exceeded the store size limit.
+    pub fn is_size_limit_exceeded(&self) -> bool {
+        matches!(self, StoreError::SizeLimitExceeded(_))
+    }
+
+    /// Returns `true` if storing failed for any other reason.
+    pub fn is_generic(&self) -> bool {
+        matches!(self, StoreError::GenericError(_))
+    }
+}
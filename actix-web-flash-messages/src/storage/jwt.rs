@@ -0,0 +1,298 @@
+use crate::storage::interface::{FlashMessageStore, LoadError, StoreError};
+use crate::FlashMessage;
+use actix_web::dev::ResponseHead;
+use actix_web::http::header::{self, HeaderName, HeaderValue};
+use actix_web::web::Query;
+use actix_web::HttpRequest;
+use anyhow::Context;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// The JOSE header of every token minted by [`JwtMessageStore`], base64url-encoded.
+const HS256_HEADER: &str = r#"{"alg":"HS256","typ":"JWT"}"#;
+
+/// A JWT-based implementation of flash messages, to hand messages over from one service to
+/// another - e.g. in a single sign-on flow.
+///
+/// Service A mints a short-lived JSON Web Token carrying its outgoing [`FlashMessage`]s and
+/// appends it to the `Location` of its redirect response. Service B, sharing the same secret,
+/// verifies the token and displays the messages.
+///
+/// ```rust
+/// use actix_web_flash_messages::{FlashMessagesFramework, storage::JwtMessageStore};
+/// use std::time::Duration;
+///
+/// let message_store = JwtMessageStore::builder(b"a-secret-shared-across-services".to_vec())
+///     .ttl(Duration::from_secs(30))
+///     .build();
+/// let message_framework = FlashMessagesFramework::builder(message_store).build();
+/// ```
+///
+/// # Token format
+///
+/// Tokens are signed using HMAC-SHA256 (`HS256`). The messages are stored in the `flash`
+/// claim, next to the standard `iat` and `exp` claims.
+/// Incoming tokens are rejected if they are signed with a different algorithm or if their
+/// signature does not match.  
+/// Expired tokens carry no messages: the URL can still be visited once the token has expired.
+///
+/// # Storing
+///
+/// Outgoing messages are only attached to redirect responses (`3xx` with a `Location` header),
+/// as a query parameter of the target URL. They are dropped for all other responses.
+/// Use [`JwtMessageStore::mint`] to build a token for a different transport.
+///
+/// # Disclaimer
+///
+/// The token is signed, not encrypted: do not use [`JwtMessageStore`] for messages that contain
+/// sensitive information.
+/// Messages are not one-time either - they will be shown every time the URL is visited, until the
+/// token expires. Keep the time-to-live short.
+///
+/// It is only available if the `jwt` feature flag is enabled.
+pub struct JwtMessageStore {
+    secret: Vec<u8>,
+    query_parameter: String,
+    header: Option<HeaderName>,
+    ttl: Duration,
+}
+
+/// A fluent builder to construct a [`JwtMessageStore`] instance.
+pub struct JwtMessageStoreBuilder {
+    secret: Vec<u8>,
+    query_parameter: Option<String>,
+    header: Option<HeaderName>,
+    ttl: Option<Duration>,
+}
+
+#[derive(serde::Serialize)]
+struct OutgoingClaims<'a> {
+    iat: u64,
+    exp: u64,
+    flash: &'a [FlashMessage],
+}
+
+#[derive(serde::Deserialize)]
+struct IncomingClaims {
+    exp: u64,
+    #[serde(default)]
+    flash: Vec<FlashMessage>,
+}
+
+#[derive(serde::Deserialize)]
+struct JoseHeader {
+    alg: String,
+}
+
+impl JwtMessageStore {
+    /// A fluent API to configure [`JwtMessageStore`].
+    ///
+    /// It takes as input the **shared secret** used to sign and verify tokens, the only required
+    /// piece of configuration.
+    pub fn builder(secret: Vec<u8>) -> JwtMessageStoreBuilder {
+        JwtMessageStoreBuilder {
+            secret,
+            query_parameter: None,
+            header: None,
+            ttl: None,
+        }
+    }
+
+    /// Mint a signed token carrying `messages`, valid for the configured time-to-live.
+    pub fn mint(&self, messages: &[FlashMessage]) -> Result<String, StoreError> {
+        let iat = unix_now();
+        let claims = OutgoingClaims {
+            iat,
+            exp: iat.saturating_add(self.ttl.as_secs()),
+            flash: messages,
+        };
+        let claims = serde_json::to_vec(&claims)
+            .context("Failed to serialise flash messages to JSON.")
+            .map_err(StoreError::SerializationError)?;
+        let signing_input = format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(HS256_HEADER),
+            URL_SAFE_NO_PAD.encode(claims)
+        );
+        let signature = self.mac(&signing_input).finalize().into_bytes();
+        Ok(format!(
+            "{}.{}",
+            signing_input,
+            URL_SAFE_NO_PAD.encode(signature)
+        ))
+    }
+
+    fn verify(&self, token: &str) -> Result<Vec<FlashMessage>, LoadError> {
+        let (signing_input, signature) = token
+            .rsplit_once('.')
+            .context("The flash token is not a valid JWT")
+            .map_err(LoadError::DeserializationError)?;
+        let (header, claims) = signing_input
+            .split_once('.')
+            .context("The flash token is not a valid JWT")
+            .map_err(LoadError::DeserializationError)?;
+
+        let header: JoseHeader = decode_segment(header)?;
+        if header.alg != "HS256" {
+            return Err(LoadError::IntegrityCheckFailed(anyhow::anyhow!(
+                "The flash token is signed using an unsupported algorithm, `{}`",
+                header.alg
+            )));
+        }
+        let signature = URL_SAFE_NO_PAD
+            .decode(signature)
+            .context("Failed to base64url-decode the signature of the flash token")
+            .map_err(LoadError::DeserializationError)?;
+        self.mac(signing_input)
+            .verify_slice(&signature)
+            .map_err(|_| {
+                LoadError::IntegrityCheckFailed(anyhow::anyhow!(
+                    "Signature validation failed for the token storing incoming flash messages"
+                ))
+            })?;
+
+        let claims: IncomingClaims = decode_segment(claims)?;
+        // The token is left in the URL: refreshing or bookmarking the page after it expired is
+        // expected, not an attack.
+        if claims.exp <= unix_now() {
+            return Ok(vec![]);
+        }
+        Ok(claims.flash)
+    }
+
+    fn mac(&self, signing_input: &str) -> HmacSha256 {
+        // HMAC accepts keys of any length.
+        let mut mac = HmacSha256::new_from_slice(&self.secret).unwrap();
+        mac.update(signing_input.as_bytes());
+        mac
+    }
+
+    /// Find the incoming token, if any - the header takes precedence over the query parameter.
+    fn incoming_token(&self, request: &HttpRequest) -> Result<Option<String>, LoadError> {
+        if let Some(value) = self
+            .header
+            .as_ref()
+            .and_then(|header| request.headers().get(header))
+        {
+            let token = value
+                .to_str()
+                .context("The flash token header is not valid ASCII")
+                .map_err(LoadError::DeserializationError)?;
+            return Ok(Some(token.to_owned()));
+        }
+        let query = Query::<HashMap<String, String>>::from_query(request.query_string())
+            .context("Failed to parse the query string of the incoming request")
+            .map_err(LoadError::DeserializationError)?;
+        Ok(query.into_inner().remove(&self.query_parameter))
+    }
+}
+
+impl JwtMessageStoreBuilder {
+    /// By default, tokens are read from and written to a query parameter named `_flash_token`.
+    /// You can use `query_parameter` to set the name to a custom value.
+    pub fn query_parameter(mut self, name: String) -> Self {
+        self.query_parameter = Some(name);
+        self
+    }
+
+    /// By default, incoming tokens are only read from the query string.
+    ///
+    /// Use `header` to accept tokens sent in a request header as well - e.g. by a client that
+    /// forwards them from another service. The header takes precedence over the query parameter.
+    pub fn header(mut self, name: HeaderName) -> Self {
+        self.header = Some(name);
+        self
+    }
+
+    /// By default, minted tokens expire 60 seconds after being issued.
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Finalise the builder and return a [`JwtMessageStore`] instance.
+    pub fn build(self) -> JwtMessageStore {
+        JwtMessageStore {
+            secret: self.secret,
+            query_parameter: self
+                .query_parameter
+                .unwrap_or_else(|| "_flash_token".to_string()),
+            header: self.header,
+            ttl: self.ttl.unwrap_or(Duration::from_secs(60)),
+        }
+    }
+}
+
+impl FlashMessageStore for JwtMessageStore {
+    fn load(&self, request: &HttpRequest) -> Result<Vec<FlashMessage>, LoadError> {
+        match self.incoming_token(request)? {
+            Some(token) => self.verify(&token),
+            None => Ok(vec![]),
+        }
+    }
+
+    fn is_one_time(&self) -> bool {
+        false
+    }
+
+    fn store(
+        &self,
+        messages: &[FlashMessage],
+        _request: HttpRequest,
+        response_head: &mut ResponseHead,
+    ) -> Result<(), StoreError> {
+        if messages.is_empty() || !response_head.status.is_redirection() {
+            return Ok(());
+        }
+        let location = match response_head
+            .headers()
+            .get(header::LOCATION)
+            .and_then(|l| l.to_str().ok())
+        {
+            Some(location) => location.to_owned(),
+            None => return Ok(()),
+        };
+        let token = self.mint(messages)?;
+        // The token must go in the query string, ahead of the fragment (if any).
+        let (target, fragment) = match location.split_once('#') {
+            Some((target, fragment)) => (target, Some(fragment)),
+            None => (location.as_str(), None),
+        };
+        let separator = if target.contains('?') { '&' } else { '?' };
+        let mut location = format!("{}{}{}={}", target, separator, self.query_parameter, token);
+        if let Some(fragment) = fragment {
+            location.push('#');
+            location.push_str(fragment);
+        }
+        let location = HeaderValue::from_str(&location)
+            .context("The `Location` header, with the flash token, is not a valid header value")
+            .map_err(StoreError::GenericError)?;
+        response_head
+            .headers_mut()
+            .insert(header::LOCATION, location);
+        Ok(())
+    }
+}
+
+fn decode_segment<T: serde::de::DeserializeOwned>(segment: &str) -> Result<T, LoadError> {
+    let bytes = URL_SAFE_NO_PAD
+        .decode(segment)
+        .context("Failed to base64url-decode a segment of the flash token")
+        .map_err(LoadError::DeserializationError)?;
+    serde_json::from_slice(&bytes)
+        .context("Failed to deserialise a segment of the flash token according to the JSON format")
+        .map_err(LoadError::DeserializationError)
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
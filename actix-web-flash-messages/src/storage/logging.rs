@@ -0,0 +1,145 @@
+use crate::storage::{FlashMessageStore, LoadError, LoadOutcome, StoreError, StoreFuture};
+use crate::{FlashMessage, Level};
+use actix_web::dev::ResponseHead;
+use actix_web::HttpRequest;
+
+/// A message store combinator that emits a log line, via `tracing`, every time flash messages
+/// are loaded or stored - e.g. to keep an audit trail of the notifications shown to your users.
+///
+/// It requires the `tracing` feature flag.
+///
+/// [`LoggingStore`] delegates storage to an inner [`FlashMessageStore`].
+/// Each log line records the number of messages and their [`Level`]s.
+///
+/// ```rust
+/// use actix_web_flash_messages::{FlashMessagesFramework, storage::{CookieMessageStore, LoggingStore}};
+/// use actix_web::cookie::Key;
+///
+/// let cookie_store = CookieMessageStore::builder(Key::generate()).build();
+/// let message_store = LoggingStore::new(cookie_store);
+/// let message_framework = FlashMessagesFramework::builder(message_store).build();
+/// ```
+///
+/// # Privacy
+///
+/// Flash messages often contain user data (e.g. "An email was sent to jane@example.com").  
+/// By default, [`LoggingStore`] does **not** log the content of flash messages: use
+/// [`LoggingStore::log_content`] to opt in.
+pub struct LoggingStore<S> {
+    inner: S,
+    log_content: bool,
+}
+
+impl<S: FlashMessageStore> LoggingStore<S> {
+    /// Build a new [`LoggingStore`] delegating storage to `inner`.
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            log_content: false,
+        }
+    }
+
+    /// By default, the content of flash messages is not logged.
+    /// You can use `log_content` to include it in every log line.
+    pub fn log_content(mut self, log_content: bool) -> Self {
+        self.log_content = log_content;
+        self
+    }
+
+    fn contents<'a>(&self, messages: &'a [FlashMessage]) -> Option<Vec<&'a str>> {
+        self.log_content
+            .then(|| messages.iter().map(|m| m.content()).collect())
+    }
+
+    fn log_loaded(&self, messages: &[FlashMessage]) {
+        tracing::info!(
+            count = messages.len(),
+            levels = ?levels(messages),
+            contents = ?self.contents(messages),
+            "Loaded incoming flash messages"
+        );
+    }
+
+    fn log_store_outcome(&self, messages: &[FlashMessage], outcome: &Result<(), StoreError>) {
+        match outcome {
+            Ok(()) => tracing::info!(
+                count = messages.len(),
+                levels = ?levels(messages),
+                contents = ?self.contents(messages),
+                "Stored outgoing flash messages"
+            ),
+            Err(e) => tracing::warn!(
+                error = %e,
+                count = messages.len(),
+                "Failed to store outgoing flash messages"
+            ),
+        }
+    }
+}
+
+impl<S: FlashMessageStore> FlashMessageStore for LoggingStore<S> {
+    fn load(&self, request: &HttpRequest) -> Result<Vec<FlashMessage>, LoadError> {
+        match self.inner.load(request) {
+            Ok(messages) => {
+                self.log_loaded(&messages);
+                Ok(messages)
+            }
+            Err(e) => {
+                tracing::warn!(error = %e, "Failed to load incoming flash messages");
+                Err(e)
+            }
+        }
+    }
+
+    fn load_lenient(&self, request: &HttpRequest) -> Result<LoadOutcome, LoadError> {
+        match self.inner.load_lenient(request) {
+            Ok(outcome) => {
+                self.log_loaded(&outcome.messages);
+                for warning in &outcome.warnings {
+                    tracing::warn!(error = %warning, "Failed to load some incoming flash messages");
+                }
+                Ok(outcome)
+            }
+            Err(e) => {
+                tracing::warn!(error = %e, "Failed to load incoming flash messages");
+                Err(e)
+            }
+        }
+    }
+
+    fn is_one_time(&self) -> bool {
+        self.inner.is_one_time()
+    }
+
+    fn on_request(&self, request: &HttpRequest) {
+        self.inner.on_request(request)
+    }
+
+    fn store(
+        &self,
+        messages: &[FlashMessage],
+        request: HttpRequest,
+        response: &mut ResponseHead,
+    ) -> Result<(), StoreError> {
+        let outcome = self.inner.store(messages, request, response);
+        self.log_store_outcome(messages, &outcome);
+        outcome
+    }
+
+    fn store_async<'a>(
+        &'a self,
+        messages: &'a [FlashMessage],
+        request: HttpRequest,
+        response: &'a mut ResponseHead,
+    ) -> StoreFuture<'a> {
+        Box::pin(async move {
+            let outcome = self.inner.store_async(messages, request, response).await;
+            self.log_store_outcome(messages, &outcome);
+            outcome
+        })
+    }
+}
+
+fn levels(messages: &[FlashMessage]) -> Vec<Level> {
+    messages.iter().map(|m| m.level()).collect()
+}
@@ -2,11 +2,61 @@
 mod interface;
 
 #[cfg(feature = "cookies")]
-pub use cookies::{CookieMessageStore, CookieMessageStoreBuilder};
+pub(crate) use cookies::{check_cookie_name, check_key_id};
+#[cfg(feature = "cookies")]
+pub use cookies::{CookieMessageStore, CookieMessageStoreBuilder, KeyError};
 #[cfg(feature = "cookies")]
 mod cookies;
 
-pub use interface::{FlashMessageStore, LoadError, StoreError};
+#[cfg(feature = "cookies")]
+pub use derived_key::DerivedKeyProvider;
+#[cfg(feature = "cookies")]
+mod derived_key;
+
+#[cfg(feature = "cookies")]
+pub use replay::{InMemoryReplayGuard, ReplayGuard};
+#[cfg(feature = "cookies")]
+mod replay;
+
+#[cfg(feature = "cookies")]
+pub use query::{QueryMessageStore, QueryMessageStoreBuilder};
+#[cfg(feature = "cookies")]
+mod query;
+
+pub use interface::{FlashMessageStore, LoadError, LoadOutcome, StoreError, StoreFuture};
+
+#[cfg(feature = "jwt")]
+pub use jwt::{JwtMessageStore, JwtMessageStoreBuilder};
+#[cfg(feature = "jwt")]
+mod jwt;
+
+#[cfg(any(feature = "cookies", feature = "sessions"))]
+mod envelope;
+
+mod headers;
+pub use headers::HeaderMessageStore;
+
+mod htmx;
+pub use htmx::{HtmxMessageStore, HxTriggerTiming};
+
+#[cfg(feature = "test-util")]
+mod capturing;
+#[cfg(feature = "test-util")]
+pub use capturing::CapturingMessageStore;
+
+#[cfg(feature = "tracing")]
+mod logging;
+#[cfg(feature = "tracing")]
+pub use logging::LoggingStore;
+
+mod redirect_aware;
+pub use redirect_aware::RedirectAwareStore;
+
+mod tee;
+pub use tee::TeeStore;
+
+mod rate_limited;
+pub use rate_limited::{RateLimitedStore, RateLimitedStoreBuilder};
 
 #[cfg(feature = "sessions")]
 mod sessions;
@@ -0,0 +1,139 @@
+use crate::storage::interface::{FlashMessageStore, LoadError, StoreError};
+use crate::FlashMessage;
+use actix_web::cookie::{Cookie, CookieJar, Key};
+use actix_web::dev::ResponseHead;
+use actix_web::web::Query;
+use actix_web::HttpRequest;
+use anyhow::Context;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use std::collections::HashMap;
+
+/// A query-parameter-based implementation of flash messages, for GET-driven flows.
+///
+/// [`QueryMessageStore`] reads incoming [`FlashMessage`]s from a signed, base64url-encoded
+/// query parameter - e.g. an email confirmation link pointing to `/login?_flash=<payload>`
+/// to show "Email confirmed!" once the user lands on the login page.
+/// Use [`QueryMessageStore::encode`] to build the query parameter value.
+///
+/// [`QueryMessageStore`] never attaches outgoing messages to a response: calling
+/// [`FlashMessage::send`] has no effect when it is the configured store.
+///
+/// Use [`QueryMessageStore::builder`] to build an instance of [`QueryMessageStore`]!
+///
+/// # Disclaimer
+///
+/// Query parameters are part of the URL: flash messages will be visible in server logs,
+/// browser history and `Referer` headers.
+/// The payload is signed, not encrypted: do not use [`QueryMessageStore`] for messages that
+/// contain sensitive information.
+/// Messages are not one-time either - they will be shown every time the URL is visited.
+pub struct QueryMessageStore {
+    query_parameter: String,
+    signing_key: Key,
+}
+
+/// A fluent builder to construct a [`QueryMessageStore`] instance.
+pub struct QueryMessageStoreBuilder {
+    query_parameter: Option<String>,
+    signing_key: Key,
+}
+
+impl QueryMessageStore {
+    /// A fluent API to configure [`QueryMessageStore`].
+    ///
+    /// It takes as input a **signing key**, the only required piece of configuration.
+    /// The query parameter used to carry flash messages is signed - this ensures that flash
+    /// messages were authored by the application and were not tampered with.
+    pub fn builder(signing_key: Key) -> QueryMessageStoreBuilder {
+        QueryMessageStoreBuilder {
+            query_parameter: None,
+            signing_key,
+        }
+    }
+
+    /// Serialise, sign and base64url-encode flash messages, returning a value suitable
+    /// for the query parameter read by this store.
+    pub fn encode(&self, messages: &[FlashMessage]) -> Result<String, StoreError> {
+        let serialised = serde_json::to_string(messages)
+            .context("Failed to serialise flash messages to JSON.")
+            .map_err(StoreError::SerializationError)?;
+
+        let mut cookie_jar = CookieJar::new();
+        cookie_jar
+            .signed_mut(&self.signing_key)
+            .add(Cookie::new(self.query_parameter.clone(), serialised));
+        let signed_value = cookie_jar.get(&self.query_parameter).unwrap().value();
+        Ok(URL_SAFE_NO_PAD.encode(signed_value))
+    }
+
+    fn decode(&self, value: &str) -> Result<Vec<FlashMessage>, LoadError> {
+        let signed_value = URL_SAFE_NO_PAD
+            .decode(value)
+            .context("Failed to base64url-decode the flash messages query parameter")
+            .and_then(|v| {
+                String::from_utf8(v)
+                    .context("The flash messages query parameter is not valid UTF-8 once decoded")
+            })
+            .map_err(LoadError::DeserializationError)?;
+
+        let mut cookie_jar = CookieJar::new();
+        cookie_jar.add_original(Cookie::new(self.query_parameter.clone(), signed_value));
+        if let Some(cookie) = cookie_jar
+            .signed(&self.signing_key)
+            .get(&self.query_parameter)
+        {
+            let messages = serde_json::from_str(cookie.value())
+                .context("Failed to deserialise the flash messages query parameter according to the JSON format")
+                .map_err(LoadError::DeserializationError)?;
+            Ok(messages)
+        } else {
+            Err(LoadError::IntegrityCheckFailed(anyhow::anyhow!(
+                "Signature validation failed for the query parameter storing incoming flash messages"
+            )))
+        }
+    }
+}
+
+impl QueryMessageStoreBuilder {
+    /// By default, the query parameter used to carry messages is named `_flash`.
+    /// You can use `query_parameter` to set the name to a custom value.
+    pub fn query_parameter(mut self, name: String) -> Self {
+        self.query_parameter = Some(name);
+        self
+    }
+
+    /// Finalise the builder and return a [`QueryMessageStore`] instance.
+    pub fn build(self) -> QueryMessageStore {
+        QueryMessageStore {
+            query_parameter: self.query_parameter.unwrap_or_else(|| "_flash".to_string()),
+            signing_key: self.signing_key,
+        }
+    }
+}
+
+impl FlashMessageStore for QueryMessageStore {
+    fn load(&self, request: &HttpRequest) -> Result<Vec<FlashMessage>, LoadError> {
+        let query = Query::<HashMap<String, String>>::from_query(request.query_string())
+            .context("Failed to parse the query string of the incoming request")
+            .map_err(LoadError::DeserializationError)?;
+        if let Some(value) = query.get(&self.query_parameter) {
+            self.decode(value)
+        } else {
+            Ok(vec![])
+        }
+    }
+
+    fn is_one_time(&self) -> bool {
+        false
+    }
+
+    fn store(
+        &self,
+        _messages: &[FlashMessage],
+        _request: HttpRequest,
+        _response_head: &mut ResponseHead,
+    ) -> Result<(), StoreError> {
+        Ok(())
+    }
+}
@@ -0,0 +1,246 @@
+use crate::incoming::consumed_flash_messages;
+use crate::storage::{FlashMessageStore, LoadError, LoadOutcome, StoreError, StoreFuture};
+use crate::{FlashMessage, Level};
+use actix_web::dev::ResponseHead;
+use actix_web::HttpRequest;
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// A message store combinator that suppresses duplicate flash messages shown to the same
+/// client within a configurable time window.
+///
+/// [`RateLimitedStore`] delegates storage to an inner [`FlashMessageStore`].
+/// It remembers - per client - when a message (identified by its content and [`Level`]) was last
+/// shown: if the same message is loaded again within the configured window it is dropped.  
+/// A message counts as shown only if the request handler has read incoming messages (e.g. via
+/// [`IncomingFlashMessages`]): peeking at them (e.g. via [`FlashMessageCount`]) does not count.
+/// It is a good fit for "don't show the same warning more than once per minute" semantics.
+///
+/// Clients are identified using the function passed to [`RateLimitedStore::builder`].
+/// Requests for which it returns `None` are never rate-limited.
+///
+/// ```rust
+/// use actix_web_flash_messages::{FlashMessagesFramework, storage::{CookieMessageStore, RateLimitedStore}};
+/// use actix_web::cookie::Key;
+/// use std::time::Duration;
+///
+/// let cookie_store = CookieMessageStore::builder(Key::generate()).build();
+/// let message_store = RateLimitedStore::builder(cookie_store, |request| {
+///     request
+///         .headers()
+///         .get("X-Client-Id")
+///         .and_then(|h| h.to_str().ok())
+///         .map(|s| s.to_owned())
+/// })
+/// .window(Duration::from_secs(60))
+/// .build();
+/// let message_framework = FlashMessagesFramework::builder(message_store).build();
+/// ```
+///
+/// # Disclaimer
+///
+/// The bookkeeping is kept in memory, in a least-recently-used cache bounded by
+/// [`RateLimitedStoreBuilder::cache_size`].
+/// It is not shared across different instances of your application.
+///
+/// [`IncomingFlashMessages`]: crate::IncomingFlashMessages
+/// [`FlashMessageCount`]: crate::FlashMessageCount
+pub struct RateLimitedStore<S> {
+    inner: S,
+    client_id: Box<ClientIdFn>,
+    window: Duration,
+    cache: Mutex<ClientCache>,
+}
+
+type ClientIdFn = dyn Fn(&HttpRequest) -> Option<String> + Send + Sync;
+
+/// A fluent builder to construct a [`RateLimitedStore`] instance.
+pub struct RateLimitedStoreBuilder<S> {
+    inner: S,
+    client_id: Box<ClientIdFn>,
+    window: Option<Duration>,
+    cache_size: Option<usize>,
+}
+
+impl<S: FlashMessageStore> RateLimitedStore<S> {
+    /// A fluent API to configure [`RateLimitedStore`].
+    ///
+    /// It takes as input the message store to delegate to and a function to
+    /// determine the client id of an incoming request.
+    pub fn builder<F>(inner: S, client_id: F) -> RateLimitedStoreBuilder<S>
+    where
+        F: Fn(&HttpRequest) -> Option<String> + Send + Sync + 'static,
+    {
+        RateLimitedStoreBuilder {
+            inner,
+            client_id: Box::new(client_id),
+            window: None,
+            cache_size: None,
+        }
+    }
+}
+
+impl<S: FlashMessageStore> RateLimitedStoreBuilder<S> {
+    /// By default, a message is not shown again to the same client for 60 seconds.
+    /// You can use `window` to set a custom duration.
+    pub fn window(mut self, window: Duration) -> Self {
+        self.window = Some(window);
+        self
+    }
+
+    /// By default, [`RateLimitedStore`] keeps track of up to 1024 clients.
+    /// When the limit is reached, the least recently seen client is evicted.
+    pub fn cache_size(mut self, cache_size: usize) -> Self {
+        self.cache_size = Some(cache_size);
+        self
+    }
+
+    /// Finalise the builder and return a [`RateLimitedStore`] instance.
+    pub fn build(self) -> RateLimitedStore<S> {
+        RateLimitedStore {
+            inner: self.inner,
+            client_id: self.client_id,
+            window: self.window.unwrap_or_else(|| Duration::from_secs(60)),
+            cache: Mutex::new(ClientCache::new(self.cache_size.unwrap_or(1024))),
+        }
+    }
+}
+
+impl<S> RateLimitedStore<S> {
+    /// Drop the messages that have already been shown to the client of `request` within the
+    /// configured window.
+    fn filter(&self, request: &HttpRequest, messages: Vec<FlashMessage>) -> Vec<FlashMessage> {
+        let client_id = match (self.client_id)(request) {
+            Some(client_id) => client_id,
+            None => return messages,
+        };
+        let now = Instant::now();
+        let mut cache = self.cache.lock().unwrap();
+        let last_shown = cache.get_or_insert(client_id, now, self.window);
+        // Duplicates within the same batch are dropped as well.
+        let mut seen = HashSet::new();
+        messages
+            .into_iter()
+            .filter(|m| {
+                let key = (m.content().to_owned(), m.level());
+                !last_shown.contains_key(&key) && seen.insert(key)
+            })
+            .collect()
+    }
+
+    /// Remember the incoming messages read by the request handler as shown to the client of
+    /// `request`.
+    ///
+    /// Messages that have only been peeked at are carried over to the next request: they have
+    /// not been shown yet.
+    fn record_shown(&self, request: &HttpRequest) {
+        let shown = match consumed_flash_messages(request) {
+            Some(shown) if !shown.is_empty() => shown,
+            _ => return,
+        };
+        let client_id = match (self.client_id)(request) {
+            Some(client_id) => client_id,
+            None => return,
+        };
+        let now = Instant::now();
+        let mut cache = self.cache.lock().unwrap();
+        let last_shown = cache.get_or_insert(client_id, now, self.window);
+        for m in shown {
+            last_shown.insert((m.content().to_owned(), m.level()), now);
+        }
+    }
+}
+
+impl<S: FlashMessageStore> FlashMessageStore for RateLimitedStore<S> {
+    fn load(&self, request: &HttpRequest) -> Result<Vec<FlashMessage>, LoadError> {
+        let messages = self.inner.load(request)?;
+        Ok(self.filter(request, messages))
+    }
+
+    fn load_lenient(&self, request: &HttpRequest) -> Result<LoadOutcome, LoadError> {
+        let mut outcome = self.inner.load_lenient(request)?;
+        outcome.messages = self.filter(request, outcome.messages);
+        Ok(outcome)
+    }
+
+    fn is_one_time(&self) -> bool {
+        self.inner.is_one_time()
+    }
+
+    fn on_request(&self, request: &HttpRequest) {
+        self.inner.on_request(request)
+    }
+
+    fn store(
+        &self,
+        messages: &[FlashMessage],
+        request: HttpRequest,
+        response: &mut ResponseHead,
+    ) -> Result<(), StoreError> {
+        self.record_shown(&request);
+        self.inner.store(messages, request, response)
+    }
+
+    fn store_async<'a>(
+        &'a self,
+        messages: &'a [FlashMessage],
+        request: HttpRequest,
+        response: &'a mut ResponseHead,
+    ) -> StoreFuture<'a> {
+        self.record_shown(&request);
+        self.inner.store_async(messages, request, response)
+    }
+}
+
+/// A least-recently-used cache of the messages shown to each client.
+struct ClientCache {
+    capacity: usize,
+    entries: HashMap<String, ClientEntry>,
+}
+
+struct ClientEntry {
+    last_seen: Instant,
+    last_shown: HashMap<(String, Level), Instant>,
+}
+
+impl ClientCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: HashMap::new(),
+        }
+    }
+
+    /// Messages shown outside of `window` no longer affect filtering: they are pruned, to keep
+    /// the memory used by long-lived clients bounded.
+    fn get_or_insert(
+        &mut self,
+        client_id: String,
+        now: Instant,
+        window: Duration,
+    ) -> &mut HashMap<(String, Level), Instant> {
+        if !self.entries.contains_key(&client_id) && self.entries.len() >= self.capacity {
+            let lru = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_seen)
+                .map(|(id, _)| id.clone());
+            if let Some(lru) = lru {
+                self.entries.remove(&lru);
+            }
+        }
+        let entry = self
+            .entries
+            .entry(client_id)
+            .or_insert_with(|| ClientEntry {
+                last_seen: now,
+                last_shown: HashMap::new(),
+            });
+        entry.last_seen = now;
+        entry
+            .last_shown
+            .retain(|_, t| now.duration_since(*t) < window);
+        &mut entry.last_shown
+    }
+}
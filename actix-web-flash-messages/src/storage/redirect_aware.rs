@@ -0,0 +1,116 @@
+use crate::storage::{FlashMessageStore, LoadError, LoadOutcome, StoreError, StoreFuture};
+use crate::FlashMessage;
+use actix_web::dev::ResponseHead;
+use actix_web::HttpRequest;
+
+/// A message store combinator that picks a backend based on the status of the outgoing response.
+///
+/// [`RedirectAwareStore`] stores outgoing messages using the "redirect" store for `3xx` responses -
+/// e.g. a lightweight [`CookieMessageStore`] for the common "post, flash, redirect" flow - and the
+/// "fallback" store for all other responses - e.g. a [`SessionMessageStore`].  
+/// The store that did not receive the outgoing messages is cleared.
+///
+/// ```rust
+/// use actix_web_flash_messages::{FlashMessagesFramework, storage::{CookieMessageStore, RedirectAwareStore, SessionMessageStore}};
+/// use actix_web::cookie::Key;
+///
+/// let cookie_store = CookieMessageStore::builder(Key::generate()).build();
+/// let message_store = RedirectAwareStore::new(cookie_store, SessionMessageStore::default());
+/// let message_framework = FlashMessagesFramework::builder(message_store).build();
+/// ```
+///
+/// # Loading
+///
+/// Incoming messages are loaded **before** the response status is known: there is no way to tell
+/// which store holds them.  
+/// [`RedirectAwareStore`] therefore consults both stores, returning the messages found in the
+/// redirect store followed by those found in the fallback store.
+///
+/// [`CookieMessageStore`]: crate::storage::CookieMessageStore
+/// [`SessionMessageStore`]: crate::storage::SessionMessageStore
+pub struct RedirectAwareStore<R, F> {
+    redirect: R,
+    fallback: F,
+}
+
+impl<R: FlashMessageStore, F: FlashMessageStore> RedirectAwareStore<R, F> {
+    /// Build a new [`RedirectAwareStore`].
+    ///
+    /// `redirect` is used for `3xx` responses, `fallback` for all other responses.
+    pub fn new(redirect: R, fallback: F) -> Self {
+        Self { redirect, fallback }
+    }
+}
+
+impl<R: FlashMessageStore, F: FlashMessageStore> FlashMessageStore for RedirectAwareStore<R, F> {
+    fn load(&self, request: &HttpRequest) -> Result<Vec<FlashMessage>, LoadError> {
+        let mut messages = self.redirect.load(request)?;
+        messages.extend(self.fallback.load(request)?);
+        Ok(messages)
+    }
+
+    /// A failure to load from one of the two stores is reported as a warning, next to the
+    /// messages loaded from the other one. It only fails if both stores fail.
+    fn load_lenient(&self, request: &HttpRequest) -> Result<LoadOutcome, LoadError> {
+        match (
+            self.redirect.load_lenient(request),
+            self.fallback.load_lenient(request),
+        ) {
+            (Ok(mut outcome), Ok(fallback)) => {
+                outcome.messages.extend(fallback.messages);
+                outcome.warnings.extend(fallback.warnings);
+                Ok(outcome)
+            }
+            (Ok(mut outcome), Err(e)) | (Err(e), Ok(mut outcome)) => {
+                outcome.warnings.push(e);
+                Ok(outcome)
+            }
+            (Err(e), Err(_)) => Err(e),
+        }
+    }
+
+    fn is_one_time(&self) -> bool {
+        self.redirect.is_one_time() && self.fallback.is_one_time()
+    }
+
+    fn on_request(&self, request: &HttpRequest) {
+        self.redirect.on_request(request);
+        self.fallback.on_request(request);
+    }
+
+    fn store(
+        &self,
+        messages: &[FlashMessage],
+        request: HttpRequest,
+        response: &mut ResponseHead,
+    ) -> Result<(), StoreError> {
+        if response.status.is_redirection() {
+            self.redirect.store(messages, request.clone(), response)?;
+            self.fallback.store(&[], request, response)
+        } else {
+            self.redirect.store(&[], request.clone(), response)?;
+            self.fallback.store(messages, request, response)
+        }
+    }
+
+    fn store_async<'a>(
+        &'a self,
+        messages: &'a [FlashMessage],
+        request: HttpRequest,
+        response: &'a mut ResponseHead,
+    ) -> StoreFuture<'a> {
+        Box::pin(async move {
+            if response.status.is_redirection() {
+                self.redirect
+                    .store_async(messages, request.clone(), response)
+                    .await?;
+                self.fallback.store_async(&[], request, response).await
+            } else {
+                self.redirect
+                    .store_async(&[], request.clone(), response)
+                    .await?;
+                self.fallback.store_async(messages, request, response).await
+            }
+        })
+    }
+}
@@ -0,0 +1,76 @@
+use std::collections::{HashSet, VecDeque};
+use std::sync::Mutex;
+
+/// Keeps track of the single-use tokens (nonces) embedded in flash cookies, to prevent replays.
+///
+/// A signed flash cookie can be sent again and again by a client to re-trigger the same
+/// messages. If you register a [`ReplayGuard`] via [`CookieMessageStoreBuilder::replay_guard`],
+/// each outgoing flash cookie embeds a random nonce: [`CookieMessageStore`] rejects incoming
+/// cookies whose nonce has already been seen.
+///
+/// `actix-web-flash-messages` provides an in-memory implementation, [`InMemoryReplayGuard`].
+/// Implement this trait to share the seen nonces across the instances of your application -
+/// e.g. using Redis.
+///
+/// [`CookieMessageStore`]: crate::storage::CookieMessageStore
+/// [`CookieMessageStoreBuilder::replay_guard`]: crate::storage::CookieMessageStoreBuilder::replay_guard
+pub trait ReplayGuard: Send + Sync {
+    /// Record `nonce` as seen.
+    ///
+    /// It returns `true` if `nonce` had never been seen before, `false` otherwise.
+    fn first_use(&self, nonce: &str) -> bool;
+}
+
+/// An in-memory [`ReplayGuard`], remembering a bounded number of nonces.
+///
+/// Once the capacity is reached, the oldest nonces are forgotten: make sure that it comfortably
+/// exceeds the number of flash cookies issued during their expected lifetime.  
+/// The seen nonces are not shared across different instances of your application.
+pub struct InMemoryReplayGuard {
+    capacity: usize,
+    seen: Mutex<SeenNonces>,
+}
+
+#[derive(Default)]
+struct SeenNonces {
+    set: HashSet<String>,
+    /// Insertion order, to evict the oldest nonces first.
+    order: VecDeque<String>,
+}
+
+impl InMemoryReplayGuard {
+    /// Build a new [`InMemoryReplayGuard`], remembering up to `capacity` nonces.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            seen: Mutex::new(SeenNonces::default()),
+        }
+    }
+}
+
+impl Default for InMemoryReplayGuard {
+    /// An [`InMemoryReplayGuard`] remembering up to 10,000 nonces.
+    fn default() -> Self {
+        Self::new(10_000)
+    }
+}
+
+impl ReplayGuard for InMemoryReplayGuard {
+    fn first_use(&self, nonce: &str) -> bool {
+        let mut seen = self.seen.lock().unwrap();
+        if seen.set.contains(nonce) {
+            return false;
+        }
+        while seen.order.len() >= self.capacity {
+            match seen.order.pop_front() {
+                Some(oldest) => {
+                    seen.set.remove(&oldest);
+                }
+                None => break,
+            }
+        }
+        seen.set.insert(nonce.to_owned());
+        seen.order.push_back(nonce.to_owned());
+        true
+    }
+}
@@ -1,8 +1,12 @@
+use crate::storage::envelope::{Envelope, StoredPayload};
 use crate::storage::{FlashMessageStore, LoadError, StoreError};
 use crate::FlashMessage;
-use actix_session::SessionExt;
+use actix_session::{Session, SessionExt};
 use actix_web::dev::ResponseHead;
-use actix_web::HttpRequest;
+use actix_web::{HttpMessage, HttpRequest};
+use anyhow::Context;
+use std::borrow::Cow;
+use std::collections::HashMap;
 
 /// A session-based implementation of flash messages.
 ///
@@ -24,46 +28,237 @@ use actix_web::HttpRequest;
 ///
 /// Flash messages will not work if you fail to mount a storage backend for your sessions.
 ///
+/// # Middleware ordering
+///
+/// The session middleware must be registered **after** [`FlashMessagesFramework`] - i.e. it must
+/// wrap it:
+///
+/// ```rust
+/// use actix_session::{storage::CookieSessionStore, SessionMiddleware};
+/// use actix_web::{cookie::Key, App};
+/// use actix_web_flash_messages::{FlashMessagesFramework, storage::SessionMessageStore};
+///
+/// let app = App::new()
+///     .wrap(FlashMessagesFramework::builder(SessionMessageStore::default()).build())
+///     .wrap(SessionMiddleware::new(CookieSessionStore::default(), Key::generate()));
+/// ```
+///
+/// If the order is reversed, the session state is persisted **before** outgoing flash messages
+/// are stored: they are silently lost.  
+/// [`SessionMessageStore`] cannot detect either mistake at runtime. `actix-session` does not
+/// expose whether its middleware is active for the current request - retrieving the session
+/// from a request that is not wrapped by it returns a new, empty session rather than an error.
+/// A clear error could be returned if `actix-session` provided a fallible way to retrieve the
+/// session attached by its middleware (e.g. a `SessionExt::try_get_session` method).
+///
+/// # Migrating existing sessions
+///
+/// [`SessionMessageStore`] can read values written under its key by hand-rolled flash message
+/// implementations, so that upgrading doesn't break existing sessions: a single [`FlashMessage`],
+/// or a plain string - loaded as an info-level message. They are overwritten using the current
+/// format the next time flash messages are stored.
+///
+/// The key passed to [`SessionMessageStore::new`] lives inside the session state - it is not a cookie
+/// name. If you are also using [`CookieMessageStore`], make sure that your session cookie is not named
+/// after the flash cookie (`_flash` by default).
+///
 /// You can find examples of application using [`SessionMessageStore`] on GitHub:
 /// using both
 /// [cookie-based sessions](https://github.com/LukeMathWalker/actix-web-flash-messages/tree/main/examples/session-cookie) and
 /// [Redis-based sessions](https://github.com/LukeMathWalker/actix-web-flash-messages/tree/main/examples/session-redis).
 ///
 /// [`FlashMessagesFramework`]: crate::FlashMessagesFramework
+/// [`CookieMessageStore`]: crate::storage::CookieMessageStore
 #[derive(Clone)]
 pub struct SessionMessageStore {
     key: String,
+    bytes_size_limit: Option<u32>,
+    merge: bool,
 }
 
 impl SessionMessageStore {
     /// Build a new [`SessionMessageStore`] and specify which key should be used
     /// to store outgoing flash messages in the session map.
     pub fn new(key: String) -> Self {
-        Self { key }
+        Self {
+            key,
+            bytes_size_limit: None,
+            merge: false,
+        }
+    }
+
+    /// Remove the flash messages attached to the session of `request`, right away - without
+    /// waiting for outgoing messages to be flushed at the end of the request.
+    ///
+    /// It comes in handy for stronger one-time semantics - e.g. in layouts that render several
+    /// fragments, to make sure that the messages can't be picked up by a concurrent request.
+    ///
+    /// ```rust
+    /// use actix_web::{web, HttpRequest, HttpResponse, get};
+    /// use actix_web_flash_messages::{IncomingFlashMessages, storage::SessionMessageStore};
+    ///
+    /// #[get("/show")]
+    /// async fn show(
+    ///     req: HttpRequest,
+    ///     messages: IncomingFlashMessages,
+    ///     store: web::Data<SessionMessageStore>,
+    /// ) -> HttpResponse {
+    ///     store.clear(&req);
+    ///     // [...] Render `messages`
+    ///     HttpResponse::Ok().finish()
+    /// }
+    /// ```
+    ///
+    /// Outgoing messages sent during the request are still stored when the request completes.
+    pub fn clear(&self, request: &HttpRequest) {
+        request.get_session().remove(&self.key);
+    }
+
+    /// By default, there is no limit on the size of the flash messages stored in the session.
+    ///
+    /// Use `bytes_size_limit` to cap the size, in bytes, of the serialised outgoing flash messages -
+    /// it prevents a runaway handler from bloating the session state.  
+    /// [`FlashMessageStore::store`] will return [`StoreError::SizeLimitExceeded`] if the limit is exceeded.
+    pub fn bytes_size_limit(mut self, bytes_size_limit: u32) -> Self {
+        self.bytes_size_limit = Some(bytes_size_limit);
+        self
     }
+
+    /// By default, [`FlashMessageStore::store`] overwrites the flash messages in the session
+    /// wholesale.
+    ///
+    /// If you enable `merge`, outgoing messages are appended to the messages found in the session
+    /// at the end of the request instead - excluding the ones that were already in the session
+    /// when the request came in. Those are the incoming messages, which have already been handled
+    /// by [`FlashMessagesFramework`] (shown, carried over or cleared), whether or not the request
+    /// loaded them.
+    ///
+    /// # Concurrency
+    ///
+    /// `actix-session` loads the session state once, when the request starts, and writes it back
+    /// wholesale when the request completes.  
+    /// `merge` only sees messages written to the session **during this request** - e.g. by your
+    /// handler or by another store sharing the same key. It cannot see messages flashed by a
+    /// concurrent request for the same session: whichever request completes last still
+    /// overwrites the session state of the other one, flash messages included.
+    /// Preventing that requires an atomic read-modify-write in the session backend, which
+    /// `actix-session` does not provide.
+    ///
+    /// [`FlashMessagesFramework`]: crate::FlashMessagesFramework
+    pub fn merge(mut self, merge: bool) -> Self {
+        self.merge = merge;
+        self
+    }
+
+    fn read(&self, session: &Session) -> Result<Vec<FlashMessage>, LoadError> {
+        let payload = session.get::<SessionPayload>(&self.key).map_err(|e| {
+            // This sucks - we are losing all context.
+            let e = anyhow::anyhow!("{}", e)
+                .context("Failed to retrieve flash messages from session storage.");
+            LoadError::GenericError(e)
+        })?;
+        match payload {
+            Some(payload) => payload
+                .into_messages()
+                .map_err(LoadError::DeserializationError),
+            None => Ok(vec![]),
+        }
+    }
+
+    /// The messages in the session that were not there when `request` came in - i.e. those
+    /// written by someone else while the request was being processed.
+    fn unseen_messages(
+        &self,
+        session: &Session,
+        request: &HttpRequest,
+    ) -> Result<Vec<FlashMessage>, StoreError> {
+        let mut messages = self.read(session).map_err(|e| {
+            StoreError::GenericError(
+                anyhow::Error::from(e)
+                    .context("Failed to retrieve the flash messages to merge with."),
+            )
+        })?;
+        let extensions = request.extensions();
+        let arrived = match extensions
+            .get::<ArrivedInSession>()
+            .and_then(|arrived| arrived.0.get(&self.key))
+        {
+            Some(arrived) => arrived,
+            None => return Ok(messages),
+        };
+        // The incoming messages are only stripped if they are still there. If the session value
+        // has been replaced during the request (e.g. via `clear`), all messages are new.
+        let current = serialise_each(&messages);
+        if current.starts_with(arrived) {
+            messages.drain(..arrived.len());
+        }
+        Ok(messages)
+    }
+}
+
+/// The messages that were in the session, by [`SessionMessageStore`] key, when the current
+/// request came in - each one serialised, for comparison.
+#[derive(Default)]
+struct ArrivedInSession(HashMap<String, Vec<String>>);
+
+fn serialise_each(messages: &[FlashMessage]) -> Vec<String> {
+    messages
+        .iter()
+        // `FlashMessage` always serialises successfully.
+        .map(|m| serde_json::to_string(m).unwrap_or_default())
+        .collect()
 }
 
 impl Default for SessionMessageStore {
     fn default() -> Self {
-        Self {
-            key: "_flash".into(),
+        Self::new("_flash".into())
+    }
+}
+
+/// The payload formats found in the session.
+///
+/// On top of the ones written by this crate, it includes values written by applications that
+/// populated the session key on their own before migrating to this crate.  
+/// They are specific to sessions: cookies are only ever written by this crate.
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum SessionPayload {
+    Stored(StoredPayload),
+    /// A single message.
+    LegacyMessage(FlashMessage),
+    /// A plain string. It is read as an info-level message.
+    LegacyText(String),
+}
+
+impl SessionPayload {
+    fn into_messages(self) -> Result<Vec<FlashMessage>, anyhow::Error> {
+        match self {
+            SessionPayload::Stored(payload) => payload.into_messages(),
+            SessionPayload::LegacyMessage(message) => Ok(vec![message]),
+            SessionPayload::LegacyText(content) => Ok(vec![FlashMessage::info(content)]),
         }
     }
 }
 
 impl FlashMessageStore for SessionMessageStore {
     fn load(&self, request: &HttpRequest) -> Result<Vec<FlashMessage>, LoadError> {
-        let session = request.get_session();
-        let messages = session
-            .get(&self.key)
-            .map_err(|e| {
-                // This sucks - we are losing all context.
-                let e = anyhow::anyhow!("{}", e)
-                    .context("Failed to retrieve flash messages from session storage.");
-                LoadError::GenericError(e)
-            })?
+        self.read(&request.get_session())
+    }
+
+    fn on_request(&self, request: &HttpRequest) {
+        if !self.merge {
+            return;
+        }
+        // A payload that can't be read is not recorded: `store` fails to merge with it anyway.
+        let arrived = self
+            .read(&request.get_session())
+            .map(|messages| serialise_each(&messages))
             .unwrap_or_default();
-        Ok(messages)
+        request
+            .extensions_mut()
+            .get_or_insert_with(ArrivedInSession::default)
+            .0
+            .insert(self.key.clone(), arrived);
     }
 
     fn store(
@@ -73,13 +268,33 @@ impl FlashMessageStore for SessionMessageStore {
         _response: &mut ResponseHead,
     ) -> Result<(), StoreError> {
         let session = request.get_session();
+        let messages = if self.merge {
+            let mut merged = self.unseen_messages(&session, &request)?;
+            merged.extend_from_slice(messages);
+            Cow::Owned(merged)
+        } else {
+            Cow::Borrowed(messages)
+        };
         if messages.is_empty() {
             // Make sure to clear up previous flash messages!
             // No need to do this on the other if-branch because we are overwriting
             // any pre-existing flash message with a new value.
             session.remove(&self.key);
         } else {
-            session.insert(&self.key, messages).map_err(|e| {
+            let envelope = Envelope::new(&messages);
+            if let Some(bytes_size_limit) = self.bytes_size_limit {
+                let serialised = serde_json::to_string(&envelope)
+                    .context("Failed to serialise flash messages to JSON.")
+                    .map_err(StoreError::SerializationError)?;
+                if serialised.len() > bytes_size_limit as usize {
+                    return Err(StoreError::SizeLimitExceeded(anyhow::anyhow!(
+                        "The configured maximum size, in bytes, is {}. The serialised outgoing flash messages are {} bytes long.",
+                        bytes_size_limit,
+                        serialised.len()
+                    )));
+                }
+            }
+            session.insert(&self.key, envelope).map_err(|e| {
                 // This sucks - we are losing all context.
                 let e = anyhow::anyhow!("{}", e)
                     .context("Failed to retrieve flash messages from session storage.");
@@ -0,0 +1,173 @@
+use crate::storage::{FlashMessageStore, LoadError, LoadOutcome, StoreError, StoreFuture};
+use crate::FlashMessage;
+use actix_web::dev::ResponseHead;
+use actix_web::HttpRequest;
+use std::sync::Arc;
+
+/// A message store combinator that writes outgoing messages to several backends at once.
+///
+/// [`TeeStore`] is useful when migrating from one store to another - e.g. from a
+/// [`SessionMessageStore`] to a [`CookieMessageStore`] - or when you want redundant delivery:
+/// every backend receives the same messages, therefore any of them can serve the next request.
+///
+/// ```rust
+/// use actix_web_flash_messages::{FlashMessagesFramework, storage::{CookieMessageStore, SessionMessageStore, TeeStore}};
+/// use actix_web::cookie::Key;
+/// use std::sync::Arc;
+///
+/// let cookie_store = CookieMessageStore::builder(Key::generate()).build();
+/// let message_store = TeeStore::new(vec![
+///     Arc::new(cookie_store),
+///     Arc::new(SessionMessageStore::default()),
+/// ]);
+/// let message_framework = FlashMessagesFramework::builder(message_store).build();
+/// ```
+///
+/// # Loading
+///
+/// Backends are consulted in order: [`TeeStore`] returns the messages found in the first backend
+/// that holds any. Messages are not merged - they would otherwise be duplicated.
+///
+/// # Partial failures
+///
+/// [`TeeStore`] fails only if **every** backend fails.
+/// When storing, all backends are written to even if some of them return an error; when loading,
+/// a failing backend is skipped in favour of the next one. Errors from the backends that failed
+/// are logged as warnings, via `tracing`, if the `tracing` feature flag is enabled.
+/// If all backends fail, the error returned by the first one is propagated.
+///
+/// [`CookieMessageStore`]: crate::storage::CookieMessageStore
+/// [`SessionMessageStore`]: crate::storage::SessionMessageStore
+pub struct TeeStore {
+    stores: Vec<Arc<dyn FlashMessageStore>>,
+}
+
+impl TeeStore {
+    /// Build a new [`TeeStore`] that fans out to `stores`.
+    ///
+    /// The order of `stores` determines the order in which they are consulted when loading
+    /// incoming messages.
+    pub fn new(stores: Vec<Arc<dyn FlashMessageStore>>) -> Self {
+        Self { stores }
+    }
+}
+
+impl FlashMessageStore for TeeStore {
+    fn load(&self, request: &HttpRequest) -> Result<Vec<FlashMessage>, LoadError> {
+        let mut first_error = None;
+        let mut succeeded = self.stores.is_empty();
+        for store in &self.stores {
+            match store.load(request) {
+                Ok(messages) if !messages.is_empty() => return Ok(messages),
+                Ok(_) => succeeded = true,
+                Err(e) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(error = %e, "Failed to load flash messages from one of the tee'd stores");
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        match first_error {
+            Some(e) if !succeeded => Err(e),
+            _ => Ok(vec![]),
+        }
+    }
+
+    /// Failures to load from a store are reported as warnings, rather than logged.
+    fn load_lenient(&self, request: &HttpRequest) -> Result<LoadOutcome, LoadError> {
+        let mut warnings = vec![];
+        let mut succeeded = self.stores.is_empty();
+        for store in &self.stores {
+            match store.load_lenient(request) {
+                Ok(mut outcome) => {
+                    warnings.append(&mut outcome.warnings);
+                    if !outcome.messages.is_empty() {
+                        return Ok(LoadOutcome {
+                            messages: outcome.messages,
+                            warnings,
+                        });
+                    }
+                    succeeded = true;
+                }
+                Err(e) => warnings.push(e),
+            }
+        }
+        if !succeeded {
+            // Every store failed: `warnings` holds one error per store, in order.
+            return Err(warnings.remove(0));
+        }
+        Ok(LoadOutcome {
+            messages: vec![],
+            warnings,
+        })
+    }
+
+    fn is_one_time(&self) -> bool {
+        self.stores.iter().all(|store| store.is_one_time())
+    }
+
+    fn on_request(&self, request: &HttpRequest) {
+        for store in &self.stores {
+            store.on_request(request);
+        }
+    }
+
+    fn store(
+        &self,
+        messages: &[FlashMessage],
+        request: HttpRequest,
+        response: &mut ResponseHead,
+    ) -> Result<(), StoreError> {
+        let mut first_error = None;
+        let mut succeeded = self.stores.is_empty();
+        for store in &self.stores {
+            record_outcome(
+                store.store(messages, request.clone(), response),
+                &mut succeeded,
+                &mut first_error,
+            );
+        }
+        match first_error {
+            Some(e) if !succeeded => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    fn store_async<'a>(
+        &'a self,
+        messages: &'a [FlashMessage],
+        request: HttpRequest,
+        response: &'a mut ResponseHead,
+    ) -> StoreFuture<'a> {
+        Box::pin(async move {
+            let mut first_error = None;
+            let mut succeeded = self.stores.is_empty();
+            for store in &self.stores {
+                record_outcome(
+                    store.store_async(messages, request.clone(), response).await,
+                    &mut succeeded,
+                    &mut first_error,
+                );
+            }
+            match first_error {
+                Some(e) if !succeeded => Err(e),
+                _ => Ok(()),
+            }
+        })
+    }
+}
+
+fn record_outcome(
+    outcome: Result<(), StoreError>,
+    succeeded: &mut bool,
+    first_error: &mut Option<StoreError>,
+) {
+    match outcome {
+        Ok(()) => *succeeded = true,
+        Err(e) => {
+            #[cfg(feature = "tracing")]
+            tracing::warn!(error = %e, "Failed to store flash messages in one of the tee'd stores");
+            first_error.get_or_insert(e);
+        }
+    }
+}
@@ -0,0 +1,90 @@
+//! Utilities to test flash message flows in your own application.
+//!
+//! It is only available if the `test-util` feature flag is enabled.
+use crate::incoming::LoadedFlashMessages;
+use crate::FlashMessage;
+use actix_http::Request;
+use actix_web::dev::{Service, ServiceResponse};
+use actix_web::http::header::{COOKIE, SET_COOKIE};
+use actix_web::test::{call_service, TestRequest};
+use actix_web::HttpMessage;
+
+/// Send a `GET` request to `set_uri`, forward the cookies it sets to a `GET` request to
+/// `show_uri` and return the flash messages that were read by the handler behind `show_uri`.
+///
+/// `app` is the service returned by [`actix_web::test::init_service`].
+///
+/// [`flash_roundtrip`] works with any store that keeps its state in cookies - e.g.
+/// [`CookieMessageStore`] or [`SessionMessageStore`] backed by a cookie session - as well as
+/// with [`CapturingMessageStore`].
+/// An empty vector is returned if the `show_uri` handler did not read incoming flash messages.
+///
+/// ```rust
+/// use actix_web_flash_messages::{
+///     test_util::flash_roundtrip, FlashMessage, FlashMessagesFramework, IncomingFlashMessages,
+///     storage::CapturingMessageStore,
+/// };
+/// use actix_web::{http, web, App, HttpResponse, Responder};
+///
+/// async fn set() -> impl Responder {
+///     FlashMessage::info("Hey there!").send();
+///     HttpResponse::SeeOther()
+///         .insert_header((http::header::LOCATION, "/show"))
+///         .finish()
+/// }
+///
+/// async fn show(messages: IncomingFlashMessages) -> impl Responder {
+///     HttpResponse::Ok().body(messages.iter().count().to_string())
+/// }
+///
+/// #[actix_web::main]
+/// async fn main() {
+///     let store = CapturingMessageStore::default();
+///     let app = actix_web::test::init_service(
+///         App::new()
+///             .wrap(FlashMessagesFramework::builder(store).build())
+///             .route("/set", web::get().to(set))
+///             .route("/show", web::get().to(show)),
+///     )
+///     .await;
+///
+///     let messages = flash_roundtrip(&app, "/set", "/show").await;
+/// #   #[cfg(not(feature = "disabled"))]
+/// #   {
+///     assert_eq!(messages.len(), 1);
+///     assert_eq!(messages[0].content(), "Hey there!");
+/// #   }
+/// }
+/// ```
+///
+/// [`CookieMessageStore`]: crate::storage::CookieMessageStore
+/// [`SessionMessageStore`]: crate::storage::SessionMessageStore
+/// [`CapturingMessageStore`]: crate::storage::CapturingMessageStore
+pub async fn flash_roundtrip<S, B>(app: &S, set_uri: &str, show_uri: &str) -> Vec<FlashMessage>
+where
+    S: Service<Request, Response = ServiceResponse<B>, Error = actix_web::Error>,
+{
+    let response = call_service(app, TestRequest::get().uri(set_uri).to_request()).await;
+    let cookies: Vec<&str> = response
+        .headers()
+        .get_all(SET_COOKIE)
+        .filter_map(|header| header.to_str().ok())
+        // Keep the `name=value` pair, dropping attributes such as `Path` or `HttpOnly`.
+        .filter_map(|cookie| cookie.split(';').next())
+        // Skip removal cookies.
+        .filter(|pair| !pair.trim_end().ends_with('='))
+        .collect();
+
+    let mut request = TestRequest::get().uri(show_uri);
+    if !cookies.is_empty() {
+        request = request.insert_header((COOKIE, cookies.join("; ")));
+    }
+    let response = call_service(app, request.to_request()).await;
+    let messages = response
+        .request()
+        .extensions()
+        .get::<LoadedFlashMessages>()
+        .map(|loaded| loaded.messages.clone())
+        .unwrap_or_default();
+    messages
+}
@@ -0,0 +1,62 @@
+// Run with `cargo test --features disabled,cookies --test disabled`.
+#![cfg(feature = "disabled")]
+use actix_web::web::resource;
+use actix_web::{web, App, HttpResponse, Responder};
+use actix_web_flash_messages::{
+    FlashMessage, FlashMessageCount, FlashScope, IncomingFlashMessages,
+};
+
+async fn send_and_show(
+    messages: IncomingFlashMessages,
+    mut scope: FlashScope,
+    FlashMessageCount(count): FlashMessageCount,
+) -> impl Responder {
+    FlashMessage::info("Hey there!").send();
+    FlashMessage::set_outgoing(vec![FlashMessage::error("Oops")]);
+    scope.push(FlashMessage::warning("Careful"));
+    HttpResponse::Ok().body(format!("{} {}", messages.iter().count(), count))
+}
+
+#[actix_rt::test]
+async fn test_flash_messages_are_no_ops_without_the_middleware() {
+    let app = actix_web::test::init_service(
+        App::new().service(resource("/").route(web::get().to(send_and_show))),
+    )
+    .await;
+
+    let resp = actix_web::test::call_service(
+        &app,
+        actix_web::test::TestRequest::get().uri("/").to_request(),
+    )
+    .await;
+    let body_bytes = actix_web::test::read_body(resp).await;
+    assert_eq!(std::str::from_utf8(&body_bytes).unwrap(), "0 0");
+}
+
+#[cfg(feature = "cookies")]
+#[actix_rt::test]
+async fn test_the_middleware_is_a_pass_through() {
+    use actix_web::cookie::Key;
+    use actix_web_flash_messages::storage::CookieMessageStore;
+    use actix_web_flash_messages::FlashMessagesFramework;
+
+    async fn set() -> impl Responder {
+        FlashMessage::info("Hey there!").send();
+        HttpResponse::Ok()
+    }
+
+    let cookie_store = CookieMessageStore::builder(Key::generate()).build();
+    let app = actix_web::test::init_service(
+        App::new()
+            .wrap(FlashMessagesFramework::builder(cookie_store).build())
+            .service(resource("/set").route(web::get().to(set))),
+    )
+    .await;
+
+    let resp = actix_web::test::call_service(
+        &app,
+        actix_web::test::TestRequest::get().uri("/set").to_request(),
+    )
+    .await;
+    assert_eq!(resp.response().cookies().count(), 0);
+}
@@ -1,3 +1,6 @@
+// The integration tests exercise flash messages end to end: they can't pass when the `disabled`
+// feature flag turns them into no-ops. See `tests/disabled.rs` instead.
+#![cfg(not(feature = "disabled"))]
 use actix_web::cookie::{Key, SameSite};
 use actix_web::web::resource;
 use actix_web::{web, App, HttpResponse, Responder};
@@ -41,8 +44,8 @@ mod cookies {
         .await;
 
         // Step 0:  GET /show
-        // No flash messages have been set - the response should be setting the flash cookie
-        // with max_age set to 0.
+        // No flash messages have been set and the client has no flash cookie - the response
+        // should not be setting any cookie.
         let resp = actix_web::test::call_service(
             &app,
             actix_web::test::TestRequest::get()
@@ -50,13 +53,11 @@ mod cookies {
                 .to_request(),
         )
         .await;
-        let cookies = resp.response().cookies().collect::<Vec<_>>();
-        assert_eq!(cookies.len(), 1);
-        let cookie = cookies.first().unwrap();
-        assert_eq!(cookie.name(), cookie_name);
-        assert_eq!(cookie.value(), "");
-        assert_eq!(cookie.max_age(), Some(time::Duration::seconds(0)));
-        assert_eq!(cookie.same_site(), Some(SameSite::Lax));
+        assert_eq!(resp.response().cookies().count(), 0);
+        assert!(resp
+            .headers()
+            .get(actix_web::http::header::SET_COOKIE)
+            .is_none());
 
         let body_length = actix_web::test::read_body(resp).await.len();
         assert_eq!(body_length, 0);
@@ -117,8 +118,8 @@ mod cookies {
         .await;
 
         // Step 0:  GET /show
-        // No flash messages have been set - the response should be setting the flash cookie
-        // with max_age set to 0.
+        // No flash messages have been set and the client has no flash cookie - the response
+        // should not be setting any cookie.
         let resp = actix_web::test::call_service(
             &app,
             actix_web::test::TestRequest::get()
@@ -126,12 +127,11 @@ mod cookies {
                 .to_request(),
         )
         .await;
-        let cookies = resp.response().cookies().collect::<Vec<_>>();
-        assert_eq!(cookies.len(), 1);
-        let cookie = cookies.first().unwrap();
-        assert_eq!(cookie.name(), cookie_name);
-        assert_eq!(cookie.value(), "");
-        assert_eq!(cookie.max_age(), Some(time::Duration::seconds(0)));
+        assert_eq!(resp.response().cookies().count(), 0);
+        assert!(resp
+            .headers()
+            .get(actix_web::http::header::SET_COOKIE)
+            .is_none());
 
         let body_length = actix_web::test::read_body(resp).await.len();
         assert_eq!(body_length, 0);
@@ -256,4 +256,6168 @@ mod sessions {
         let body = std::str::from_utf8(&body_bytes).unwrap();
         assert_eq!(body, "Hey there! - info\n");
     }
+
+    async fn peek_session(session: actix_session::Session) -> impl Responder {
+        let stored = session.get::<serde_json::Value>("_flash").unwrap();
+        HttpResponse::Ok().body(format!("{:?}", stored.map(|v| v.to_string())))
+    }
+
+    async fn show_then_fail(messages: IncomingFlashMessages) -> actix_web::Result<HttpResponse> {
+        assert_eq!(messages.iter().count(), 1);
+        Err(actix_web::error::ErrorInternalServerError(
+            "Something went wrong",
+        ))
+    }
+
+    #[actix_rt::test]
+    async fn test_flash_messages_are_removed_from_the_session_once_displayed() {
+        let session_middleware =
+            SessionMiddleware::builder(CookieSessionStore::default(), Key::generate())
+                .cookie_name("_session".to_string())
+                .build();
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(FlashMessagesFramework::builder(SessionMessageStore::default()).build())
+                .wrap(session_middleware)
+                .service(resource("/set").route(web::get().to(set)))
+                .service(resource("/show").route(web::get().to(show)))
+                .service(resource("/show_then_fail").route(web::get().to(show_then_fail)))
+                .service(resource("/peek").route(web::get().to(peek_session))),
+        )
+        .await;
+        let session_cookie = |resp: &actix_web::dev::ServiceResponse| {
+            resp.response()
+                .cookies()
+                .find(|c| c.name() == "_session")
+                .unwrap()
+                .into_owned()
+        };
+
+        for show_path in ["/show", "/show_then_fail"] {
+            let resp = actix_web::test::call_service(
+                &app,
+                actix_web::test::TestRequest::get().uri("/set").to_request(),
+            )
+            .await;
+            let cookie = session_cookie(&resp);
+
+            // The message is stored in the session...
+            let resp = actix_web::test::call_service(
+                &app,
+                actix_web::test::TestRequest::get()
+                    .uri("/peek")
+                    .cookie(cookie.clone())
+                    .to_request(),
+            )
+            .await;
+            let body_bytes = actix_web::test::read_body(resp).await;
+            assert!(std::str::from_utf8(&body_bytes)
+                .unwrap()
+                .contains("Hey there!"));
+
+            // ...until it is displayed.
+            let resp = actix_web::test::call_service(
+                &app,
+                actix_web::test::TestRequest::get()
+                    .uri(show_path)
+                    .cookie(cookie)
+                    .to_request(),
+            )
+            .await;
+            let cookie = session_cookie(&resp);
+            let resp = actix_web::test::call_service(
+                &app,
+                actix_web::test::TestRequest::get()
+                    .uri("/peek")
+                    .cookie(cookie)
+                    .to_request(),
+            )
+            .await;
+            let body_bytes = actix_web::test::read_body(resp).await;
+            assert_eq!(
+                std::str::from_utf8(&body_bytes).unwrap(),
+                "None",
+                "The session still holds flash messages after `GET {}`",
+                show_path
+            );
+        }
+    }
+}
+
+#[cfg(feature = "sessions")]
+mod session_payload_versioning {
+    use super::*;
+    use actix_session::{storage::CookieSessionStore, Session, SessionMiddleware};
+    use actix_web_flash_messages::storage::SessionMessageStore;
+
+    async fn write_legacy(session: Session) -> impl Responder {
+        session
+            .insert("_flash", vec![FlashMessage::info("Hey there!")])
+            .unwrap();
+        HttpResponse::Ok()
+    }
+
+    async fn write_legacy_text(session: Session) -> impl Responder {
+        session.insert("_flash", "Welcome back!").unwrap();
+        HttpResponse::Ok()
+    }
+
+    async fn write_legacy_message(session: Session) -> impl Responder {
+        session
+            .insert("_flash", FlashMessage::warning("Your trial ends soon"))
+            .unwrap();
+        HttpResponse::Ok()
+    }
+
+    async fn peek_raw(session: Session) -> impl Responder {
+        let raw = session.get::<serde_json::Value>("_flash").unwrap().unwrap();
+        HttpResponse::Ok().body(raw.to_string())
+    }
+
+    #[actix_rt::test]
+    async fn test_session_payloads_are_versioned_and_legacy_ones_are_readable() {
+        let session_middleware =
+            SessionMiddleware::builder(CookieSessionStore::default(), Key::generate())
+                .cookie_name("_session".to_string())
+                .build();
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(session_middleware)
+                // Routes outside of the flash messages framework, to poke at the session directly.
+                .service(resource("/legacy").route(web::get().to(write_legacy)))
+                .service(resource("/raw").route(web::get().to(peek_raw)))
+                .service(
+                    web::scope("/flash")
+                        .wrap(
+                            FlashMessagesFramework::builder(SessionMessageStore::default()).build(),
+                        )
+                        .service(resource("/set").route(web::get().to(set)))
+                        .service(resource("/show").route(web::get().to(show))),
+                ),
+        )
+        .await;
+        let session_cookie = |resp: &actix_web::dev::ServiceResponse| {
+            resp.response()
+                .cookies()
+                .find(|c| c.name() == "_session")
+                .unwrap()
+                .into_owned()
+        };
+
+        // Round-trip: messages are stored in a versioned envelope.
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/flash/set")
+                .to_request(),
+        )
+        .await;
+        let cookie = session_cookie(&resp);
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/raw")
+                .cookie(cookie.clone())
+                .to_request(),
+        )
+        .await;
+        let raw: serde_json::Value =
+            serde_json::from_slice(&actix_web::test::read_body(resp).await).unwrap();
+        assert_eq!(raw["v"], 1);
+        assert_eq!(raw["messages"][0]["content"], "Hey there!");
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/flash/show")
+                .cookie(cookie)
+                .to_request(),
+        )
+        .await;
+        let body_bytes = actix_web::test::read_body(resp).await;
+        assert_eq!(
+            std::str::from_utf8(&body_bytes).unwrap(),
+            "Hey there! - info\n"
+        );
+
+        // Legacy: a bare sequence of messages can still be read.
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/legacy")
+                .to_request(),
+        )
+        .await;
+        let cookie = session_cookie(&resp);
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/flash/show")
+                .cookie(cookie)
+                .to_request(),
+        )
+        .await;
+        let body_bytes = actix_web::test::read_body(resp).await;
+        assert_eq!(
+            std::str::from_utf8(&body_bytes).unwrap(),
+            "Hey there! - info\n"
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_hand_rolled_session_values_are_readable() {
+        let session_middleware =
+            SessionMiddleware::builder(CookieSessionStore::default(), Key::generate())
+                .cookie_name("_session".to_string())
+                .build();
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(session_middleware)
+                .service(resource("/legacy_text").route(web::get().to(write_legacy_text)))
+                .service(resource("/legacy_message").route(web::get().to(write_legacy_message)))
+                .service(
+                    web::scope("/flash")
+                        .wrap(
+                            FlashMessagesFramework::builder(SessionMessageStore::default()).build(),
+                        )
+                        .service(resource("/show").route(web::get().to(show))),
+                ),
+        )
+        .await;
+
+        for (path, expected) in [
+            ("/legacy_text", "Welcome back! - info\n"),
+            ("/legacy_message", "Your trial ends soon - warning\n"),
+        ] {
+            let resp = actix_web::test::call_service(
+                &app,
+                actix_web::test::TestRequest::get().uri(path).to_request(),
+            )
+            .await;
+            let cookie = resp
+                .response()
+                .cookies()
+                .find(|c| c.name() == "_session")
+                .unwrap()
+                .into_owned();
+            let resp = actix_web::test::call_service(
+                &app,
+                actix_web::test::TestRequest::get()
+                    .uri("/flash/show")
+                    .cookie(cookie)
+                    .to_request(),
+            )
+            .await;
+            assert!(resp.status().is_success());
+            let body_bytes = actix_web::test::read_body(resp).await;
+            assert_eq!(std::str::from_utf8(&body_bytes).unwrap(), expected);
+        }
+    }
+}
+
+#[cfg(feature = "cookies")]
+mod rate_limited {
+    use super::*;
+    use actix_web_flash_messages::storage::{CookieMessageStore, RateLimitedStore};
+
+    #[actix_rt::test]
+    async fn test_duplicate_messages_are_suppressed_within_the_window() {
+        let cookie_store = CookieMessageStore::builder(Key::generate()).build();
+        let message_store = RateLimitedStore::builder(cookie_store, |request| {
+            request
+                .headers()
+                .get("X-Client-Id")
+                .and_then(|h| h.to_str().ok())
+                .map(|s| s.to_owned())
+        })
+        .build();
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(FlashMessagesFramework::builder(message_store).build())
+                .service(resource("/set").route(web::get().to(set)))
+                .service(resource("/show").route(web::get().to(show))),
+        )
+        .await;
+
+        for (client_id, expected_body) in [
+            ("alice", "Hey there! - info\n"),
+            ("alice", ""),
+            ("bob", "Hey there! - info\n"),
+        ] {
+            let resp = actix_web::test::call_service(
+                &app,
+                actix_web::test::TestRequest::get().uri("/set").to_request(),
+            )
+            .await;
+            let flash_cookie = resp
+                .response()
+                .cookies()
+                .find(|c| c.name() == "_flash")
+                .unwrap()
+                .into_owned();
+
+            let resp = actix_web::test::call_service(
+                &app,
+                actix_web::test::TestRequest::get()
+                    .uri("/show")
+                    .insert_header(("X-Client-Id", client_id))
+                    .cookie(flash_cookie)
+                    .to_request(),
+            )
+            .await;
+            let body_bytes = actix_web::test::read_body(resp).await;
+            let body = std::str::from_utf8(&body_bytes).unwrap();
+            assert_eq!(body, expected_body);
+        }
+    }
+
+    async fn peek(
+        actix_web_flash_messages::FlashMessageCount(count): actix_web_flash_messages::FlashMessageCount,
+    ) -> impl Responder {
+        HttpResponse::Ok().body(count.to_string())
+    }
+
+    #[actix_rt::test]
+    async fn test_peeked_messages_are_not_marked_as_shown() {
+        let cookie_store = CookieMessageStore::builder(Key::generate()).build();
+        let message_store =
+            RateLimitedStore::builder(cookie_store, |_| Some("alice".into())).build();
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(FlashMessagesFramework::builder(message_store).build())
+                .service(resource("/set").route(web::get().to(set)))
+                .service(resource("/peek").route(web::get().to(peek)))
+                .service(resource("/show").route(web::get().to(show))),
+        )
+        .await;
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get().uri("/set").to_request(),
+        )
+        .await;
+        let flash_cookie = resp
+            .response()
+            .cookies()
+            .find(|c| c.name() == "_flash")
+            .unwrap()
+            .into_owned();
+
+        // Peeking at the message carries it over to the next request...
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/peek")
+                .cookie(flash_cookie)
+                .to_request(),
+        )
+        .await;
+        let flash_cookie = resp
+            .response()
+            .cookies()
+            .find(|c| c.name() == "_flash")
+            .unwrap()
+            .into_owned();
+        let body_bytes = actix_web::test::read_body(resp).await;
+        assert_eq!(std::str::from_utf8(&body_bytes).unwrap(), "1");
+
+        // ...where it is shown, even though it is within the window.
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/show")
+                .cookie(flash_cookie)
+                .to_request(),
+        )
+        .await;
+        let body_bytes = actix_web::test::read_body(resp).await;
+        assert_eq!(
+            std::str::from_utf8(&body_bytes).unwrap(),
+            "Hey there! - info\n"
+        );
+    }
+}
+
+mod levels {
+    use actix_web_flash_messages::{FlashMessage, Level};
+
+    #[test]
+    fn test_each_level_has_an_emoji() {
+        let emojis = [
+            (Level::Debug, "🐛"),
+            (Level::Info, "ℹ️"),
+            (Level::Success, "✅"),
+            (Level::Warning, "⚠️"),
+            (Level::Error, "❌"),
+        ];
+        for (level, emoji) in emojis {
+            assert_eq!(level.emoji(), emoji);
+            let message = FlashMessage::new("Hey there!".into(), level);
+            assert_eq!(
+                message.display_with_emoji(),
+                format!("{} Hey there!", emoji)
+            );
+        }
+    }
+
+    #[test]
+    fn test_level_comparisons() {
+        let levels = [
+            Level::Debug,
+            Level::Info,
+            Level::Success,
+            Level::Warning,
+            Level::Error,
+        ];
+        for (i, level) in levels.iter().enumerate() {
+            for (j, other) in levels.iter().enumerate() {
+                assert_eq!(level.is_at_least(*other), i >= j);
+                assert_eq!(level.is_below(*other), i < j);
+            }
+        }
+    }
+
+    #[test]
+    fn test_levels_can_be_deserialized_from_names() {
+        let levels: Vec<Level> =
+            serde_json::from_str(r#"["Debug", "info", "Success", "warning", "Error"]"#).unwrap();
+        assert_eq!(
+            levels,
+            vec![
+                Level::Debug,
+                Level::Info,
+                Level::Success,
+                Level::Warning,
+                Level::Error
+            ]
+        );
+    }
+
+    #[cfg(feature = "numeric-levels")]
+    #[test]
+    fn test_levels_can_be_deserialized_from_numeric_severities() {
+        let levels: Vec<Level> = serde_json::from_str("[0, 1, 2, 3, 4]").unwrap();
+        assert_eq!(
+            levels,
+            vec![
+                Level::Debug,
+                Level::Info,
+                Level::Success,
+                Level::Warning,
+                Level::Error
+            ]
+        );
+        assert!(serde_json::from_str::<Level>("5").is_err());
+    }
+
+    #[cfg(not(feature = "numeric-levels"))]
+    #[test]
+    fn test_numeric_severities_are_rejected_without_the_numeric_levels_feature() {
+        assert!(serde_json::from_str::<Level>("4").is_err());
+    }
+
+    /// A deserializer for a non-self-describing format (e.g. `bincode`): unit variants are encoded
+    /// as their index and `deserialize_any` is not supported.
+    #[cfg(not(feature = "numeric-levels"))]
+    struct VariantIndexDeserializer(u32);
+
+    #[cfg(not(feature = "numeric-levels"))]
+    mod variant_index {
+        use super::VariantIndexDeserializer;
+        use serde::de::value::Error;
+        use serde::de::{Error as _, IntoDeserializer};
+
+        impl<'de> serde::Deserializer<'de> for VariantIndexDeserializer {
+            type Error = Error;
+
+            fn deserialize_any<V: serde::de::Visitor<'de>>(self, _: V) -> Result<V::Value, Error> {
+                Err(Error::custom("the format is not self-describing"))
+            }
+
+            fn deserialize_enum<V: serde::de::Visitor<'de>>(
+                self,
+                _name: &'static str,
+                _variants: &'static [&'static str],
+                visitor: V,
+            ) -> Result<V::Value, Error> {
+                visitor.visit_enum(self)
+            }
+
+            serde::forward_to_deserialize_any! {
+                bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes
+                byte_buf option unit unit_struct newtype_struct seq tuple tuple_struct map struct
+                identifier ignored_any
+            }
+        }
+
+        impl<'de> serde::de::EnumAccess<'de> for VariantIndexDeserializer {
+            type Error = Error;
+            type Variant = Self;
+
+            fn variant_seed<V: serde::de::DeserializeSeed<'de>>(
+                self,
+                seed: V,
+            ) -> Result<(V::Value, Self), Error> {
+                let index: serde::de::value::U32Deserializer<Error> = self.0.into_deserializer();
+                Ok((seed.deserialize(index)?, self))
+            }
+        }
+
+        impl<'de> serde::de::VariantAccess<'de> for VariantIndexDeserializer {
+            type Error = Error;
+
+            fn unit_variant(self) -> Result<(), Error> {
+                Ok(())
+            }
+
+            fn newtype_variant_seed<T: serde::de::DeserializeSeed<'de>>(
+                self,
+                _: T,
+            ) -> Result<T::Value, Error> {
+                Err(Error::custom("unexpected newtype variant"))
+            }
+
+            fn tuple_variant<V: serde::de::Visitor<'de>>(
+                self,
+                _: usize,
+                _: V,
+            ) -> Result<V::Value, Error> {
+                Err(Error::custom("unexpected tuple variant"))
+            }
+
+            fn struct_variant<V: serde::de::Visitor<'de>>(
+                self,
+                _: &'static [&'static str],
+                _: V,
+            ) -> Result<V::Value, Error> {
+                Err(Error::custom("unexpected struct variant"))
+            }
+        }
+    }
+
+    #[cfg(not(feature = "numeric-levels"))]
+    #[test]
+    fn test_levels_can_be_deserialized_from_non_self_describing_formats() {
+        use serde::Deserialize;
+
+        let level = Level::deserialize(VariantIndexDeserializer(4)).unwrap();
+        assert_eq!(level, Level::Error);
+        assert!(Level::deserialize(VariantIndexDeserializer(5)).is_err());
+    }
+
+    #[test]
+    fn test_levels_round_trip() {
+        let serialized = serde_json::to_string(&Level::Error).unwrap();
+        if cfg!(feature = "numeric-levels") {
+            assert_eq!(serialized, "4");
+        } else {
+            assert_eq!(serialized, r#""error""#);
+        }
+        let level: Level = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(level, Level::Error);
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn test_levels_convert_to_tracing_levels() {
+        let conversions = [
+            (Level::Debug, tracing::Level::DEBUG),
+            (Level::Info, tracing::Level::INFO),
+            (Level::Success, tracing::Level::INFO),
+            (Level::Warning, tracing::Level::WARN),
+            (Level::Error, tracing::Level::ERROR),
+        ];
+        for (level, expected) in conversions {
+            assert_eq!(tracing::Level::from(level), expected);
+        }
+    }
+}
+
+#[cfg(feature = "cookies")]
+mod ordering {
+    use super::*;
+    use actix_web_flash_messages::storage::CookieMessageStore;
+
+    async fn set_with_priority() -> impl Responder {
+        FlashMessage::info("First").send();
+        FlashMessage::info("Second").send();
+        FlashMessage::error("Urgent").send_front();
+        HttpResponse::SeeOther()
+            .insert_header((actix_web::http::header::LOCATION, "/show"))
+            .finish()
+    }
+
+    #[actix_rt::test]
+    async fn test_send_front_puts_messages_ahead_of_the_queue() {
+        let cookie_store = CookieMessageStore::builder(Key::generate()).build();
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(FlashMessagesFramework::builder(cookie_store).build())
+                .service(resource("/set").route(web::get().to(set_with_priority)))
+                .service(resource("/show").route(web::get().to(show))),
+        )
+        .await;
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get().uri("/set").to_request(),
+        )
+        .await;
+        let flash_cookie = resp
+            .response()
+            .cookies()
+            .find(|c| c.name() == "_flash")
+            .unwrap()
+            .into_owned();
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/show")
+                .cookie(flash_cookie)
+                .to_request(),
+        )
+        .await;
+        let body_bytes = actix_web::test::read_body(resp).await;
+        let body = std::str::from_utf8(&body_bytes).unwrap();
+        assert_eq!(body, "Urgent - error\nFirst - info\nSecond - info\n");
+    }
+}
+
+#[cfg(feature = "cookies")]
+mod query {
+    use super::*;
+    use actix_web_flash_messages::storage::QueryMessageStore;
+
+    #[actix_rt::test]
+    async fn test_flash_messages_are_read_from_a_signed_query_parameter() {
+        let signing_key = Key::generate();
+        let link_store = QueryMessageStore::builder(signing_key.clone()).build();
+        let payload = link_store
+            .encode(&[FlashMessage::success("Email confirmed!")])
+            .unwrap();
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(
+                    FlashMessagesFramework::builder(
+                        QueryMessageStore::builder(signing_key).build(),
+                    )
+                    .build(),
+                )
+                .service(resource("/show").route(web::get().to(show))),
+        )
+        .await;
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri(&format!("/show?_flash={}", payload))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.response().cookies().count(), 0);
+        let body_bytes = actix_web::test::read_body(resp).await;
+        let body = std::str::from_utf8(&body_bytes).unwrap();
+        assert_eq!(body, "Email confirmed! - success\n");
+
+        // A payload signed with a different key is rejected.
+        let forged_payload = QueryMessageStore::builder(Key::generate())
+            .build()
+            .encode(&[FlashMessage::error("Forged!")])
+            .unwrap();
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri(&format!("/show?_flash={}", forged_payload))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+    }
+}
+
+#[cfg(feature = "cookies")]
+mod size_limit {
+    use super::*;
+    use actix_web::dev::ResponseHead;
+    use actix_web::http::StatusCode;
+    use actix_web_flash_messages::storage::{CookieMessageStore, FlashMessageStore, StoreError};
+
+    #[test]
+    fn test_size_limit_error_points_at_the_largest_message() {
+        let cookie_store = CookieMessageStore::builder(Key::generate())
+            .bytes_size_limit(256)
+            .build();
+        let messages = vec![
+            FlashMessage::info("Short"),
+            FlashMessage::error("x".repeat(300)),
+            FlashMessage::info("Also short"),
+        ];
+        let error = cookie_store
+            .store(
+                &messages,
+                actix_web::test::TestRequest::default().to_http_request(),
+                &mut ResponseHead::new(StatusCode::OK),
+            )
+            .unwrap_err();
+        let source = match error {
+            StoreError::SizeLimitExceeded(e) => e.to_string(),
+            e => panic!("Unexpected error: {:?}", e),
+        };
+        assert!(source.contains("There are 3 outgoing flash messages"));
+        assert!(
+            source.contains("the largest one is at index 1, with a content length of 300 bytes")
+        );
+    }
+}
+
+#[cfg(feature = "cookies")]
+mod lazy_loading {
+    use super::*;
+    use actix_web::dev::ResponseHead;
+    use actix_web::HttpRequest;
+    use actix_web_flash_messages::storage::{
+        CookieMessageStore, FlashMessageStore, LoadError, StoreError,
+    };
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    struct CountingStore {
+        inner: CookieMessageStore,
+        loads: Arc<AtomicUsize>,
+    }
+
+    impl FlashMessageStore for CountingStore {
+        fn load(&self, request: &HttpRequest) -> Result<Vec<FlashMessage>, LoadError> {
+            self.loads.fetch_add(1, Ordering::SeqCst);
+            self.inner.load(request)
+        }
+
+        fn store(
+            &self,
+            messages: &[FlashMessage],
+            request: HttpRequest,
+            response: &mut ResponseHead,
+        ) -> Result<(), StoreError> {
+            self.inner.store(messages, request, response)
+        }
+    }
+
+    async fn show_twice(
+        first: IncomingFlashMessages,
+        second: IncomingFlashMessages,
+    ) -> impl Responder {
+        assert_eq!(first.iter().len(), second.iter().len());
+        HttpResponse::Ok()
+    }
+
+    #[actix_rt::test]
+    async fn test_messages_are_loaded_lazily_and_at_most_once_per_request() {
+        let loads = Arc::new(AtomicUsize::new(0));
+        let store = CountingStore {
+            inner: CookieMessageStore::builder(Key::generate()).build(),
+            loads: loads.clone(),
+        };
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(FlashMessagesFramework::builder(store).build())
+                .service(resource("/set").route(web::get().to(set)))
+                .service(resource("/show").route(web::get().to(show_twice))),
+        )
+        .await;
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get().uri("/set").to_request(),
+        )
+        .await;
+        assert_eq!(loads.load(Ordering::SeqCst), 0);
+        let flash_cookie = resp
+            .response()
+            .cookies()
+            .find(|c| c.name() == "_flash")
+            .unwrap()
+            .into_owned();
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/show")
+                .cookie(flash_cookie)
+                .to_request(),
+        )
+        .await;
+        assert_eq!(loads.load(Ordering::SeqCst), 1);
+        // The flash cookie is still cleared at flush time.
+        let cookie = resp.response().cookies().next().unwrap();
+        assert_eq!(cookie.value(), "");
+        assert_eq!(cookie.max_age(), Some(time::Duration::seconds(0)));
+    }
+}
+
+mod typed_data {
+    use actix_web_flash_messages::{FlashMessage, Level};
+
+    #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+    struct RetryAfter {
+        retry_after_seconds: u64,
+    }
+
+    #[test]
+    fn test_typed_data_survives_a_serialization_round_trip() {
+        let message = FlashMessage::warning("Too many attempts")
+            .with_data(&RetryAfter {
+                retry_after_seconds: 30,
+            })
+            .unwrap();
+        let serialized = serde_json::to_string(&message).unwrap();
+        let message: FlashMessage = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(
+            message.data::<RetryAfter>().unwrap().unwrap(),
+            RetryAfter {
+                retry_after_seconds: 30
+            }
+        );
+    }
+
+    #[test]
+    fn test_messages_without_data_are_backward_compatible() {
+        let message = FlashMessage::info("Hey there!");
+        let serialized = serde_json::to_string(&message).unwrap();
+        assert!(!serialized.contains("data"));
+
+        let message: FlashMessage =
+            serde_json::from_str(r#"{"content": "Hey there!", "level": "Info"}"#).unwrap();
+        assert_eq!(message.level(), Level::Info);
+        assert!(message.data::<RetryAfter>().is_none());
+    }
+}
+
+#[cfg(all(feature = "cookies", feature = "sessions"))]
+mod multiple_stores {
+    use super::*;
+    use actix_session::{storage::CookieSessionStore, SessionMiddleware};
+    use actix_web_flash_messages::storage::{CookieMessageStore, SessionMessageStore};
+
+    #[actix_rt::test]
+    async fn test_the_innermost_framework_handles_flash_messages() {
+        let session_middleware =
+            SessionMiddleware::builder(CookieSessionStore::default(), Key::generate())
+                .cookie_name("_session".to_string())
+                .build();
+        let cookie_store = CookieMessageStore::builder(Key::generate()).build();
+        let app = actix_web::test::init_service(
+            App::new()
+                // Innermost framework: it receives the messages sent by handlers.
+                .wrap(FlashMessagesFramework::builder(cookie_store).build())
+                .wrap(FlashMessagesFramework::builder(SessionMessageStore::default()).build())
+                .wrap(session_middleware)
+                .service(resource("/set").route(web::get().to(set)))
+                .service(resource("/show").route(web::get().to(show))),
+        )
+        .await;
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get().uri("/set").to_request(),
+        )
+        .await;
+        // Messages end up in the flash cookie - the session is never touched.
+        let cookies = resp.response().cookies().collect::<Vec<_>>();
+        assert_eq!(cookies.len(), 1);
+        let flash_cookie = cookies.first().unwrap().clone().into_owned();
+        assert_eq!(flash_cookie.name(), "_flash");
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/show")
+                .cookie(flash_cookie)
+                .to_request(),
+        )
+        .await;
+        let body_bytes = actix_web::test::read_body(resp).await;
+        let body = std::str::from_utf8(&body_bytes).unwrap();
+        assert_eq!(body, "Hey there! - info\n");
+    }
+}
+
+#[cfg(feature = "cookies")]
+mod key_rotation {
+    use super::*;
+    use actix_web::dev::ResponseHead;
+    use actix_web::http::StatusCode;
+    use actix_web_flash_messages::storage::{CookieMessageStore, FlashMessageStore};
+
+    pub(super) fn flash_cookie(store: &CookieMessageStore) -> actix_web::cookie::Cookie<'static> {
+        let mut head = ResponseHead::new(StatusCode::OK);
+        store
+            .store(
+                &[FlashMessage::info("Hey there!")],
+                actix_web::test::TestRequest::default().to_http_request(),
+                &mut head,
+            )
+            .unwrap();
+        let header = head
+            .headers()
+            .get(actix_web::http::header::SET_COOKIE)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_owned();
+        actix_web::cookie::Cookie::parse_encoded(header).unwrap()
+    }
+
+    pub(super) fn load(
+        store: &CookieMessageStore,
+        cookie: actix_web::cookie::Cookie<'static>,
+    ) -> Vec<FlashMessage> {
+        store
+            .load(
+                &actix_web::test::TestRequest::default()
+                    .cookie(cookie)
+                    .to_http_request(),
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn test_cookies_signed_with_a_previous_key_are_accepted() {
+        let old_key = Key::generate();
+        let new_key = Key::generate();
+        let rotated_store = CookieMessageStore::builder(new_key)
+            .key_id("v2".into())
+            .verification_key("v1".into(), old_key.clone())
+            .build();
+
+        // A cookie carrying the identifier of the previous key.
+        let old_store = CookieMessageStore::builder(old_key.clone())
+            .key_id("v1".into())
+            .build();
+        let cookie = flash_cookie(&old_store);
+        assert!(cookie.value().starts_with("v1."));
+        assert_eq!(load(&rotated_store, cookie).len(), 1);
+
+        // A cookie set before key identifiers were introduced.
+        let legacy_store = CookieMessageStore::builder(old_key).build();
+        assert_eq!(load(&rotated_store, flash_cookie(&legacy_store)).len(), 1);
+
+        // A cookie signed with the current key.
+        assert_eq!(load(&rotated_store, flash_cookie(&rotated_store)).len(), 1);
+    }
+
+    #[test]
+    fn test_cookies_signed_with_an_unknown_key_are_rejected() {
+        let store = CookieMessageStore::builder(Key::generate())
+            .key_id("v2".into())
+            .build();
+        let unknown_store = CookieMessageStore::builder(Key::generate())
+            .key_id("v2".into())
+            .build();
+        let error = store
+            .load(
+                &actix_web::test::TestRequest::default()
+                    .cookie(flash_cookie(&unknown_store))
+                    .to_http_request(),
+            )
+            .err()
+            .unwrap();
+        assert!(error.is_integrity_failure());
+        assert!(!error.is_deserialization());
+        assert!(!error.is_generic());
+    }
+}
+
+#[cfg(all(feature = "cookies", feature = "test-util"))]
+mod derived_keys {
+    use super::key_rotation::{flash_cookie, load};
+    use super::*;
+    use actix_web_flash_messages::storage::{
+        CookieMessageStore, DerivedKeyProvider, FlashMessageStore, LoadError,
+    };
+    use std::sync::{Arc, Mutex};
+    use time::{Date, Month};
+
+    /// A fixed "today", to stay clear of the actual day boundary.
+    fn day(offset: i64) -> Date {
+        Date::from_calendar_date(2024, Month::February, 27).unwrap() + time::Duration::days(offset)
+    }
+
+    /// A derived store that believes today is `date`.
+    fn store_on(provider: &DerivedKeyProvider, date: Date) -> CookieMessageStore {
+        CookieMessageStore::builder_with_derived_keys(provider.clone().today(move || date)).build()
+    }
+
+    /// The cookie the derived store issues on `date`.
+    fn cookie_issued(
+        provider: &DerivedKeyProvider,
+        date: Date,
+    ) -> actix_web::cookie::Cookie<'static> {
+        flash_cookie(&store_on(provider, date))
+    }
+
+    fn try_load(
+        store: &CookieMessageStore,
+        cookie: actix_web::cookie::Cookie<'static>,
+    ) -> Result<Vec<FlashMessage>, LoadError> {
+        store.load(
+            &actix_web::test::TestRequest::default()
+                .cookie(cookie)
+                .to_http_request(),
+        )
+    }
+
+    #[test]
+    fn test_cookies_are_signed_with_the_key_of_the_day() {
+        let provider = DerivedKeyProvider::new(Key::generate());
+        let store = store_on(&provider, day(0));
+        let cookie = flash_cookie(&store);
+        assert!(cookie.value().starts_with("2024-02-27."));
+        assert_eq!(load(&store, cookie).len(), 1);
+
+        // A cookie signed manually, with the key derived for the day, is accepted as well.
+        let manual_store = CookieMessageStore::builder(provider.key_for(day(0)))
+            .key_id(day(0).to_string())
+            .build();
+        assert_eq!(load(&store, flash_cookie(&manual_store)).len(), 1);
+    }
+
+    #[test]
+    fn test_cookies_issued_right_before_midnight_are_accepted_right_after_it() {
+        let provider = DerivedKeyProvider::new(Key::generate());
+        let today = Arc::new(Mutex::new(day(0)));
+        let clock = today.clone();
+        let store = CookieMessageStore::builder_with_derived_keys(
+            provider.today(move || *clock.lock().unwrap()),
+        )
+        .build();
+
+        // Issued at 23:59:59 on day D...
+        let cookie = flash_cookie(&store);
+        // ...read at 00:00:01 on day D+1.
+        *today.lock().unwrap() = day(1);
+        assert_eq!(load(&store, cookie).len(), 1);
+        // Outgoing cookies are now signed with the key of day D+1.
+        assert!(flash_cookie(&store).value().starts_with("2024-02-28."));
+    }
+
+    #[test]
+    fn test_cookies_issued_yesterday_are_accepted_by_default() {
+        let provider = DerivedKeyProvider::new(Key::generate());
+        let store = store_on(&provider, day(2));
+        assert_eq!(load(&store, cookie_issued(&provider, day(1))).len(), 1);
+
+        let error = try_load(&store, cookie_issued(&provider, day(0)))
+            .err()
+            .unwrap();
+        assert!(error.is_integrity_failure());
+    }
+
+    #[test]
+    fn test_the_verification_window_is_configurable() {
+        let provider = DerivedKeyProvider::new(Key::generate());
+        // Issued on day D, accepted up to day D+N with `previous_days(N)`...
+        let store = store_on(&provider.clone().previous_days(2), day(2));
+        assert_eq!(load(&store, cookie_issued(&provider, day(0))).len(), 1);
+        // ...and rejected from day D+N+1 onwards.
+        let store = store_on(&provider.clone().previous_days(2), day(3));
+        let error = try_load(&store, cookie_issued(&provider, day(0)))
+            .err()
+            .unwrap();
+        assert!(error.is_integrity_failure());
+
+        // Only today's key is accepted: cookies in flight at midnight are rejected.
+        let provider = provider.previous_days(0);
+        let store = store_on(&provider, day(1));
+        assert_eq!(load(&store, cookie_issued(&provider, day(1))).len(), 1);
+        assert!(try_load(&store, cookie_issued(&provider, day(0))).is_err());
+    }
+
+    #[test]
+    fn test_keys_derived_from_a_different_master_key_are_rejected() {
+        let store = store_on(&DerivedKeyProvider::new(Key::generate()), day(0));
+        let other_provider = DerivedKeyProvider::new(Key::generate());
+        let error = try_load(&store, cookie_issued(&other_provider, day(0)))
+            .err()
+            .unwrap();
+        assert!(error.is_integrity_failure());
+    }
+
+    #[test]
+    fn test_static_verification_keys_are_still_accepted() {
+        let old_key = Key::generate();
+        let store =
+            CookieMessageStore::builder_with_derived_keys(DerivedKeyProvider::new(Key::generate()))
+                .verification_key("v1".into(), old_key.clone())
+                .build();
+        let old_store = CookieMessageStore::builder(old_key.clone())
+            .key_id("v1".into())
+            .build();
+        assert_eq!(load(&store, flash_cookie(&old_store)).len(), 1);
+        let legacy_store = CookieMessageStore::builder(old_key).build();
+        assert_eq!(load(&store, flash_cookie(&legacy_store)).len(), 1);
+    }
+
+    #[test]
+    fn test_each_day_gets_a_different_key() {
+        let provider = DerivedKeyProvider::new(Key::generate());
+        assert_eq!(
+            provider.key_for(day(0)).master(),
+            provider.key_for(day(0)).master()
+        );
+        assert_ne!(
+            provider.key_for(day(0)).master(),
+            provider.key_for(day(1)).master()
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "A key identifier cannot be set when using derived keys")]
+    fn test_key_ids_cannot_be_combined_with_derived_keys() {
+        let _ =
+            CookieMessageStore::builder_with_derived_keys(DerivedKeyProvider::new(Key::generate()))
+                .key_id("v1".into())
+                .build();
+    }
+}
+
+#[cfg(feature = "cookies")]
+mod interop {
+    use super::*;
+    use actix_web_flash_messages::interop::FlashAppExt;
+    use actix_web_flash_messages::storage::CookieMessageStore;
+
+    #[actix_rt::test]
+    async fn test_with_flash_messages_mounts_a_json_endpoint() {
+        let cookie_store = CookieMessageStore::builder(Key::generate()).build();
+        let app = actix_web::test::init_service(
+            App::new()
+                .service(resource("/set").route(web::get().to(set)))
+                .with_flash_messages(FlashMessagesFramework::builder(cookie_store).build()),
+        )
+        .await;
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get().uri("/set").to_request(),
+        )
+        .await;
+        let flash_cookie = resp
+            .response()
+            .cookies()
+            .find(|c| c.name() == "_flash")
+            .unwrap()
+            .into_owned();
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/flash.json")
+                .cookie(flash_cookie)
+                .to_request(),
+        )
+        .await;
+        let body: serde_json::Value = actix_web::test::read_body_json(resp).await;
+        let messages = body["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0]["content"], "Hey there!");
+    }
+}
+
+#[cfg(feature = "test-util")]
+mod capturing {
+    use super::*;
+    use actix_web_flash_messages::storage::CapturingMessageStore;
+
+    #[actix_rt::test]
+    async fn test_capturing_store_records_outgoing_messages() {
+        let store = CapturingMessageStore::default();
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(FlashMessagesFramework::builder(store.clone()).build())
+                .service(resource("/set").route(web::get().to(set)))
+                .service(resource("/show").route(web::get().to(show))),
+        )
+        .await;
+
+        actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get().uri("/set").to_request(),
+        )
+        .await;
+        {
+            let captured = store.captured.lock().unwrap();
+            assert_eq!(captured.len(), 1);
+            assert_eq!(captured[0].content(), "Hey there!");
+        }
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/show")
+                .to_request(),
+        )
+        .await;
+        let body_bytes = actix_web::test::read_body(resp).await;
+        assert_eq!(
+            std::str::from_utf8(&body_bytes).unwrap(),
+            "Hey there! - info\n"
+        );
+        assert!(store.captured.lock().unwrap().is_empty());
+    }
+}
+
+#[cfg(feature = "sessions")]
+mod session_size_limit {
+    use actix_web::dev::ResponseHead;
+    use actix_web::http::StatusCode;
+    use actix_web_flash_messages::storage::{FlashMessageStore, SessionMessageStore};
+    use actix_web_flash_messages::FlashMessage;
+
+    #[test]
+    fn test_session_store_enforces_the_size_limit() {
+        let store = SessionMessageStore::default().bytes_size_limit(128);
+        let request = actix_web::test::TestRequest::default().to_http_request();
+
+        let result = store.store(
+            &[FlashMessage::info("Short")],
+            request.clone(),
+            &mut ResponseHead::new(StatusCode::OK),
+        );
+        assert!(result.is_ok());
+
+        let result = store.store(
+            &[FlashMessage::info("x".repeat(200))],
+            request,
+            &mut ResponseHead::new(StatusCode::OK),
+        );
+        let error = result.unwrap_err();
+        assert!(error.is_size_limit_exceeded());
+        assert!(!error.is_serialization());
+        assert!(!error.is_generic());
+    }
+}
+
+#[cfg(feature = "cookies")]
+mod peeking {
+    use super::*;
+    use actix_web_flash_messages::storage::CookieMessageStore;
+    use actix_web_flash_messages::FlashMessageCount;
+
+    async fn badge(FlashMessageCount(count): FlashMessageCount) -> impl Responder {
+        HttpResponse::Ok().body(count.to_string())
+    }
+
+    #[actix_rt::test]
+    async fn test_counting_messages_does_not_consume_them() {
+        let cookie_store = CookieMessageStore::builder(Key::generate()).build();
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(FlashMessagesFramework::builder(cookie_store).build())
+                .service(resource("/set").route(web::get().to(set)))
+                .service(resource("/badge").route(web::get().to(badge)))
+                .service(resource("/show").route(web::get().to(show))),
+        )
+        .await;
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get().uri("/set").to_request(),
+        )
+        .await;
+        let flash_cookie = resp
+            .response()
+            .cookies()
+            .find(|c| c.name() == "_flash")
+            .unwrap()
+            .into_owned();
+
+        // Peeking carries the messages over to the next request.
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/badge")
+                .cookie(flash_cookie)
+                .to_request(),
+        )
+        .await;
+        let flash_cookie = resp
+            .response()
+            .cookies()
+            .find(|c| c.name() == "_flash")
+            .unwrap()
+            .into_owned();
+        assert!(!flash_cookie.value().is_empty());
+        let body_bytes = actix_web::test::read_body(resp).await;
+        assert_eq!(std::str::from_utf8(&body_bytes).unwrap(), "1");
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/show")
+                .cookie(flash_cookie)
+                .to_request(),
+        )
+        .await;
+        let cookie = resp.response().cookies().next().unwrap().into_owned();
+        assert_eq!(cookie.value(), "");
+        let body_bytes = actix_web::test::read_body(resp).await;
+        assert_eq!(
+            std::str::from_utf8(&body_bytes).unwrap(),
+            "Hey there! - info\n"
+        );
+    }
+}
+
+#[cfg(feature = "cookies")]
+mod key_from_env {
+    use actix_web_flash_messages::storage::{CookieMessageStore, KeyError};
+    use base64::Engine;
+
+    #[test]
+    fn test_signing_key_is_loaded_from_the_environment() {
+        let encoded = base64::engine::general_purpose::STANDARD.encode([42u8; 64]);
+        std::env::set_var("FLASH_TEST_VALID_KEY", encoded);
+        assert!(CookieMessageStore::builder_from_env("FLASH_TEST_VALID_KEY").is_ok());
+    }
+
+    #[test]
+    fn test_invalid_signing_keys_are_rejected() {
+        assert!(matches!(
+            CookieMessageStore::builder_from_env("FLASH_TEST_MISSING_KEY"),
+            Err(KeyError::MissingEnvVar(_))
+        ));
+
+        std::env::set_var("FLASH_TEST_NOT_BASE64_KEY", "not base64!");
+        assert!(matches!(
+            CookieMessageStore::builder_from_env("FLASH_TEST_NOT_BASE64_KEY"),
+            Err(KeyError::DecodingError(_))
+        ));
+
+        let encoded = base64::engine::general_purpose::STANDARD.encode([42u8; 32]);
+        std::env::set_var("FLASH_TEST_SHORT_KEY", encoded);
+        assert!(matches!(
+            CookieMessageStore::builder_from_env("FLASH_TEST_SHORT_KEY"),
+            Err(KeyError::InvalidLength(_))
+        ));
+    }
+}
+
+#[cfg(feature = "cookies")]
+mod websocket_handshake {
+    use super::*;
+    use actix_web::http::{header, StatusCode};
+    use actix_web_flash_messages::storage::CookieMessageStore;
+
+    async fn upgrade() -> impl Responder {
+        FlashMessage::info("Connected!").send();
+        HttpResponse::SwitchingProtocols()
+            .insert_header((header::UPGRADE, "websocket"))
+            .insert_header((header::CONNECTION, "upgrade"))
+            .finish()
+    }
+
+    async fn reject() -> impl Responder {
+        FlashMessage::error("You must log in to open a live connection").send();
+        HttpResponse::Forbidden().finish()
+    }
+
+    #[actix_rt::test]
+    async fn test_flash_cookies_are_attached_to_handshake_responses() {
+        let cookie_store = CookieMessageStore::builder(Key::generate()).build();
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(FlashMessagesFramework::builder(cookie_store).build())
+                .service(resource("/ws").route(web::get().to(upgrade)))
+                .service(resource("/ws-rejected").route(web::get().to(reject))),
+        )
+        .await;
+
+        for (uri, expected_status) in [
+            ("/ws", StatusCode::SWITCHING_PROTOCOLS),
+            ("/ws-rejected", StatusCode::FORBIDDEN),
+        ] {
+            let resp = actix_web::test::call_service(
+                &app,
+                actix_web::test::TestRequest::get()
+                    .uri(uri)
+                    .insert_header((header::UPGRADE, "websocket"))
+                    .insert_header((header::CONNECTION, "upgrade"))
+                    .to_request(),
+            )
+            .await;
+            assert_eq!(resp.status(), expected_status);
+            let flash_cookie = resp
+                .response()
+                .cookies()
+                .find(|c| c.name() == "_flash")
+                .unwrap();
+            assert!(!flash_cookie.value().is_empty());
+        }
+    }
+}
+
+mod message_builder {
+    use actix_web_flash_messages::{FlashMessage, Level};
+
+    #[test]
+    fn test_static_content_survives_a_serialization_round_trip() {
+        let message = FlashMessage::new_static("Saved successfully", Level::Success);
+        assert_eq!(message.content(), "Saved successfully");
+        let serialized = serde_json::to_string(&message).unwrap();
+        let message: FlashMessage = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(message.content(), "Saved successfully");
+        assert_eq!(message.level(), Level::Success);
+        let message = message.with_content("Saved!");
+        assert_eq!(message.content(), "Saved!");
+    }
+
+    #[test]
+    fn test_level_str_matches_the_display_implementation() {
+        for level in [
+            Level::Debug,
+            Level::Info,
+            Level::Success,
+            Level::Warning,
+            Level::Error,
+        ] {
+            let message = FlashMessage::new("Hey there!".into(), level);
+            assert_eq!(message.level_str(), level.to_string());
+        }
+        assert_eq!(FlashMessage::warning("Careful").level_str(), "warning");
+    }
+
+    #[test]
+    fn test_into_content_returns_the_content() {
+        let message = FlashMessage::info("Hey there!").with_code("greeting");
+        assert_eq!(message.content(), "Hey there!");
+        assert_eq!(message.into_content(), "Hey there!");
+        let message = FlashMessage::new_static("Saved", Level::Success);
+        assert_eq!(message.into_content(), "Saved");
+    }
+
+    #[test]
+    fn test_level_is_derived_from_the_status_class() {
+        use actix_web::http::StatusCode;
+
+        let cases = [
+            (StatusCode::CONTINUE, Level::Info),
+            (StatusCode::CREATED, Level::Success),
+            (StatusCode::SEE_OTHER, Level::Info),
+            (StatusCode::NOT_FOUND, Level::Warning),
+            (StatusCode::SERVICE_UNAVAILABLE, Level::Error),
+        ];
+        for (status, level) in cases {
+            let message = FlashMessage::from_status(status, "Done");
+            assert_eq!(message.level(), level, "Unexpected level for {}", status);
+            assert_eq!(message.content(), "Done");
+        }
+    }
+
+    #[test]
+    fn test_optional_fields_survive_a_serialization_round_trip() {
+        let message = FlashMessage::builder()
+            .content("Your password will expire in 3 days")
+            .level(Level::Warning)
+            .title("Security")
+            .metadata("expires_in_days", "3")
+            .build();
+        let serialized = serde_json::to_string(&message).unwrap();
+        let message: FlashMessage = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(message.content(), "Your password will expire in 3 days");
+        assert_eq!(message.level(), Level::Warning);
+        assert_eq!(message.title(), Some("Security"));
+        assert_eq!(message.metadata()["expires_in_days"], "3");
+    }
+
+    #[test]
+    fn test_codes_survive_a_serialization_round_trip() {
+        let message = FlashMessage::success("Check your inbox!").with_code("password.reset.sent");
+        let serialized = serde_json::to_value(&message).unwrap();
+        assert_eq!(serialized["code"], "password.reset.sent");
+        let message: FlashMessage = serde_json::from_value(serialized).unwrap();
+        assert_eq!(message.code(), Some("password.reset.sent"));
+
+        let message: FlashMessage =
+            serde_json::from_str(r#"{"content": "Hey there!", "level": "Info"}"#).unwrap();
+        assert_eq!(message.code(), None);
+    }
+
+    #[test]
+    fn test_content_and_level_can_be_rewritten() {
+        let message = FlashMessage::builder()
+            .content("Disk almost full")
+            .level(Level::Warning)
+            .title("Storage")
+            .metadata("usage", "97%")
+            .build()
+            .with_code("disk.full");
+        let message = message
+            .with_content("Le disque est presque plein")
+            .with_level(Level::Error);
+        assert_eq!(message.content(), "Le disque est presque plein");
+        assert_eq!(message.level(), Level::Error);
+        // All other fields are retained.
+        assert_eq!(message.title(), Some("Storage"));
+        assert_eq!(message.metadata()["usage"], "97%");
+        assert_eq!(message.code(), Some("disk.full"));
+    }
+
+    #[test]
+    fn test_builder_defaults_to_an_info_message() {
+        let message = FlashMessage::builder().content("Hey there!").build();
+        assert_eq!(message.level(), Level::Info);
+        assert_eq!(message.title(), None);
+        assert!(message.metadata().is_empty());
+    }
+}
+
+mod headers {
+    use super::*;
+    use actix_web_flash_messages::storage::HeaderMessageStore;
+
+    #[actix_rt::test]
+    async fn test_flash_messages_are_sent_and_read_as_headers() {
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(
+                    FlashMessagesFramework::builder(
+                        HeaderMessageStore::default().trust_incoming(true),
+                    )
+                    .build(),
+                )
+                .service(resource("/set").route(web::get().to(set)))
+                .service(resource("/show").route(web::get().to(show))),
+        )
+        .await;
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get().uri("/set").to_request(),
+        )
+        .await;
+        let headers = resp
+            .headers()
+            .get_all("X-Flash")
+            .map(|h| h.to_str().unwrap().to_owned())
+            .collect::<Vec<_>>();
+        assert_eq!(headers, vec!["info;Hey%20there%21".to_string()]);
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/show")
+                .insert_header(("X-Flash", "warning;Caf%C3%A9%3B%20closed"))
+                .to_request(),
+        )
+        .await;
+        let body_bytes = actix_web::test::read_body(resp).await;
+        assert_eq!(
+            std::str::from_utf8(&body_bytes).unwrap(),
+            "Café; closed - warning\n"
+        );
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/show")
+                .insert_header(("X-Flash", "unknown;Hey"))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_rt::test]
+    async fn test_incoming_headers_are_ignored_by_default() {
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(FlashMessagesFramework::builder(HeaderMessageStore::default()).build())
+                .service(resource("/show").route(web::get().to(show))),
+        )
+        .await;
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/show")
+                .insert_header(("X-Flash", "error;Call%20us%20now"))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), 200);
+        let body_bytes = actix_web::test::read_body(resp).await;
+        assert!(body_bytes.is_empty());
+    }
+}
+
+#[cfg(feature = "cookies")]
+mod clear_on_read {
+    use super::*;
+    use actix_web::dev::{ServiceFactory, ServiceRequest, ServiceResponse};
+    use actix_web_flash_messages::storage::CookieMessageStore;
+
+    async fn read_and_resend(messages: IncomingFlashMessages) -> impl Responder {
+        assert_eq!(messages.iter().len(), 1);
+        FlashMessage::warning("Please fix the errors below").send();
+        HttpResponse::Ok()
+    }
+
+    async fn noop() -> impl Responder {
+        HttpResponse::Ok()
+    }
+
+    fn flash_cookie(resp: &ServiceResponse) -> actix_web::cookie::Cookie<'static> {
+        resp.response()
+            .cookies()
+            .find(|c| c.name() == "_flash")
+            .unwrap()
+            .into_owned()
+    }
+
+    fn build_app(
+        clear_on_read: bool,
+    ) -> App<
+        impl ServiceFactory<
+            ServiceRequest,
+            Config = (),
+            Response = ServiceResponse,
+            Error = actix_web::Error,
+            InitError = (),
+        >,
+    > {
+        let cookie_store = CookieMessageStore::builder(Key::generate()).build();
+        App::new()
+            .wrap(
+                FlashMessagesFramework::builder(cookie_store)
+                    .clear_on_read(clear_on_read)
+                    .build(),
+            )
+            .service(resource("/set").route(web::get().to(set)))
+            .service(resource("/resend").route(web::get().to(read_and_resend)))
+            .service(resource("/noop").route(web::get().to(noop)))
+            .service(resource("/show").route(web::get().to(show)))
+    }
+
+    #[actix_rt::test]
+    async fn test_consumed_messages_are_cleared_when_new_ones_are_sent() {
+        let app = actix_web::test::init_service(build_app(false)).await;
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get().uri("/set").to_request(),
+        )
+        .await;
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/resend")
+                .cookie(flash_cookie(&resp))
+                .to_request(),
+        )
+        .await;
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/show")
+                .cookie(flash_cookie(&resp))
+                .to_request(),
+        )
+        .await;
+        let body_bytes = actix_web::test::read_body(resp).await;
+        assert_eq!(
+            std::str::from_utf8(&body_bytes).unwrap(),
+            "Please fix the errors below - warning\n"
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_messages_are_retained_until_read() {
+        let app = actix_web::test::init_service(build_app(true)).await;
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get().uri("/set").to_request(),
+        )
+        .await;
+        // A request that does not read flash messages carries them over.
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/noop")
+                .cookie(flash_cookie(&resp))
+                .to_request(),
+        )
+        .await;
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/show")
+                .cookie(flash_cookie(&resp))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(flash_cookie(&resp).value(), "");
+        let body_bytes = actix_web::test::read_body(resp).await;
+        assert_eq!(
+            std::str::from_utf8(&body_bytes).unwrap(),
+            "Hey there! - info\n"
+        );
+    }
+}
+
+#[cfg(feature = "cookies")]
+mod flash_scope {
+    use super::*;
+    use actix_web_flash_messages::storage::CookieMessageStore;
+    use actix_web_flash_messages::FlashScope;
+
+    async fn set_with_scope(mut flash: FlashScope) -> impl Responder {
+        FlashMessage::info("Sent directly").send();
+        flash.info("Committed explicitly");
+        flash.debug("Below the minimum level");
+        assert_eq!(flash.pending().len(), 2);
+        flash.commit();
+        HttpResponse::Ok()
+    }
+
+    async fn set_with_dropped_scope(mut flash: FlashScope) -> impl Responder {
+        flash.warning("Committed on drop");
+        HttpResponse::Ok()
+    }
+
+    #[actix_rt::test]
+    async fn test_flash_scope_shares_the_mailbox_with_send() {
+        let cookie_store = CookieMessageStore::builder(Key::generate()).build();
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(FlashMessagesFramework::builder(cookie_store).build())
+                .service(resource("/set").route(web::get().to(set_with_scope)))
+                .service(resource("/set-dropped").route(web::get().to(set_with_dropped_scope)))
+                .service(resource("/show").route(web::get().to(show))),
+        )
+        .await;
+
+        for (uri, expected_body) in [
+            (
+                "/set",
+                "Sent directly - info\nCommitted explicitly - info\n",
+            ),
+            ("/set-dropped", "Committed on drop - warning\n"),
+        ] {
+            let resp = actix_web::test::call_service(
+                &app,
+                actix_web::test::TestRequest::get().uri(uri).to_request(),
+            )
+            .await;
+            let flash_cookie = resp
+                .response()
+                .cookies()
+                .find(|c| c.name() == "_flash")
+                .unwrap()
+                .into_owned();
+            let resp = actix_web::test::call_service(
+                &app,
+                actix_web::test::TestRequest::get()
+                    .uri("/show")
+                    .cookie(flash_cookie)
+                    .to_request(),
+            )
+            .await;
+            let body_bytes = actix_web::test::read_body(resp).await;
+            assert_eq!(std::str::from_utf8(&body_bytes).unwrap(), expected_body);
+        }
+    }
+}
+
+#[cfg(feature = "cookies")]
+mod rendering {
+    use super::*;
+    use actix_web_flash_messages::storage::CookieMessageStore;
+
+    async fn show_rendered(messages: IncomingFlashMessages) -> impl Responder {
+        let custom = messages.render_with(|m| m.content().to_uppercase());
+        let default = messages.rendered_iter().collect::<Vec<_>>();
+        HttpResponse::Ok().body(format!("{}|{}", custom.join(","), default.join(",")))
+    }
+
+    #[actix_rt::test]
+    async fn test_messages_are_rendered_with_the_configured_renderer() {
+        let cookie_store = CookieMessageStore::builder(Key::generate()).build();
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(
+                    FlashMessagesFramework::builder(cookie_store)
+                        .renderer(|m| format!("<p>{}</p>", m.content()))
+                        .build(),
+                )
+                .service(resource("/set").route(web::get().to(set)))
+                .service(resource("/show").route(web::get().to(show_rendered))),
+        )
+        .await;
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get().uri("/set").to_request(),
+        )
+        .await;
+        let flash_cookie = resp
+            .response()
+            .cookies()
+            .find(|c| c.name() == "_flash")
+            .unwrap()
+            .into_owned();
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/show")
+                .cookie(flash_cookie)
+                .to_request(),
+        )
+        .await;
+        let body_bytes = actix_web::test::read_body(resp).await;
+        assert_eq!(
+            std::str::from_utf8(&body_bytes).unwrap(),
+            "HEY THERE!|<p>Hey there!</p>"
+        );
+    }
+}
+
+mod flatten {
+    use actix_web_flash_messages::{FlashMessage, Level};
+
+    #[derive(serde::Serialize, serde::Deserialize)]
+    struct AppFlash {
+        #[serde(flatten)]
+        message: FlashMessage,
+        dismissible: bool,
+    }
+
+    #[test]
+    fn test_flash_message_can_be_flattened_into_an_outer_struct() {
+        let flash = AppFlash {
+            message: FlashMessage::builder()
+                .content("Hey there!")
+                .level(Level::Warning)
+                .title("Heads up")
+                .build(),
+            dismissible: true,
+        };
+        let serialized = serde_json::to_value(&flash).unwrap();
+        assert_eq!(serialized["content"], "Hey there!");
+        assert_eq!(serialized["title"], "Heads up");
+        assert_eq!(serialized["dismissible"], true);
+        assert!(serialized.get("metadata").is_none());
+
+        let flash: AppFlash = serde_json::from_value(serialized).unwrap();
+        assert_eq!(flash.message.content(), "Hey there!");
+        assert_eq!(flash.message.level(), Level::Warning);
+        assert_eq!(flash.message.title(), Some("Heads up"));
+        assert!(flash.dismissible);
+
+        // Numeric levels are accepted when flattened as well.
+        if cfg!(feature = "numeric-levels") {
+            let flash: AppFlash =
+                serde_json::from_str(r#"{"content": "Oops", "level": 4, "dismissible": false}"#)
+                    .unwrap();
+            assert_eq!(flash.message.level(), Level::Error);
+        }
+    }
+}
+
+#[cfg(feature = "cookies")]
+mod max_chunks {
+    use super::*;
+    use actix_web::cookie::time::Duration;
+    use actix_web::cookie::Cookie;
+    use actix_web_flash_messages::storage::{CookieMessageStore, FlashMessageStore};
+
+    #[test]
+    fn test_requests_with_too_many_flash_cookies_are_rejected() {
+        let store = CookieMessageStore::builder(Key::generate())
+            .max_chunks(4)
+            .build();
+
+        let mut request = actix_web::test::TestRequest::default();
+        for i in 0..3 {
+            request = request.cookie(Cookie::new(format!("_flash.{}", i), "chunk"));
+        }
+        // Unrelated cookies are not counted.
+        request = request.cookie(Cookie::new("_flash.other", "value"));
+        assert!(store.load(&request.to_http_request()).is_ok());
+
+        let mut request = actix_web::test::TestRequest::default();
+        for i in 0..5 {
+            request = request.cookie(Cookie::new(format!("_flash.{}", i), "chunk"));
+        }
+        let error = store.load(&request.to_http_request()).err().unwrap();
+        assert!(error.is_integrity_failure());
+    }
+
+    #[actix_rt::test]
+    async fn test_chunk_cookies_are_cleared_by_the_next_response() {
+        let store = CookieMessageStore::builder(Key::generate())
+            .max_chunks(4)
+            .build();
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(FlashMessagesFramework::builder(store).build())
+                .service(resource("/show").route(web::get().to(show))),
+        )
+        .await;
+
+        let mut request = actix_web::test::TestRequest::get().uri("/show");
+        for i in 0..5 {
+            request = request.cookie(Cookie::new(format!("_flash.{}", i), "chunk"));
+        }
+        let resp = actix_web::test::call_service(&app, request.to_request()).await;
+        assert_eq!(resp.status(), 400);
+        let mut removed: Vec<_> = resp
+            .response()
+            .cookies()
+            .filter(|c| c.value().is_empty() && c.max_age() == Some(Duration::ZERO))
+            .map(|c| c.name().to_owned())
+            .collect();
+        removed.sort();
+        assert_eq!(
+            removed,
+            vec!["_flash.0", "_flash.1", "_flash.2", "_flash.3", "_flash.4"]
+        );
+    }
+}
+
+#[cfg(feature = "cookies")]
+mod scoped {
+    use super::*;
+    use actix_web_flash_messages::storage::CookieMessageStore;
+
+    async fn noop() -> impl Responder {
+        HttpResponse::Ok()
+    }
+
+    #[actix_rt::test]
+    async fn test_framework_can_wrap_a_scope() {
+        let cookie_store = CookieMessageStore::builder(Key::generate()).build();
+        let app = actix_web::test::init_service(
+            App::new()
+                .service(
+                    web::scope("/admin")
+                        .wrap(FlashMessagesFramework::builder(cookie_store).build())
+                        .service(resource("/set").route(web::get().to(set)))
+                        .service(resource("/show").route(web::get().to(show))),
+                )
+                .service(resource("/public").route(web::get().to(noop))),
+        )
+        .await;
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/admin/set")
+                .to_request(),
+        )
+        .await;
+        let flash_cookie = resp
+            .response()
+            .cookies()
+            .find(|c| c.name() == "_flash")
+            .unwrap()
+            .into_owned();
+
+        // Routes outside of the scope are not touched by the framework.
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/public")
+                .cookie(flash_cookie.clone())
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.response().cookies().count(), 0);
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/admin/show")
+                .cookie(flash_cookie)
+                .to_request(),
+        )
+        .await;
+        let body_bytes = actix_web::test::read_body(resp).await;
+        assert_eq!(
+            std::str::from_utf8(&body_bytes).unwrap(),
+            "Hey there! - info\n"
+        );
+    }
+}
+
+#[cfg(feature = "cookies")]
+mod nested {
+    use super::*;
+    use actix_web_flash_messages::storage::CookieMessageStore;
+    use actix_web_flash_messages::Level;
+
+    fn flash_cookie(
+        resp: &actix_web::dev::ServiceResponse,
+        name: &str,
+    ) -> actix_web::cookie::Cookie<'static> {
+        resp.response()
+            .cookies()
+            .find(|c| c.name() == name)
+            .unwrap()
+            .into_owned()
+    }
+
+    #[actix_rt::test]
+    async fn test_nested_frameworks_do_not_cross_contaminate() {
+        let outer_store = CookieMessageStore::builder(Key::generate())
+            .cookie_name("_outer".into())
+            .build();
+        let inner_store = CookieMessageStore::builder(Key::generate())
+            .cookie_name("_inner".into())
+            .build();
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(
+                    FlashMessagesFramework::builder(outer_store)
+                        .clear_on_read(true)
+                        .build(),
+                )
+                .service(
+                    web::scope("/admin")
+                        .wrap(FlashMessagesFramework::builder(inner_store).build())
+                        .service(resource("/set").route(web::get().to(set)))
+                        .service(resource("/show").route(web::get().to(show))),
+                )
+                .service(resource("/set").route(web::get().to(set)))
+                .service(resource("/show").route(web::get().to(show))),
+        )
+        .await;
+
+        // Messages sent outside of the scope go to the outer framework...
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get().uri("/set").to_request(),
+        )
+        .await;
+        let outer_cookie = flash_cookie(&resp, "_outer");
+        assert!(resp.response().cookies().all(|c| c.name() != "_inner"));
+
+        // ...while messages sent within the scope go to the innermost one.
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/admin/set")
+                .cookie(outer_cookie.clone())
+                .to_request(),
+        )
+        .await;
+        let inner_cookie = flash_cookie(&resp, "_inner");
+        // Reading and writing the inner store does not consume the outer messages.
+        let outer_cookie = flash_cookie(&resp, "_outer");
+        assert!(!outer_cookie.value().is_empty());
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/admin/show")
+                .cookie(outer_cookie)
+                .cookie(inner_cookie)
+                .to_request(),
+        )
+        .await;
+        let outer_cookie = flash_cookie(&resp, "_outer");
+        assert!(!outer_cookie.value().is_empty());
+        assert_eq!(flash_cookie(&resp, "_inner").value(), "");
+        let body_bytes = actix_web::test::read_body(resp).await;
+        assert_eq!(
+            std::str::from_utf8(&body_bytes).unwrap(),
+            "Hey there! - info\n"
+        );
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/show")
+                .cookie(outer_cookie)
+                .to_request(),
+        )
+        .await;
+        assert_eq!(flash_cookie(&resp, "_outer").value(), "");
+        let body_bytes = actix_web::test::read_body(resp).await;
+        assert_eq!(
+            std::str::from_utf8(&body_bytes).unwrap(),
+            "Hey there! - info\n"
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_outer_settings_do_not_leak_into_a_nested_framework() {
+        let outer_store = CookieMessageStore::builder(Key::generate())
+            .cookie_name("_outer".into())
+            .build();
+        let inner_store = CookieMessageStore::builder(Key::generate())
+            .cookie_name("_inner".into())
+            .build();
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(
+                    FlashMessagesFramework::builder(outer_store)
+                        .debug_param("flash_debug", "1")
+                        .on_load(|messages| {
+                            messages
+                                .into_iter()
+                                .map(|m| {
+                                    let content = m.content().to_uppercase();
+                                    m.with_content(content)
+                                })
+                                .collect()
+                        })
+                        .build(),
+                )
+                .service(
+                    web::scope("/admin")
+                        .wrap(
+                            FlashMessagesFramework::builder(inner_store)
+                                .minimum_level(Level::Debug)
+                                .build(),
+                        )
+                        .service(resource("/set").route(web::get().to(set)))
+                        .service(resource("/show").route(web::get().to(show))),
+                ),
+        )
+        .await;
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/admin/set")
+                .to_request(),
+        )
+        .await;
+        let inner_cookie = flash_cookie(&resp, "_inner");
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/admin/show")
+                .cookie(inner_cookie)
+                .to_request(),
+        )
+        .await;
+        let body_bytes = actix_web::test::read_body(resp).await;
+        // Neither the outer `on_load` hook nor the outer read-time level filter apply.
+        assert_eq!(
+            std::str::from_utf8(&body_bytes).unwrap(),
+            "Hey there! - info\nHow is it going? - debug\n"
+        );
+    }
+}
+
+#[cfg(feature = "cookies")]
+mod flash_back {
+    use super::*;
+    use actix_web::http::header;
+    use actix_web::HttpRequest;
+    use actix_web_flash_messages::interop::flash_back;
+    use actix_web_flash_messages::storage::CookieMessageStore;
+
+    async fn go_back(req: HttpRequest) -> HttpResponse {
+        flash_back(&req, [FlashMessage::success("Saved!")])
+    }
+
+    #[actix_rt::test]
+    async fn test_flash_back_redirects_to_same_origin_referers_only() {
+        let cookie_store = CookieMessageStore::builder(Key::generate()).build();
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(FlashMessagesFramework::builder(cookie_store).build())
+                .service(resource("/save").route(web::post().to(go_back))),
+        )
+        .await;
+
+        for (referer, expected_location) in [
+            (
+                Some("http://localhost:8080/posts?page=2&sort=desc"),
+                "http://localhost:8080/posts?page=2&sort=desc",
+            ),
+            (Some("/posts?page=2"), "/posts?page=2"),
+            (Some("https://evil.com/phishing"), "/"),
+            (Some("http://localhost:9999/posts"), "/"),
+            (Some("//evil.com/phishing"), "/"),
+            (None, "/"),
+        ] {
+            let mut request = actix_web::test::TestRequest::post()
+                .uri("/save")
+                .insert_header((header::HOST, "localhost:8080"));
+            if let Some(referer) = referer {
+                request = request.insert_header((header::REFERER, referer));
+            }
+            let resp = actix_web::test::call_service(&app, request.to_request()).await;
+            assert_eq!(resp.status(), actix_web::http::StatusCode::SEE_OTHER);
+            assert_eq!(
+                resp.headers().get(header::LOCATION).unwrap(),
+                expected_location
+            );
+            assert!(resp.response().cookies().any(|c| c.name() == "_flash"));
+        }
+    }
+}
+
+#[cfg(feature = "cookies")]
+mod channels {
+    use super::*;
+    use actix_web_flash_messages::storage::CookieMessageStore;
+    use actix_web_flash_messages::Level;
+
+    async fn set_channels() -> impl Responder {
+        FlashMessage::builder()
+            .content("Welcome back!")
+            .channel("banner")
+            .send();
+        FlashMessage::builder()
+            .content("Email is required")
+            .level(Level::Error)
+            .channel("form-errors")
+            .send();
+        FlashMessage::info("No channel").send();
+        HttpResponse::Ok()
+    }
+
+    async fn show_channels(messages: IncomingFlashMessages) -> impl Responder {
+        let contents = |channel| {
+            messages
+                .channel(channel)
+                .map(|m| m.content())
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+        HttpResponse::Ok().body(format!(
+            "{}|{}|{}",
+            contents("banner"),
+            contents("form-errors"),
+            contents(FlashMessage::DEFAULT_CHANNEL)
+        ))
+    }
+
+    #[actix_rt::test]
+    async fn test_messages_are_retrieved_per_channel() {
+        let cookie_store = CookieMessageStore::builder(Key::generate()).build();
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(FlashMessagesFramework::builder(cookie_store).build())
+                .service(resource("/set").route(web::get().to(set_channels)))
+                .service(resource("/show").route(web::get().to(show_channels))),
+        )
+        .await;
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get().uri("/set").to_request(),
+        )
+        .await;
+        let flash_cookie = resp
+            .response()
+            .cookies()
+            .find(|c| c.name() == "_flash")
+            .unwrap()
+            .into_owned();
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/show")
+                .cookie(flash_cookie)
+                .to_request(),
+        )
+        .await;
+        let body_bytes = actix_web::test::read_body(resp).await;
+        assert_eq!(
+            std::str::from_utf8(&body_bytes).unwrap(),
+            "Welcome back!|Email is required|No channel"
+        );
+    }
+
+    #[test]
+    fn test_messages_without_a_channel_can_be_deserialized() {
+        let message: FlashMessage =
+            serde_json::from_str(r#"{"content": "Hey there!", "level": "Info"}"#).unwrap();
+        assert_eq!(message.channel(), None);
+    }
+}
+
+#[cfg(feature = "cookies")]
+mod key_from_app_data {
+    use super::*;
+    use actix_web_flash_messages::storage::{CookieMessageStore, FlashMessageStore};
+
+    #[actix_rt::test]
+    async fn test_signing_key_is_resolved_from_app_data() {
+        let key = Key::generate();
+        let app = actix_web::test::init_service(
+            App::new()
+                .app_data(web::Data::new(key.clone()))
+                .wrap(
+                    FlashMessagesFramework::builder(
+                        CookieMessageStore::builder_from_app_data().build(),
+                    )
+                    .build(),
+                )
+                .service(resource("/set").route(web::get().to(set)))
+                .service(resource("/show").route(web::get().to(show))),
+        )
+        .await;
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get().uri("/set").to_request(),
+        )
+        .await;
+        let flash_cookie = resp
+            .response()
+            .cookies()
+            .find(|c| c.name() == "_flash")
+            .unwrap()
+            .into_owned();
+
+        // The cookie is signed with the key stored in the application data.
+        let verifying_app = actix_web::test::init_service(
+            App::new()
+                .wrap(
+                    FlashMessagesFramework::builder(CookieMessageStore::builder(key).build())
+                        .build(),
+                )
+                .service(resource("/show").route(web::get().to(show))),
+        )
+        .await;
+        let resp = actix_web::test::call_service(
+            &verifying_app,
+            actix_web::test::TestRequest::get()
+                .uri("/show")
+                .cookie(flash_cookie.clone())
+                .to_request(),
+        )
+        .await;
+        let body_bytes = actix_web::test::read_body(resp).await;
+        assert_eq!(
+            std::str::from_utf8(&body_bytes).unwrap(),
+            "Hey there! - info\n"
+        );
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/show")
+                .cookie(flash_cookie)
+                .to_request(),
+        )
+        .await;
+        let body_bytes = actix_web::test::read_body(resp).await;
+        assert_eq!(
+            std::str::from_utf8(&body_bytes).unwrap(),
+            "Hey there! - info\n"
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_loading_fails_if_the_signing_key_is_not_registered() {
+        let cookie_store = CookieMessageStore::builder(Key::generate()).build();
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(FlashMessagesFramework::builder(cookie_store).build())
+                .service(resource("/set").route(web::get().to(set))),
+        )
+        .await;
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get().uri("/set").to_request(),
+        )
+        .await;
+        let flash_cookie = resp
+            .response()
+            .cookies()
+            .find(|c| c.name() == "_flash")
+            .unwrap()
+            .into_owned();
+
+        let store = CookieMessageStore::builder_from_app_data().build();
+        let request = actix_web::test::TestRequest::get()
+            .cookie(flash_cookie)
+            .to_http_request();
+        let error = store.load(&request).err().unwrap();
+        assert!(error.is_generic());
+    }
+}
+
+#[cfg(feature = "cookies")]
+mod skip_empty {
+    use super::*;
+    use actix_web_flash_messages::storage::CookieMessageStore;
+    use actix_web_flash_messages::FlashScope;
+
+    async fn set_blank(mut flash: FlashScope) -> impl Responder {
+        FlashMessage::error("").send();
+        FlashMessage::error("  \n\t").send();
+        FlashMessage::info("Hey there!").send();
+        flash.warning(" ");
+        HttpResponse::Ok()
+    }
+
+    async fn body_after_set_blank(skip_empty: bool) -> String {
+        let cookie_store = CookieMessageStore::builder(Key::generate()).build();
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(
+                    FlashMessagesFramework::builder(cookie_store)
+                        .skip_empty(skip_empty)
+                        .build(),
+                )
+                .service(resource("/set").route(web::get().to(set_blank)))
+                .service(resource("/show").route(web::get().to(show))),
+        )
+        .await;
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get().uri("/set").to_request(),
+        )
+        .await;
+        let flash_cookie = resp
+            .response()
+            .cookies()
+            .find(|c| c.name() == "_flash")
+            .unwrap()
+            .into_owned();
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/show")
+                .cookie(flash_cookie)
+                .to_request(),
+        )
+        .await;
+        let body_bytes = actix_web::test::read_body(resp).await;
+        std::str::from_utf8(&body_bytes).unwrap().to_owned()
+    }
+
+    #[actix_rt::test]
+    async fn test_whitespace_only_messages_are_skipped_when_enabled() {
+        assert_eq!(body_after_set_blank(true).await, "Hey there! - info\n");
+    }
+
+    #[actix_rt::test]
+    async fn test_empty_messages_are_sent_by_default() {
+        assert_eq!(
+            body_after_set_blank(false).await,
+            " - error\n  \n\t - error\nHey there! - info\n  - warning\n"
+        );
+    }
+}
+
+#[cfg(feature = "cookies")]
+mod cookie_name_validation {
+    use actix_web::cookie::Key;
+    use actix_web_flash_messages::storage::CookieMessageStore;
+
+    #[test]
+    #[should_panic(expected = "Invalid cookie name, `my flash;`: it contains ' '")]
+    fn test_cookie_names_with_illegal_characters_are_rejected() {
+        let _ = CookieMessageStore::builder(Key::generate()).cookie_name("my flash;".into());
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid cookie name: it must not be empty")]
+    fn test_empty_cookie_names_are_rejected() {
+        let _ = CookieMessageStore::builder(Key::generate()).cookie_name(String::new());
+    }
+
+    #[test]
+    fn test_valid_cookie_names_are_accepted() {
+        CookieMessageStore::builder(Key::generate())
+            .cookie_name("__Host-flash_messages.v2".into())
+            .build();
+    }
+}
+
+#[cfg(feature = "cookies")]
+mod streaming {
+    use super::*;
+    use actix_web::body::{BodySize, MessageBody};
+    use actix_web::web::Bytes;
+    use actix_web_flash_messages::storage::CookieMessageStore;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    /// A single-chunk body that tries to send a flash message while it is being streamed.
+    struct LateBody {
+        done: bool,
+    }
+
+    impl MessageBody for LateBody {
+        type Error = std::convert::Infallible;
+
+        fn size(&self) -> BodySize {
+            BodySize::Stream
+        }
+
+        fn poll_next(
+            mut self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+        ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+            if self.done {
+                return Poll::Ready(None);
+            }
+            self.done = true;
+            let sent = std::panic::catch_unwind(|| FlashMessage::info("Too late!").send()).is_ok();
+            Poll::Ready(Some(Ok(Bytes::from(format!("sent: {}", sent)))))
+        }
+    }
+
+    async fn stream() -> HttpResponse {
+        FlashMessage::info("Export started").send();
+        HttpResponse::Ok().body(LateBody { done: false })
+    }
+
+    #[actix_rt::test]
+    async fn test_messages_cannot_be_sent_while_the_body_is_streamed() {
+        let cookie_store = CookieMessageStore::builder(Key::generate()).build();
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(FlashMessagesFramework::builder(cookie_store).build())
+                .service(resource("/stream").route(web::get().to(stream)))
+                .service(resource("/show").route(web::get().to(show))),
+        )
+        .await;
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/stream")
+                .to_request(),
+        )
+        .await;
+        // Messages sent by the handler are flushed before the body is streamed.
+        let flash_cookie = resp
+            .response()
+            .cookies()
+            .find(|c| c.name() == "_flash")
+            .unwrap()
+            .into_owned();
+        let body_bytes = actix_web::test::read_body(resp).await;
+        assert_eq!(std::str::from_utf8(&body_bytes).unwrap(), "sent: false");
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/show")
+                .cookie(flash_cookie)
+                .to_request(),
+        )
+        .await;
+        let body_bytes = actix_web::test::read_body(resp).await;
+        assert_eq!(
+            std::str::from_utf8(&body_bytes).unwrap(),
+            "Export started - info\n"
+        );
+    }
+}
+
+#[cfg(feature = "tracing")]
+mod logging {
+    use actix_web::dev::ResponseHead;
+    use actix_web::http::StatusCode;
+    use actix_web_flash_messages::storage::{FlashMessageStore, HeaderMessageStore, LoggingStore};
+    use actix_web_flash_messages::FlashMessage;
+    use std::sync::{Arc, Mutex};
+
+    /// Collect log lines in memory.
+    #[derive(Clone, Default)]
+    struct Logs(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for Logs {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn logs_while(store: LoggingStore<HeaderMessageStore>) -> String {
+        let logs = Logs::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_ansi(false)
+            .with_writer(move || writer.clone())
+            .finish();
+        tracing::subscriber::with_default(subscriber, || {
+            let messages = vec![
+                FlashMessage::info("Sent to jane@example.com"),
+                FlashMessage::error("Payment declined"),
+            ];
+            let request = actix_web::test::TestRequest::default().to_http_request();
+            let mut response = ResponseHead::new(StatusCode::OK);
+            store.store(&messages, request, &mut response).unwrap();
+
+            let mut incoming = actix_web::test::TestRequest::default();
+            for value in response.headers().get_all("x-flash") {
+                incoming = incoming.append_header(("x-flash", value.clone()));
+            }
+            let loaded = store.load(&incoming.to_http_request()).unwrap();
+            assert_eq!(loaded.len(), 2);
+        });
+        let logs = logs.0.lock().unwrap();
+        String::from_utf8(logs.clone()).unwrap()
+    }
+
+    #[test]
+    fn test_loads_and_stores_are_logged_without_content_by_default() {
+        let logs = logs_while(LoggingStore::new(
+            HeaderMessageStore::default().trust_incoming(true),
+        ));
+        let lines = logs.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].contains("Stored outgoing flash messages"));
+        assert!(lines[1].contains("Loaded incoming flash messages"));
+        for line in lines {
+            assert!(line.contains("count=2"));
+            assert!(line.contains("levels=[info, error]"));
+            assert!(!line.contains("jane@example.com"));
+        }
+    }
+
+    #[test]
+    fn test_content_is_logged_when_opted_in() {
+        let logs = logs_while(
+            LoggingStore::new(HeaderMessageStore::default().trust_incoming(true)).log_content(true),
+        );
+        assert_eq!(logs.matches("jane@example.com").count(), 2);
+    }
+}
+
+#[cfg(feature = "cookies")]
+mod with_store {
+    use super::*;
+    use actix_web_flash_messages::storage::{CookieMessageStore, HeaderMessageStore};
+    use actix_web_flash_messages::Level;
+
+    fn build_framework() -> FlashMessagesFramework {
+        FlashMessagesFramework::builder(CookieMessageStore::builder(Key::generate()).build())
+            .minimum_level(Level::Debug)
+            .build()
+    }
+
+    #[actix_rt::test]
+    async fn test_the_store_can_be_swapped_retaining_the_configuration() {
+        let framework = build_framework().with_store(HeaderMessageStore::default());
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(framework)
+                .service(resource("/set").route(web::get().to(set))),
+        )
+        .await;
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get().uri("/set").to_request(),
+        )
+        .await;
+        assert_eq!(resp.response().cookies().count(), 0);
+        let headers = resp
+            .headers()
+            .get_all("X-Flash")
+            .map(|h| h.to_str().unwrap().to_owned())
+            .collect::<Vec<_>>();
+        // The debug-level message is retained: the minimum level was carried over.
+        assert_eq!(
+            headers,
+            vec!["info;Hey%20there%21", "debug;How%20is%20it%20going%3F"]
+        );
+    }
+}
+
+#[cfg(feature = "cookies")]
+mod builder_arc {
+    use super::*;
+    use actix_web_flash_messages::storage::{CookieMessageStore, FlashMessageStore};
+    use std::sync::Arc;
+
+    #[actix_rt::test]
+    async fn test_a_store_can_be_shared_across_frameworks() {
+        let store: Arc<dyn FlashMessageStore> =
+            Arc::new(CookieMessageStore::builder(Key::generate()).build());
+        let setter = actix_web::test::init_service(
+            App::new()
+                .wrap(FlashMessagesFramework::builder_arc(store.clone()).build())
+                .service(resource("/set").route(web::get().to(set))),
+        )
+        .await;
+        let shower = actix_web::test::init_service(
+            App::new()
+                .wrap(FlashMessagesFramework::builder_arc(store).build())
+                .service(resource("/show").route(web::get().to(show))),
+        )
+        .await;
+
+        let resp = actix_web::test::call_service(
+            &setter,
+            actix_web::test::TestRequest::get().uri("/set").to_request(),
+        )
+        .await;
+        let cookie = resp.response().cookies().next().unwrap().into_owned();
+        let resp = actix_web::test::call_service(
+            &shower,
+            actix_web::test::TestRequest::get()
+                .uri("/show")
+                .cookie(cookie)
+                .to_request(),
+        )
+        .await;
+        let body_bytes = actix_web::test::read_body(resp).await;
+        assert_eq!(
+            std::str::from_utf8(&body_bytes).unwrap(),
+            "Hey there! - info\n"
+        );
+    }
+}
+
+#[cfg(feature = "cookies")]
+mod dynamic_minimum_level {
+    use super::*;
+    use actix_web_flash_messages::storage::CookieMessageStore;
+    use actix_web_flash_messages::Level;
+
+    #[actix_rt::test]
+    async fn test_minimum_level_is_determined_per_request() {
+        let cookie_store = CookieMessageStore::builder(Key::generate()).build();
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(
+                    FlashMessagesFramework::builder(cookie_store)
+                        .minimum_level(Level::Error)
+                        .dynamic_minimum_level(|request| {
+                            if request.query_string() == "debug=1" {
+                                Level::Debug
+                            } else {
+                                Level::Info
+                            }
+                        })
+                        .build(),
+                )
+                .service(resource("/set").route(web::get().to(set)))
+                .service(resource("/show").route(web::get().to(show))),
+        )
+        .await;
+
+        for (uri, expected) in [
+            ("/set", "Hey there! - info\n"),
+            (
+                "/set?debug=1",
+                "Hey there! - info\nHow is it going? - debug\n",
+            ),
+        ] {
+            let resp = actix_web::test::call_service(
+                &app,
+                actix_web::test::TestRequest::get().uri(uri).to_request(),
+            )
+            .await;
+            let flash_cookie = resp
+                .response()
+                .cookies()
+                .find(|c| c.name() == "_flash")
+                .unwrap()
+                .into_owned();
+            let resp = actix_web::test::call_service(
+                &app,
+                actix_web::test::TestRequest::get()
+                    .uri("/show")
+                    .cookie(flash_cookie)
+                    .to_request(),
+            )
+            .await;
+            let body_bytes = actix_web::test::read_body(resp).await;
+            assert_eq!(std::str::from_utf8(&body_bytes).unwrap(), expected);
+        }
+    }
+}
+
+#[cfg(feature = "cookies")]
+mod conditional_send {
+    use super::*;
+    use actix_web_flash_messages::storage::CookieMessageStore;
+
+    async fn set_conditionally() -> impl Responder {
+        FlashMessage::info("Sent").send_if(true);
+        FlashMessage::info("Not sent").send_if(false);
+        FlashMessage::send_if_some(Some(3), |n| FlashMessage::info(format!("{} days", n)));
+        FlashMessage::send_if_some(None::<u32>, |n| FlashMessage::info(format!("{} days", n)));
+        let ok: Result<(), String> =
+            FlashMessage::send_if_err(Ok(()), |e| FlashMessage::error(e.to_owned()));
+        assert!(ok.is_ok());
+        let err: Result<(), String> =
+            FlashMessage::send_if_err(Err("Failed".into()), |e| FlashMessage::error(e.to_owned()));
+        assert!(err.is_err());
+        HttpResponse::Ok()
+    }
+
+    #[actix_rt::test]
+    async fn test_messages_are_only_sent_when_the_condition_holds() {
+        let cookie_store = CookieMessageStore::builder(Key::generate()).build();
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(FlashMessagesFramework::builder(cookie_store).build())
+                .service(resource("/set").route(web::get().to(set_conditionally)))
+                .service(resource("/show").route(web::get().to(show))),
+        )
+        .await;
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get().uri("/set").to_request(),
+        )
+        .await;
+        let flash_cookie = resp
+            .response()
+            .cookies()
+            .find(|c| c.name() == "_flash")
+            .unwrap()
+            .into_owned();
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/show")
+                .cookie(flash_cookie)
+                .to_request(),
+        )
+        .await;
+        let body_bytes = actix_web::test::read_body(resp).await;
+        assert_eq!(
+            std::str::from_utf8(&body_bytes).unwrap(),
+            "Sent - info\n3 days - info\nFailed - error\n"
+        );
+    }
+}
+
+#[cfg(all(feature = "cookies", feature = "sessions"))]
+mod redirect_aware {
+    use super::*;
+    use actix_session::{storage::CookieSessionStore, SessionMiddleware};
+    use actix_web_flash_messages::storage::{
+        CookieMessageStore, RedirectAwareStore, SessionMessageStore,
+    };
+
+    async fn set_without_redirect() -> impl Responder {
+        FlashMessage::info("Rendered later").send();
+        HttpResponse::Ok()
+    }
+
+    #[actix_rt::test]
+    async fn test_messages_are_stored_based_on_the_response_status() {
+        let message_store = RedirectAwareStore::new(
+            CookieMessageStore::builder(Key::generate()).build(),
+            SessionMessageStore::default(),
+        );
+        let session_middleware =
+            SessionMiddleware::builder(CookieSessionStore::default(), Key::generate())
+                .cookie_name("_session".to_string())
+                .build();
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(FlashMessagesFramework::builder(message_store).build())
+                .wrap(session_middleware)
+                .service(resource("/set").route(web::get().to(set)))
+                .service(resource("/set_ok").route(web::get().to(set_without_redirect)))
+                .service(resource("/show").route(web::get().to(show))),
+        )
+        .await;
+        let find_cookie = |resp: &actix_web::dev::ServiceResponse, name: &str| {
+            resp.response()
+                .cookies()
+                .find(|c| c.name() == name)
+                .map(|c| c.into_owned())
+        };
+
+        // A redirect: the message goes in the flash cookie, no session is created.
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get().uri("/set").to_request(),
+        )
+        .await;
+        assert!(find_cookie(&resp, "_session").is_none());
+        let flash_cookie = find_cookie(&resp, "_flash").unwrap();
+        assert!(!flash_cookie.value().is_empty());
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/show")
+                .cookie(flash_cookie)
+                .to_request(),
+        )
+        .await;
+        let body_bytes = actix_web::test::read_body(resp).await;
+        assert_eq!(
+            std::str::from_utf8(&body_bytes).unwrap(),
+            "Hey there! - info\n"
+        );
+
+        // Not a redirect: the message goes in the session.
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/set_ok")
+                .to_request(),
+        )
+        .await;
+        assert!(find_cookie(&resp, "_flash").is_none());
+        let session_cookie = find_cookie(&resp, "_session").unwrap();
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/show")
+                .cookie(session_cookie)
+                .to_request(),
+        )
+        .await;
+        let body_bytes = actix_web::test::read_body(resp).await;
+        assert_eq!(
+            std::str::from_utf8(&body_bytes).unwrap(),
+            "Rendered later - info\n"
+        );
+    }
+}
+
+#[cfg(feature = "cookies")]
+mod expires {
+    use super::*;
+    use actix_web_flash_messages::storage::CookieMessageStore;
+    use time::OffsetDateTime;
+
+    #[actix_rt::test]
+    async fn test_flash_and_removal_cookies_carry_an_expiry_date() {
+        let expires = OffsetDateTime::from_unix_timestamp(4_102_444_800).unwrap();
+        let cookie_store = CookieMessageStore::builder(Key::generate())
+            .expires(expires)
+            .build();
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(FlashMessagesFramework::builder(cookie_store).build())
+                .service(resource("/set").route(web::get().to(set)))
+                .service(resource("/show").route(web::get().to(show))),
+        )
+        .await;
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get().uri("/set").to_request(),
+        )
+        .await;
+        let flash_cookie = resp
+            .response()
+            .cookies()
+            .find(|c| c.name() == "_flash")
+            .unwrap()
+            .into_owned();
+        assert_eq!(flash_cookie.expires_datetime(), Some(expires));
+        assert_eq!(flash_cookie.max_age(), None);
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/show")
+                .cookie(flash_cookie)
+                .to_request(),
+        )
+        .await;
+        let removal_cookie = resp
+            .response()
+            .cookies()
+            .find(|c| c.name() == "_flash")
+            .unwrap();
+        assert_eq!(removal_cookie.max_age(), Some(time::Duration::seconds(0)));
+        assert_eq!(
+            removal_cookie.expires_datetime(),
+            Some(OffsetDateTime::UNIX_EPOCH)
+        );
+    }
+}
+
+#[cfg(feature = "cookies")]
+mod configure_cookie {
+    use super::*;
+    use actix_web_flash_messages::storage::CookieMessageStore;
+
+    #[actix_rt::test]
+    async fn test_the_hook_customises_flash_and_removal_cookies() {
+        let cookie_store = CookieMessageStore::builder(Key::generate())
+            .same_site(SameSite::Strict)
+            .configure_cookie(|cookie| cookie.secure(false).max_age(time::Duration::minutes(5)))
+            .build();
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(FlashMessagesFramework::builder(cookie_store).build())
+                .service(resource("/set").route(web::get().to(set)))
+                .service(resource("/show").route(web::get().to(show))),
+        )
+        .await;
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get().uri("/set").to_request(),
+        )
+        .await;
+        let flash_cookie = resp
+            .response()
+            .cookies()
+            .find(|c| c.name() == "_flash")
+            .unwrap()
+            .into_owned();
+        assert!(!flash_cookie.secure().unwrap_or_default());
+        assert_eq!(flash_cookie.max_age(), Some(time::Duration::minutes(5)));
+        // Attributes set via the dedicated builder methods are retained.
+        assert_eq!(flash_cookie.same_site(), Some(SameSite::Strict));
+        assert_eq!(flash_cookie.http_only(), Some(true));
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/show")
+                .cookie(flash_cookie)
+                .to_request(),
+        )
+        .await;
+        let removal_cookie = resp
+            .response()
+            .cookies()
+            .find(|c| c.name() == "_flash")
+            .unwrap();
+        assert!(!removal_cookie.secure().unwrap_or_default());
+        // The hook cannot turn the removal cookie into a persistent one.
+        assert_eq!(removal_cookie.max_age(), Some(time::Duration::seconds(0)));
+    }
+}
+
+#[cfg(feature = "cookies")]
+mod payload_versioning {
+    use super::*;
+    use actix_web::cookie::{Cookie, CookieJar};
+    use actix_web_flash_messages::storage::CookieMessageStore;
+
+    /// Sign `payload` the way `CookieMessageStore` does.
+    fn signed_cookie(key: &Key, payload: &str) -> Cookie<'static> {
+        let mut jar = CookieJar::new();
+        jar.signed_mut(key)
+            .add(Cookie::new("_flash", payload.to_owned()));
+        jar.get("_flash").unwrap().clone()
+    }
+
+    async fn show_with(key: &Key, flash_cookie: Cookie<'static>) -> (u16, String) {
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(
+                    FlashMessagesFramework::builder(
+                        CookieMessageStore::builder(key.clone()).build(),
+                    )
+                    .build(),
+                )
+                .service(resource("/show").route(web::get().to(show))),
+        )
+        .await;
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/show")
+                .cookie(flash_cookie)
+                .to_request(),
+        )
+        .await;
+        let status = resp.status().as_u16();
+        let body_bytes = actix_web::test::read_body(resp).await;
+        (status, std::str::from_utf8(&body_bytes).unwrap().to_owned())
+    }
+
+    #[actix_rt::test]
+    async fn test_payload_is_wrapped_in_a_versioned_envelope() {
+        let key = Key::generate();
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(
+                    FlashMessagesFramework::builder(
+                        CookieMessageStore::builder(key.clone()).build(),
+                    )
+                    .build(),
+                )
+                .service(resource("/set").route(web::get().to(set))),
+        )
+        .await;
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get().uri("/set").to_request(),
+        )
+        .await;
+        let flash_cookie = resp
+            .response()
+            .cookies()
+            .find(|c| c.name() == "_flash")
+            .unwrap()
+            .into_owned();
+        let value = percent_encoding::percent_decode_str(flash_cookie.value())
+            .decode_utf8()
+            .unwrap()
+            .into_owned();
+        assert!(value.contains(r#"{"v":1,"messages":[{"content":"Hey there!""#));
+
+        let (_, body) = show_with(&key, flash_cookie).await;
+        assert_eq!(body, "Hey there! - info\n");
+    }
+
+    #[actix_rt::test]
+    async fn test_legacy_unversioned_payloads_can_be_read() {
+        let key = Key::generate();
+        let legacy = signed_cookie(&key, r#"[{"content":"Hey there!","level":"Info"}]"#);
+        let (status, body) = show_with(&key, legacy).await;
+        assert_eq!(status, 200);
+        assert_eq!(body, "Hey there! - info\n");
+    }
+
+    #[actix_rt::test]
+    async fn test_unknown_versions_are_rejected() {
+        let key = Key::generate();
+        let future = signed_cookie(&key, r#"{"v":2,"messages":[]}"#);
+        let (status, _) = show_with(&key, future).await;
+        assert_eq!(status, 400);
+    }
+
+    #[actix_rt::test]
+    async fn test_unknown_message_fields_are_ignored() {
+        let key = Key::generate();
+        let newer = signed_cookie(
+            &key,
+            r#"{"v":1,"messages":[{"content":"Hey there!","level":"info","dismissible":true,"icon":{"name":"wave"}}]}"#,
+        );
+        let (status, body) = show_with(&key, newer).await;
+        assert_eq!(status, 200);
+        assert_eq!(body, "Hey there! - info\n");
+    }
+
+    #[actix_rt::test]
+    async fn test_unknown_envelope_fields_are_ignored() {
+        let key = Key::generate();
+        let newer = signed_cookie(
+            &key,
+            r#"{"v":1,"compression":null,"messages":[{"content":"Hey there!","level":"info"}]}"#,
+        );
+        let (status, body) = show_with(&key, newer).await;
+        assert_eq!(status, 200);
+        assert_eq!(body, "Hey there! - info\n");
+    }
+
+    #[actix_rt::test]
+    async fn test_session_only_legacy_formats_are_rejected() {
+        let key = Key::generate();
+        for payload in [
+            r#""Hey there!""#,
+            r#"{"content":"Hey there!","level":"info"}"#,
+        ] {
+            let (status, _) = show_with(&key, signed_cookie(&key, payload)).await;
+            assert_eq!(status, 400);
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_unknown_fields_are_ignored_in_legacy_payloads() {
+        let key = Key::generate();
+        let legacy = signed_cookie(
+            &key,
+            r#"[{"content":"Hey there!","level":"Info","dismissible":true}]"#,
+        );
+        let (status, body) = show_with(&key, legacy).await;
+        assert_eq!(status, 200);
+        assert_eq!(body, "Hey there! - info\n");
+    }
+}
+
+#[cfg(feature = "cookies")]
+mod take {
+    use super::*;
+    use actix_web_flash_messages::storage::CookieMessageStore;
+
+    async fn take_messages(messages: IncomingFlashMessages) -> impl Responder {
+        let messages = messages.take();
+        HttpResponse::Ok().json(messages.iter().map(|m| m.content()).collect::<Vec<_>>())
+    }
+
+    #[actix_rt::test]
+    async fn test_taken_messages_are_cleared_exactly_once() {
+        for clear_on_read in [false, true] {
+            let cookie_store = CookieMessageStore::builder(Key::generate()).build();
+            let app = actix_web::test::init_service(
+                App::new()
+                    .wrap(
+                        FlashMessagesFramework::builder(cookie_store)
+                            .clear_on_read(clear_on_read)
+                            .build(),
+                    )
+                    .service(resource("/set").route(web::get().to(set)))
+                    .service(resource("/take").route(web::get().to(take_messages))),
+            )
+            .await;
+
+            let resp = actix_web::test::call_service(
+                &app,
+                actix_web::test::TestRequest::get().uri("/set").to_request(),
+            )
+            .await;
+            let flash_cookie = resp
+                .response()
+                .cookies()
+                .find(|c| c.name() == "_flash")
+                .unwrap()
+                .into_owned();
+            let resp = actix_web::test::call_service(
+                &app,
+                actix_web::test::TestRequest::get()
+                    .uri("/take")
+                    .cookie(flash_cookie)
+                    .to_request(),
+            )
+            .await;
+            let flash_cookies = resp
+                .response()
+                .cookies()
+                .filter(|c| c.name() == "_flash")
+                .map(|c| c.into_owned())
+                .collect::<Vec<_>>();
+            assert_eq!(flash_cookies.len(), 1);
+            assert_eq!(flash_cookies[0].value(), "");
+            let body_bytes = actix_web::test::read_body(resp).await;
+            assert_eq!(
+                std::str::from_utf8(&body_bytes).unwrap(),
+                r#"["Hey there!"]"#
+            );
+        }
+    }
+}
+
+mod truncation {
+    use actix_web_flash_messages::FlashMessage;
+
+    #[test]
+    fn test_short_content_is_borrowed_as_is() {
+        let message = FlashMessage::info("Hey there!");
+        assert!(matches!(
+            message.content_truncated(10),
+            std::borrow::Cow::Borrowed("Hey there!")
+        ));
+    }
+
+    #[test]
+    fn test_content_is_truncated_on_a_char_boundary() {
+        // 'é' is 2 bytes long, '🦀' is 4 bytes long.
+        let message = FlashMessage::info("café🦀crab");
+        // Truncating at 7 bytes leaves room for 4 bytes of content: "caf" + half of 'é'.
+        assert_eq!(message.content_truncated(7), "caf…");
+        assert_eq!(message.content_truncated(8), "café…");
+        // The crab is 4 bytes long: it doesn't fit in 11 - 3 = 8 bytes.
+        assert_eq!(message.content_truncated(11), "café…");
+        assert_eq!(message.content_truncated(12), "café🦀…");
+        for max in 0..20 {
+            let truncated = message.content_truncated(max);
+            assert!(truncated.len() <= max);
+        }
+    }
+
+    #[test]
+    fn test_the_ellipsis_is_omitted_if_it_does_not_fit() {
+        let message = FlashMessage::info("café🦀crab");
+        assert_eq!(message.content_truncated(0), "");
+        assert_eq!(message.content_truncated(2), "ca");
+        // 'é' is 2 bytes long: it doesn't fit in the last byte.
+        let message = FlashMessage::info("éa");
+        assert_eq!(message.content_truncated(1), "");
+    }
+}
+
+#[cfg(feature = "cookies")]
+mod truncate_content {
+    use super::*;
+    use actix_web_flash_messages::storage::{CookieMessageStore, HeaderMessageStore};
+
+    async fn set_long() -> impl Responder {
+        FlashMessage::error(format!("Stack trace: {}", "é".repeat(100))).send();
+        HttpResponse::Ok()
+    }
+
+    #[actix_rt::test]
+    async fn test_content_is_truncated_when_sent() {
+        let cookie_store = CookieMessageStore::builder(Key::generate()).build();
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(
+                    FlashMessagesFramework::builder(cookie_store)
+                        .truncate_content(20)
+                        .build(),
+                )
+                .service(resource("/set").route(web::get().to(set_long)))
+                .service(resource("/show").route(web::get().to(show))),
+        )
+        .await;
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get().uri("/set").to_request(),
+        )
+        .await;
+        let flash_cookie = resp
+            .response()
+            .cookies()
+            .find(|c| c.name() == "_flash")
+            .unwrap()
+            .into_owned();
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/show")
+                .cookie(flash_cookie)
+                .to_request(),
+        )
+        .await;
+        let body_bytes = actix_web::test::read_body(resp).await;
+        assert_eq!(
+            std::str::from_utf8(&body_bytes).unwrap(),
+            "Stack trace: éé… - error\n"
+        );
+    }
+    #[actix_rt::test]
+    async fn test_content_never_exceeds_a_limit_shorter_than_the_ellipsis() {
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(
+                    FlashMessagesFramework::builder(HeaderMessageStore::default())
+                        .truncate_content(2)
+                        .build(),
+                )
+                .service(resource("/set").route(web::get().to(set_long))),
+        )
+        .await;
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get().uri("/set").to_request(),
+        )
+        .await;
+        let header = resp.headers().get("x-flash").unwrap();
+        assert_eq!(header, "error;St");
+    }
+}
+
+#[cfg(feature = "cookies")]
+mod load_outside_handlers {
+    use super::*;
+    use actix_web::dev::Service;
+    use actix_web::HttpMessage;
+    use actix_web_flash_messages::load_flash_messages;
+    use actix_web_flash_messages::storage::CookieMessageStore;
+
+    /// The number of incoming messages, as seen by the middleware.
+    #[derive(Clone)]
+    struct SeenByMiddleware(usize);
+
+    async fn show_seen(
+        seen: web::ReqData<SeenByMiddleware>,
+        messages: IncomingFlashMessages,
+    ) -> impl Responder {
+        HttpResponse::Ok().body(format!("{}|{}", seen.0, messages.iter().count()))
+    }
+
+    async fn ignore() -> impl Responder {
+        HttpResponse::Ok()
+    }
+
+    #[actix_rt::test]
+    async fn test_messages_can_be_loaded_by_middleware_without_consuming_them() {
+        let cookie_store = CookieMessageStore::builder(Key::generate()).build();
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap_fn(|req, srv| {
+                    let n_messages = load_flash_messages(req.request()).unwrap().len();
+                    req.extensions_mut().insert(SeenByMiddleware(n_messages));
+                    srv.call(req)
+                })
+                .wrap(FlashMessagesFramework::builder(cookie_store).build())
+                .service(resource("/set").route(web::get().to(set)))
+                .service(resource("/ignore").route(web::get().to(ignore)))
+                .service(resource("/show").route(web::get().to(show_seen))),
+        )
+        .await;
+        let flash_cookie = |resp: &actix_web::dev::ServiceResponse| {
+            resp.response()
+                .cookies()
+                .find(|c| c.name() == "_flash")
+                .unwrap()
+                .into_owned()
+        };
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get().uri("/set").to_request(),
+        )
+        .await;
+        let cookie = flash_cookie(&resp);
+
+        // The middleware loaded the messages, but the handler didn't read them: they are carried over.
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/ignore")
+                .cookie(cookie)
+                .to_request(),
+        )
+        .await;
+        let cookie = flash_cookie(&resp);
+        assert!(!cookie.value().is_empty());
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/show")
+                .cookie(cookie)
+                .to_request(),
+        )
+        .await;
+        assert_eq!(flash_cookie(&resp).value(), "");
+        let body_bytes = actix_web::test::read_body(resp).await;
+        assert_eq!(std::str::from_utf8(&body_bytes).unwrap(), "1|1");
+    }
+}
+
+#[cfg(feature = "cookies")]
+mod replay_guard {
+    use super::*;
+    use actix_web_flash_messages::storage::{CookieMessageStore, InMemoryReplayGuard, ReplayGuard};
+
+    #[actix_rt::test]
+    async fn test_replayed_flash_cookies_are_rejected() {
+        let key = Key::generate();
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(
+                    FlashMessagesFramework::builder(
+                        CookieMessageStore::builder(key.clone())
+                            .replay_guard(InMemoryReplayGuard::default())
+                            .build(),
+                    )
+                    .build(),
+                )
+                .service(resource("/set").route(web::get().to(set)))
+                .service(resource("/show").route(web::get().to(show))),
+        )
+        .await;
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get().uri("/set").to_request(),
+        )
+        .await;
+        let flash_cookie = resp
+            .response()
+            .cookies()
+            .find(|c| c.name() == "_flash")
+            .unwrap()
+            .into_owned();
+
+        let show = |cookie| {
+            actix_web::test::TestRequest::get()
+                .uri("/show")
+                .cookie(cookie)
+                .to_request()
+        };
+        let resp = actix_web::test::call_service(&app, show(flash_cookie.clone())).await;
+        assert_eq!(resp.status(), 200);
+        let body_bytes = actix_web::test::read_body(resp).await;
+        assert_eq!(
+            std::str::from_utf8(&body_bytes).unwrap(),
+            "Hey there! - info\n"
+        );
+
+        // Replaying the same cookie fails.
+        let resp = actix_web::test::call_service(&app, show(flash_cookie)).await;
+        assert_eq!(resp.status(), 400);
+    }
+
+    #[test]
+    fn test_in_memory_guard_forgets_the_oldest_nonces_when_full() {
+        let guard = InMemoryReplayGuard::new(2);
+        assert!(guard.first_use("a"));
+        assert!(!guard.first_use("a"));
+        assert!(guard.first_use("b"));
+        assert!(guard.first_use("c"));
+        // "a" has been evicted to make room for "c".
+        assert!(guard.first_use("a"));
+        assert!(!guard.first_use("c"));
+    }
+}
+
+#[cfg(all(feature = "test-util", feature = "cookies"))]
+mod roundtrip {
+    use super::*;
+    use actix_web_flash_messages::storage::CookieMessageStore;
+    use actix_web_flash_messages::test_util::flash_roundtrip;
+
+    #[actix_rt::test]
+    async fn test_flash_roundtrip_returns_messages_read_by_the_show_handler() {
+        let store = CookieMessageStore::builder(Key::generate()).build();
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(FlashMessagesFramework::builder(store).build())
+                .service(resource("/set").route(web::get().to(set)))
+                .service(resource("/show").route(web::get().to(show))),
+        )
+        .await;
+
+        let messages = flash_roundtrip(&app, "/set", "/show").await;
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].content(), "Hey there!");
+
+        // Nothing has been flashed by `/show`.
+        let messages = flash_roundtrip(&app, "/show", "/show").await;
+        assert!(messages.is_empty());
+    }
+}
+
+#[cfg(all(feature = "cookies", feature = "sessions"))]
+mod tee {
+    use super::*;
+    use actix_session::{storage::CookieSessionStore, SessionMiddleware};
+    use actix_web::dev::ResponseHead;
+    use actix_web::HttpRequest;
+    use actix_web_flash_messages::storage::{
+        CookieMessageStore, FlashMessageStore, LoadError, SessionMessageStore, StoreError, TeeStore,
+    };
+    use std::sync::Arc;
+
+    struct FailingStore;
+
+    impl FlashMessageStore for FailingStore {
+        fn load(&self, _request: &HttpRequest) -> Result<Vec<FlashMessage>, LoadError> {
+            Err(LoadError::GenericError(anyhow::anyhow!("Unavailable")))
+        }
+
+        fn store(
+            &self,
+            _messages: &[FlashMessage],
+            _request: HttpRequest,
+            _response: &mut ResponseHead,
+        ) -> Result<(), StoreError> {
+            Err(StoreError::GenericError(anyhow::anyhow!("Unavailable")))
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_messages_can_be_read_from_any_backend() {
+        let message_store = TeeStore::new(vec![
+            Arc::new(CookieMessageStore::builder(Key::generate()).build()),
+            Arc::new(SessionMessageStore::default()),
+            Arc::new(FailingStore),
+        ]);
+        let session_middleware =
+            SessionMiddleware::builder(CookieSessionStore::default(), Key::generate())
+                .cookie_name("_session".to_string())
+                .build();
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(FlashMessagesFramework::builder(message_store).build())
+                .wrap(session_middleware)
+                .service(resource("/set").route(web::get().to(set)))
+                .service(resource("/show").route(web::get().to(show))),
+        )
+        .await;
+
+        // A failing backend does not prevent the others from receiving the messages.
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get().uri("/set").to_request(),
+        )
+        .await;
+        let find_cookie = |name: &str| {
+            resp.response()
+                .cookies()
+                .find(|c| c.name() == name)
+                .map(|c| c.into_owned())
+                .unwrap()
+        };
+        let flash_cookie = find_cookie("_flash");
+        let session_cookie = find_cookie("_session");
+
+        for cookie in [flash_cookie, session_cookie] {
+            let resp = actix_web::test::call_service(
+                &app,
+                actix_web::test::TestRequest::get()
+                    .uri("/show")
+                    .cookie(cookie)
+                    .to_request(),
+            )
+            .await;
+            let body_bytes = actix_web::test::read_body(resp).await;
+            assert_eq!(
+                std::str::from_utf8(&body_bytes).unwrap(),
+                "Hey there! - info\n"
+            );
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_store_fails_if_every_backend_fails() {
+        let message_store = TeeStore::new(vec![Arc::new(FailingStore), Arc::new(FailingStore)]);
+        let request = actix_web::test::TestRequest::default().to_http_request();
+        let mut response = HttpResponse::Ok().finish();
+        let result = message_store.store(
+            &[FlashMessage::info("Hey there!")],
+            request.clone(),
+            response.head_mut(),
+        );
+        assert!(result.is_err());
+        assert!(message_store.load(&request).is_err());
+    }
+}
+
+#[cfg(feature = "cookies")]
+mod message_expiry {
+    use super::*;
+    use actix_web_flash_messages::storage::CookieMessageStore;
+
+    async fn set_with_expiry() -> impl Responder {
+        FlashMessage::info("Expired").with_expires_at(1).send();
+        FlashMessage::info("Still valid")
+            .with_expires_at(u64::MAX)
+            .send();
+        HttpResponse::SeeOther()
+            .insert_header((actix_web::http::header::LOCATION, "/show"))
+            .finish()
+    }
+
+    async fn set_expired() -> impl Responder {
+        FlashMessage::builder()
+            .content("Expired")
+            .expires_at(1)
+            .send();
+        HttpResponse::SeeOther()
+            .insert_header((actix_web::http::header::LOCATION, "/count"))
+            .finish()
+    }
+
+    async fn count(
+        actix_web_flash_messages::FlashMessageCount(count): actix_web_flash_messages::FlashMessageCount,
+    ) -> impl Responder {
+        HttpResponse::Ok().body(count.to_string())
+    }
+
+    #[actix_rt::test]
+    async fn test_expired_messages_are_dropped_on_load() {
+        let store = CookieMessageStore::builder(Key::generate()).build();
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(FlashMessagesFramework::builder(store).build())
+                .service(resource("/set").route(web::get().to(set_with_expiry)))
+                .service(resource("/set_expired").route(web::get().to(set_expired)))
+                .service(resource("/count").route(web::get().to(count)))
+                .service(resource("/show").route(web::get().to(show))),
+        )
+        .await;
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get().uri("/set").to_request(),
+        )
+        .await;
+        let cookie = resp.response().cookies().next().unwrap().into_owned();
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/show")
+                .cookie(cookie)
+                .to_request(),
+        )
+        .await;
+        let body_bytes = actix_web::test::read_body(resp).await;
+        assert_eq!(
+            std::str::from_utf8(&body_bytes).unwrap(),
+            "Still valid - info\n"
+        );
+
+        // Peeking does not consume messages, but expired ones are still removed from the cookie.
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/set_expired")
+                .to_request(),
+        )
+        .await;
+        let cookie = resp.response().cookies().next().unwrap().into_owned();
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/count")
+                .cookie(cookie)
+                .to_request(),
+        )
+        .await;
+        let removal_cookie = resp.response().cookies().next().unwrap().into_owned();
+        assert_eq!(removal_cookie.value(), "");
+        let body_bytes = actix_web::test::read_body(resp).await;
+        assert_eq!(std::str::from_utf8(&body_bytes).unwrap(), "0");
+    }
+}
+
+mod incoming_serialization {
+    use super::*;
+    use actix_web_flash_messages::Level;
+
+    #[test]
+    fn test_incoming_messages_serialize_as_a_bare_array() {
+        let incoming: IncomingFlashMessages =
+            serde_json::from_str(r#"[{"content": "Hey there!", "level": "Info"}]"#).unwrap();
+        let serialized = serde_json::to_string(&incoming).unwrap();
+        if cfg!(feature = "numeric-levels") {
+            assert_eq!(serialized, r#"[{"content":"Hey there!","level":1}]"#);
+        } else {
+            assert_eq!(serialized, r#"[{"content":"Hey there!","level":"info"}]"#);
+        }
+    }
+
+    #[test]
+    fn test_merge_concatenates_messages() {
+        let first: IncomingFlashMessages =
+            serde_json::from_str(r#"[{"content": "A", "level": "info"}]"#).unwrap();
+        let second: IncomingFlashMessages = serde_json::from_str(
+            r#"[{"content": "B", "level": "error"}, {"content": "A", "level": "info"}]"#,
+        )
+        .unwrap();
+        let merged = first.merge(second);
+        let contents: Vec<_> = merged.iter().map(|m| m.content()).collect();
+        assert_eq!(contents, vec!["A", "B", "A"]);
+        let messages = merged.dedup().take();
+        let contents: Vec<_> = messages.iter().map(|m| m.content()).collect();
+        assert_eq!(contents, vec!["A", "B"]);
+    }
+
+    #[test]
+    fn test_dedup_keeps_the_first_occurrence() {
+        let incoming: IncomingFlashMessages = serde_json::from_str(
+            r#"[
+                {"content": "A", "level": "info"},
+                {"content": "A", "level": "error"},
+                {"content": "B", "level": "info", "title": "First"},
+                {"content": "A", "level": "info"},
+                {"content": "B", "level": "info", "title": "Second"}
+            ]"#,
+        )
+        .unwrap();
+        let messages = incoming.dedup().take();
+        let messages: Vec<_> = messages
+            .iter()
+            .map(|m| (m.content(), m.level(), m.title()))
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                ("A", Level::Info, None),
+                ("A", Level::Error, None),
+                ("B", Level::Info, Some("First")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_summary_counts_messages_by_level() {
+        let incoming: IncomingFlashMessages = serde_json::from_str(
+            r#"[
+                {"content": "A", "level": "info"},
+                {"content": "B", "level": "error"},
+                {"content": "C", "level": "info"}
+            ]"#,
+        )
+        .unwrap();
+        let summary = incoming.summary();
+        assert_eq!(summary.total, 3);
+        assert_eq!(
+            summary.by_level.into_iter().collect::<Vec<_>>(),
+            vec![(Level::Info, 2), (Level::Error, 1)]
+        );
+        assert_eq!(summary.max_level, Some(Level::Error));
+
+        let json = serde_json::to_value(incoming.summary()).unwrap();
+        assert_eq!(json["total"], 3);
+        assert_eq!(
+            json["max_level"],
+            serde_json::to_value(Level::Error).unwrap()
+        );
+
+        let empty: IncomingFlashMessages = serde_json::from_str("[]").unwrap();
+        let summary = empty.summary();
+        assert_eq!(summary.total, 0);
+        assert!(summary.by_level.is_empty());
+        assert_eq!(summary.max_level, None);
+    }
+}
+
+#[cfg(feature = "cookies")]
+mod content_prefix {
+    use super::*;
+    use actix_web_flash_messages::storage::CookieMessageStore;
+
+    async fn set_mixed() -> impl Responder {
+        FlashMessage::info(" ").send();
+        FlashMessage::info("Hey there!").send();
+        FlashMessage::error("Stack trace: [...]").send();
+        HttpResponse::Ok()
+    }
+
+    #[actix_rt::test]
+    async fn test_prefix_composes_with_skip_empty_and_truncation() {
+        let cookie_store = CookieMessageStore::builder(Key::generate()).build();
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(
+                    FlashMessagesFramework::builder(cookie_store)
+                        .content_prefix("[STAGING] ")
+                        .skip_empty(true)
+                        .truncate_content(20)
+                        .build(),
+                )
+                .service(resource("/set").route(web::get().to(set_mixed)))
+                .service(resource("/show").route(web::get().to(show))),
+        )
+        .await;
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get().uri("/set").to_request(),
+        )
+        .await;
+        let flash_cookie = resp
+            .response()
+            .cookies()
+            .find(|c| c.name() == "_flash")
+            .unwrap()
+            .into_owned();
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/show")
+                .cookie(flash_cookie)
+                .to_request(),
+        )
+        .await;
+        let body_bytes = actix_web::test::read_body(resp).await;
+        // The blank message is still skipped and the prefix counts towards the length limit.
+        assert_eq!(
+            std::str::from_utf8(&body_bytes).unwrap(),
+            "[STAGING] Hey there! - info\n[STAGING] Stack t… - error\n"
+        );
+    }
+}
+
+#[cfg(feature = "cookies")]
+mod method_preserving_redirects {
+    use super::*;
+    use actix_web::http::StatusCode;
+    use actix_web_flash_messages::storage::CookieMessageStore;
+
+    async fn submit() -> impl Responder {
+        FlashMessage::info("Hey there!").send();
+        HttpResponse::TemporaryRedirect()
+            .insert_header((actix_web::http::header::LOCATION, "/target"))
+            .finish()
+    }
+
+    #[actix_rt::test]
+    async fn test_flash_cookie_survives_a_307_redirect() {
+        let cookie_store = CookieMessageStore::builder(Key::generate()).build();
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(FlashMessagesFramework::builder(cookie_store).build())
+                .service(resource("/submit").route(web::post().to(submit)))
+                .service(resource("/target").route(web::post().to(show))),
+        )
+        .await;
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::post()
+                .uri("/submit")
+                .set_form([("name", "Luca")])
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::TEMPORARY_REDIRECT);
+        let flash_cookie = resp
+            .response()
+            .cookies()
+            .find(|c| c.name() == "_flash")
+            .unwrap()
+            .into_owned();
+
+        // The browser re-issues the `POST`, body included, against the new location.
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::post()
+                .uri("/target")
+                .set_form([("name", "Luca")])
+                .cookie(flash_cookie)
+                .to_request(),
+        )
+        .await;
+        let body_bytes = actix_web::test::read_body(resp).await;
+        assert_eq!(
+            std::str::from_utf8(&body_bytes).unwrap(),
+            "Hey there! - info\n"
+        );
+    }
+}
+
+#[cfg(feature = "cookies")]
+mod legacy_cookies {
+    use super::*;
+    use actix_web::cookie::Cookie;
+    use actix_web_flash_messages::storage::CookieMessageStore;
+
+    #[actix_rt::test]
+    async fn test_legacy_cookies_are_read_and_cleared() {
+        let cookie_store = CookieMessageStore::builder(Key::generate())
+            .legacy_cookie_prefix("flash_".into())
+            .build();
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(FlashMessagesFramework::builder(cookie_store).build())
+                .service(resource("/set").route(web::get().to(set)))
+                .service(resource("/show").route(web::get().to(show))),
+        )
+        .await;
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get().uri("/set").to_request(),
+        )
+        .await;
+        let flash_cookie = resp
+            .response()
+            .cookies()
+            .find(|c| c.name() == "_flash")
+            .unwrap()
+            .into_owned();
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/show")
+                .cookie(Cookie::new("flash_2", "Second"))
+                .cookie(Cookie::new("flash_1", "First"))
+                .cookie(Cookie::new("flash_other", "Ignored"))
+                .cookie(flash_cookie)
+                .to_request(),
+        )
+        .await;
+        let mut removed = resp
+            .response()
+            .cookies()
+            .filter(|c| c.max_age() == Some(time::Duration::seconds(0)))
+            .map(|c| c.name().to_owned())
+            .collect::<Vec<_>>();
+        removed.sort();
+        assert_eq!(removed, vec!["_flash", "flash_1", "flash_2"]);
+        let body_bytes = actix_web::test::read_body(resp).await;
+        assert_eq!(
+            std::str::from_utf8(&body_bytes).unwrap(),
+            "First - info\nSecond - info\nHey there! - info\n"
+        );
+    }
+}
+
+#[cfg(feature = "cookies")]
+mod flash_stored {
+    use super::*;
+    use actix_web_flash_messages::storage::CookieMessageStore;
+    use actix_web_flash_messages::FlashStored;
+
+    #[actix_rt::test]
+    async fn test_the_number_of_stored_messages_is_exposed_on_the_response() {
+        let cookie_store = CookieMessageStore::builder(Key::generate()).build();
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(FlashMessagesFramework::builder(cookie_store).build())
+                .service(resource("/set").route(web::get().to(set)))
+                .service(resource("/show").route(web::get().to(show))),
+        )
+        .await;
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get().uri("/set").to_request(),
+        )
+        .await;
+        assert_eq!(
+            resp.response().extensions().get::<FlashStored>(),
+            Some(&FlashStored { count: 1 })
+        );
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/show")
+                .to_request(),
+        )
+        .await;
+        assert_eq!(
+            resp.response().extensions().get::<FlashStored>(),
+            Some(&FlashStored { count: 0 })
+        );
+    }
+}
+
+#[cfg(feature = "jwt")]
+mod jwt {
+    use super::*;
+    use actix_web::http::header::LOCATION;
+    use actix_web::HttpRequest;
+    use actix_web_flash_messages::storage::{FlashMessageStore, JwtMessageStore, LoadError};
+    use std::time::Duration;
+
+    fn store(secret: &[u8]) -> JwtMessageStore {
+        JwtMessageStore::builder(secret.to_vec()).build()
+    }
+
+    fn request_with_token(token: &str) -> HttpRequest {
+        actix_web::test::TestRequest::get()
+            .uri(&format!("/show?_flash_token={}", token))
+            .to_http_request()
+    }
+
+    #[actix_rt::test]
+    async fn test_messages_are_handed_over_via_the_redirect_location() {
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(FlashMessagesFramework::builder(store(b"shared")).build())
+                .service(resource("/set").route(web::get().to(set)))
+                .service(resource("/show").route(web::get().to(show))),
+        )
+        .await;
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get().uri("/set").to_request(),
+        )
+        .await;
+        let location = resp.headers().get(LOCATION).unwrap().to_str().unwrap();
+        assert!(location.starts_with("/show?_flash_token="));
+
+        // Another service sharing the secret.
+        let other_service = actix_web::test::init_service(
+            App::new()
+                .wrap(FlashMessagesFramework::builder(store(b"shared")).build())
+                .service(resource("/show").route(web::get().to(show))),
+        )
+        .await;
+        let resp = actix_web::test::call_service(
+            &other_service,
+            actix_web::test::TestRequest::get()
+                .uri(location)
+                .to_request(),
+        )
+        .await;
+        let body_bytes = actix_web::test::read_body(resp).await;
+        assert_eq!(
+            std::str::from_utf8(&body_bytes).unwrap(),
+            "Hey there! - info\n"
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_the_redirect_location_shows_no_messages_once_the_token_has_expired() {
+        let expired_store = JwtMessageStore::builder(b"shared".to_vec())
+            .ttl(Duration::ZERO)
+            .build();
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(FlashMessagesFramework::builder(expired_store).build())
+                .service(resource("/set").route(web::get().to(set)))
+                .service(resource("/show").route(web::get().to(show))),
+        )
+        .await;
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get().uri("/set").to_request(),
+        )
+        .await;
+        let location = resp.headers().get(LOCATION).unwrap().to_str().unwrap();
+        assert!(location.starts_with("/show?_flash_token="));
+
+        // E.g. the page is refreshed, or bookmarked, after the token has expired.
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri(location)
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), 200);
+        let body_bytes = actix_web::test::read_body(resp).await;
+        assert!(body_bytes.is_empty());
+    }
+
+    #[test]
+    fn test_tampered_and_unsigned_tokens_are_rejected() {
+        let token = store(b"shared")
+            .mint(&[FlashMessage::info("Hey there!")])
+            .unwrap();
+        let messages = store(b"shared").load(&request_with_token(&token)).unwrap();
+        assert_eq!(messages[0].content(), "Hey there!");
+
+        // Wrong secret.
+        let result = store(b"other").load(&request_with_token(&token));
+        assert!(matches!(result, Err(LoadError::IntegrityCheckFailed(_))));
+
+        // Expired.
+        let expired = JwtMessageStore::builder(b"shared".to_vec())
+            .ttl(Duration::ZERO)
+            .build()
+            .mint(&[FlashMessage::info("Hey there!")])
+            .unwrap();
+        let messages = store(b"shared")
+            .load(&request_with_token(&expired))
+            .unwrap();
+        assert!(messages.is_empty());
+        // Expired, with the wrong secret.
+        let result = store(b"other").load(&request_with_token(&expired));
+        assert!(matches!(result, Err(LoadError::IntegrityCheckFailed(_))));
+
+        // `alg: none`, with the signature stripped.
+        let (_, rest) = token.split_once('.').unwrap();
+        let (claims, _) = rest.split_once('.').unwrap();
+        let unsigned = format!("eyJhbGciOiJub25lIiwidHlwIjoiSldUIn0.{}.", claims);
+        let result = store(b"shared").load(&request_with_token(&unsigned));
+        assert!(matches!(result, Err(LoadError::IntegrityCheckFailed(_))));
+    }
+}
+
+#[cfg(feature = "cookies")]
+mod debug_param {
+    use super::*;
+    use actix_web_flash_messages::storage::CookieMessageStore;
+
+    #[actix_rt::test]
+    async fn test_debug_param_reveals_messages_below_the_minimum_level() {
+        let cookie_store = CookieMessageStore::builder(Key::generate()).build();
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(
+                    FlashMessagesFramework::builder(cookie_store)
+                        .debug_param("flash_debug", "1")
+                        .build(),
+                )
+                .service(resource("/set").route(web::get().to(set)))
+                .service(resource("/show").route(web::get().to(show))),
+        )
+        .await;
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get().uri("/set").to_request(),
+        )
+        .await;
+        let flash_cookie = resp
+            .response()
+            .cookies()
+            .find(|c| c.name() == "_flash")
+            .unwrap()
+            .into_owned();
+
+        for (uri, expected) in [
+            ("/show", "Hey there! - info\n"),
+            ("/show?flash_debug=0", "Hey there! - info\n"),
+            (
+                "/show?flash_debug=1",
+                "Hey there! - info\nHow is it going? - debug\n",
+            ),
+        ] {
+            let resp = actix_web::test::call_service(
+                &app,
+                actix_web::test::TestRequest::get()
+                    .uri(uri)
+                    .cookie(flash_cookie.clone())
+                    .to_request(),
+            )
+            .await;
+            let body_bytes = actix_web::test::read_body(resp).await;
+            assert_eq!(std::str::from_utf8(&body_bytes).unwrap(), expected);
+        }
+    }
+}
+
+#[cfg(feature = "cookies")]
+mod macros {
+    use super::*;
+    use actix_web_flash_messages::storage::CookieMessageStore;
+    use actix_web_flash_messages::{
+        flash_debug, flash_error, flash_info, flash_success, flash_warning,
+    };
+
+    async fn set_via_macros() -> impl Responder {
+        let user_id = 7;
+        flash_debug!("Debugging");
+        flash_info!("User {} saved", user_id);
+        flash_success!("Done");
+        flash_warning!("{} warnings", 2);
+        flash_error!("Oops: {reason}", reason = "timeout");
+        HttpResponse::Ok()
+    }
+
+    #[actix_rt::test]
+    async fn test_macros_format_and_send_messages() {
+        let cookie_store = CookieMessageStore::builder(Key::generate()).build();
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(FlashMessagesFramework::builder(cookie_store).build())
+                .service(resource("/set").route(web::get().to(set_via_macros)))
+                .service(resource("/show").route(web::get().to(show))),
+        )
+        .await;
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get().uri("/set").to_request(),
+        )
+        .await;
+        let flash_cookie = resp
+            .response()
+            .cookies()
+            .find(|c| c.name() == "_flash")
+            .unwrap()
+            .into_owned();
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/show")
+                .cookie(flash_cookie)
+                .to_request(),
+        )
+        .await;
+        let body_bytes = actix_web::test::read_body(resp).await;
+        // The debug-level message is below the default minimum level.
+        assert_eq!(
+            std::str::from_utf8(&body_bytes).unwrap(),
+            "User 7 saved - info\nDone - success\n2 warnings - warning\nOops: timeout - error\n"
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_macros_do_not_panic_outside_of_the_framework() {
+        // E.g. a background job, spawned outside of any request handler.
+        actix_rt::spawn(async {
+            flash_info!("User {} saved", 7);
+            flash_error!("Oops");
+        })
+        .await
+        .unwrap();
+        assert!(!FlashMessage::info("Hey there!").try_send());
+    }
+}
+
+#[cfg(feature = "cookies")]
+mod max_per_level {
+    use super::*;
+    use actix_web_flash_messages::storage::CookieMessageStore;
+    use actix_web_flash_messages::Level;
+
+    async fn set_burst() -> impl Responder {
+        for i in 1..=4 {
+            FlashMessage::error(format!("Error {}", i)).send();
+            FlashMessage::info(format!("Info {}", i)).send();
+        }
+        HttpResponse::Ok()
+    }
+
+    #[actix_rt::test]
+    async fn test_only_the_last_messages_of_a_limited_level_are_kept() {
+        let cookie_store = CookieMessageStore::builder(Key::generate()).build();
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(
+                    FlashMessagesFramework::builder(cookie_store)
+                        .max_per_level(Level::Error, 2)
+                        .build(),
+                )
+                .service(resource("/set").route(web::get().to(set_burst)))
+                .service(resource("/show").route(web::get().to(show))),
+        )
+        .await;
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get().uri("/set").to_request(),
+        )
+        .await;
+        let flash_cookie = resp
+            .response()
+            .cookies()
+            .find(|c| c.name() == "_flash")
+            .unwrap()
+            .into_owned();
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/show")
+                .cookie(flash_cookie)
+                .to_request(),
+        )
+        .await;
+        let body_bytes = actix_web::test::read_body(resp).await;
+        assert_eq!(
+            std::str::from_utf8(&body_bytes).unwrap(),
+            "Info 1 - info\nInfo 2 - info\nError 3 - error\nInfo 3 - info\nError 4 - error\nInfo 4 - info\n"
+        );
+    }
+}
+
+#[cfg(feature = "sessions")]
+mod session_clear {
+    use super::*;
+    use actix_session::{storage::CookieSessionStore, SessionExt, SessionMiddleware};
+    use actix_web::HttpRequest;
+    use actix_web_flash_messages::storage::SessionMessageStore;
+
+    async fn clear(req: HttpRequest, store: web::Data<SessionMessageStore>) -> impl Responder {
+        let before = req
+            .get_session()
+            .get::<serde_json::Value>("_flash")
+            .unwrap();
+        store.clear(&req);
+        let after = req
+            .get_session()
+            .get::<serde_json::Value>("_flash")
+            .unwrap();
+        HttpResponse::Ok().body(format!("{} {}", before.is_some(), after.is_some()))
+    }
+
+    #[actix_rt::test]
+    async fn test_flash_key_is_removed_on_demand() {
+        let store = SessionMessageStore::default();
+        let session_middleware =
+            SessionMiddleware::builder(CookieSessionStore::default(), Key::generate())
+                .cookie_name("_session".to_string())
+                .build();
+        let app = actix_web::test::init_service(
+            App::new()
+                .app_data(web::Data::new(store.clone()))
+                .wrap(FlashMessagesFramework::builder(store).build())
+                .wrap(session_middleware)
+                .service(resource("/set").route(web::get().to(set)))
+                .service(resource("/clear").route(web::get().to(clear))),
+        )
+        .await;
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get().uri("/set").to_request(),
+        )
+        .await;
+        let session_cookie = resp
+            .response()
+            .cookies()
+            .find(|c| c.name() == "_session")
+            .unwrap()
+            .into_owned();
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/clear")
+                .cookie(session_cookie)
+                .to_request(),
+        )
+        .await;
+        let body_bytes = actix_web::test::read_body(resp).await;
+        assert_eq!(std::str::from_utf8(&body_bytes).unwrap(), "true false");
+    }
+}
+
+#[cfg(feature = "cookies")]
+mod cookie_prefixes {
+    use super::*;
+    use actix_web_flash_messages::storage::CookieMessageStore;
+
+    #[actix_rt::test]
+    async fn test_host_prefixed_flash_cookies_roundtrip() {
+        let cookie_store = CookieMessageStore::builder(Key::generate())
+            .cookie_name("__Host-flash".into())
+            .build();
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(FlashMessagesFramework::builder(cookie_store).build())
+                .service(resource("/set").route(web::get().to(set)))
+                .service(resource("/show").route(web::get().to(show))),
+        )
+        .await;
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get().uri("/set").to_request(),
+        )
+        .await;
+        let flash_cookie = resp
+            .response()
+            .cookies()
+            .find(|c| c.name() == "__Host-flash")
+            .unwrap()
+            .into_owned();
+        assert_eq!(flash_cookie.secure(), Some(true));
+        assert_eq!(flash_cookie.path(), Some("/"));
+        assert_eq!(flash_cookie.domain(), None);
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/show")
+                .cookie(flash_cookie)
+                .to_request(),
+        )
+        .await;
+        // Browsers would ignore a removal cookie that is not marked as `Secure`.
+        let removal_cookie = resp
+            .response()
+            .cookies()
+            .find(|c| c.name() == "__Host-flash")
+            .unwrap()
+            .into_owned();
+        assert_eq!(removal_cookie.secure(), Some(true));
+        assert_eq!(removal_cookie.path(), Some("/"));
+        let body_bytes = actix_web::test::read_body(resp).await;
+        assert_eq!(
+            std::str::from_utf8(&body_bytes).unwrap(),
+            "Hey there! - info\n"
+        );
+    }
+
+    #[test]
+    fn test_secure_prefix_allows_a_domain_and_a_custom_path() {
+        let _ = CookieMessageStore::builder(Key::generate())
+            .cookie_name("__Secure-flash".into())
+            .domain("example.com".into())
+            .path("/app".into())
+            .build();
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "cookies using the `__Secure-` or `__Host-` prefix must be marked as `Secure`"
+    )]
+    fn test_secure_prefix_requires_the_secure_attribute() {
+        let _ = CookieMessageStore::builder(Key::generate())
+            .cookie_name("__Secure-flash".into())
+            .configure_cookie(|cookie| cookie.secure(false))
+            .build();
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "cookies using the `__Host-` prefix must not have a `Domain` attribute"
+    )]
+    fn test_host_prefix_rejects_a_domain() {
+        let _ = CookieMessageStore::builder(Key::generate())
+            .cookie_name("__Host-flash".into())
+            .domain("example.com".into())
+            .build();
+    }
+
+    #[test]
+    #[should_panic(expected = "cookies using the `__Host-` prefix must have `Path=/`")]
+    fn test_host_prefix_requires_the_root_path() {
+        let _ = CookieMessageStore::builder(Key::generate())
+            .cookie_name("__Host-flash".into())
+            .path("/app".into())
+            .build();
+    }
+
+    #[test]
+    #[should_panic(expected = "must be marked as `Secure`")]
+    fn test_host_prefix_requires_the_secure_attribute() {
+        let _ = CookieMessageStore::builder(Key::generate())
+            .cookie_name("__Host-flash".into())
+            .configure_cookie(|cookie| cookie.secure(false))
+            .build();
+    }
+}
+
+#[cfg(feature = "cookies")]
+mod lenient_loading {
+    use super::*;
+    use actix_web::cookie::Cookie;
+    use actix_web::dev::ResponseHead;
+    use actix_web::http::StatusCode;
+    use actix_web::HttpRequest;
+    #[cfg(feature = "tracing")]
+    use actix_web_flash_messages::storage::LoggingStore;
+    use actix_web_flash_messages::storage::{
+        CookieMessageStore, FlashMessageStore, LoadError, RateLimitedStore, RedirectAwareStore,
+        StoreError,
+    };
+    use actix_web_flash_messages::LenientFlashMessages;
+
+    async fn show_lenient(incoming: LenientFlashMessages) -> HttpResponse {
+        let mut body = String::new();
+        for message in incoming.messages.iter() {
+            writeln!(body, "{} - {}", message.content(), message.level()).unwrap();
+        }
+        for warning in &incoming.warnings {
+            writeln!(
+                body,
+                "integrity failure: {}",
+                warning.is_integrity_failure()
+            )
+            .unwrap();
+        }
+        HttpResponse::Ok().body(body)
+    }
+
+    #[actix_rt::test]
+    async fn test_good_messages_are_returned_alongside_decode_failures() {
+        let cookie_store = CookieMessageStore::builder(Key::generate())
+            .legacy_cookie_prefix("flash_".into())
+            .build();
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(FlashMessagesFramework::builder(cookie_store).build())
+                .service(resource("/show").route(web::get().to(show)))
+                .service(resource("/show_lenient").route(web::get().to(show_lenient))),
+        )
+        .await;
+        let request = |uri: &str| {
+            actix_web::test::TestRequest::get()
+                .uri(uri)
+                .cookie(Cookie::new("flash_1", "Legacy"))
+                .cookie(Cookie::new("_flash", "tampered"))
+                .to_request()
+        };
+
+        // The strict extractor rejects the request altogether.
+        let resp = actix_web::test::call_service(&app, request("/show")).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let resp = actix_web::test::call_service(&app, request("/show_lenient")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body_bytes = actix_web::test::read_body(resp).await;
+        assert_eq!(
+            std::str::from_utf8(&body_bytes).unwrap(),
+            "Legacy - info\nintegrity failure: true\n"
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_no_warnings_are_reported_for_valid_messages() {
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(
+                    FlashMessagesFramework::builder(
+                        CookieMessageStore::builder(Key::generate()).build(),
+                    )
+                    .build(),
+                )
+                .service(resource("/set").route(web::get().to(set)))
+                .service(resource("/show_lenient").route(web::get().to(show_lenient))),
+        )
+        .await;
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get().uri("/set").to_request(),
+        )
+        .await;
+        let flash_cookie = resp
+            .response()
+            .cookies()
+            .find(|c| c.name() == "_flash")
+            .unwrap()
+            .into_owned();
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/show_lenient")
+                .cookie(flash_cookie)
+                .to_request(),
+        )
+        .await;
+        let body_bytes = actix_web::test::read_body(resp).await;
+        assert_eq!(
+            std::str::from_utf8(&body_bytes).unwrap(),
+            "Hey there! - info\n"
+        );
+    }
+
+    /// A store whose backend is always unavailable.
+    struct FailingStore;
+
+    impl FlashMessageStore for FailingStore {
+        fn load(&self, _request: &HttpRequest) -> Result<Vec<FlashMessage>, LoadError> {
+            Err(LoadError::GenericError(anyhow::anyhow!(
+                "Backend unavailable"
+            )))
+        }
+
+        fn store(
+            &self,
+            _messages: &[FlashMessage],
+            _request: HttpRequest,
+            _response: &mut ResponseHead,
+        ) -> Result<(), StoreError> {
+            Ok(())
+        }
+    }
+
+    fn partially_failing_store() -> RedirectAwareStore<CookieMessageStore, FailingStore> {
+        let cookie_store = CookieMessageStore::builder(Key::generate())
+            .legacy_cookie_prefix("flash_".into())
+            .build();
+        RedirectAwareStore::new(cookie_store, FailingStore)
+    }
+
+    #[actix_rt::test]
+    async fn test_combinators_forward_partial_failures() {
+        #[allow(unused_mut)]
+        let mut stores: Vec<Box<dyn Fn() -> FlashMessagesFramework>> = vec![Box::new(|| {
+            let store =
+                RateLimitedStore::builder(partially_failing_store(), |_| Some("client".into()))
+                    .build();
+            FlashMessagesFramework::builder(store).build()
+        })];
+        #[cfg(feature = "tracing")]
+        stores.push(Box::new(|| {
+            FlashMessagesFramework::builder(LoggingStore::new(partially_failing_store())).build()
+        }));
+        for framework in stores {
+            let app = actix_web::test::init_service(
+                App::new()
+                    .wrap(framework())
+                    .service(resource("/show_lenient").route(web::get().to(show_lenient))),
+            )
+            .await;
+            let resp = actix_web::test::call_service(
+                &app,
+                actix_web::test::TestRequest::get()
+                    .uri("/show_lenient")
+                    .cookie(Cookie::new("flash_1", "Legacy"))
+                    .to_request(),
+            )
+            .await;
+            assert_eq!(resp.status(), StatusCode::OK);
+            let body_bytes = actix_web::test::read_body(resp).await;
+            assert_eq!(
+                std::str::from_utf8(&body_bytes).unwrap(),
+                "Legacy - info\nintegrity failure: false\n"
+            );
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_cookies_signed_with_a_stale_key_are_cleared() {
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(
+                    FlashMessagesFramework::builder(
+                        CookieMessageStore::builder(Key::generate()).build(),
+                    )
+                    .build(),
+                )
+                .service(resource("/show_lenient").route(web::get().to(show_lenient))),
+        )
+        .await;
+        // A cookie signed with a key that has since been rotated out.
+        let stale_store = CookieMessageStore::builder(Key::generate()).build();
+        let stale_app = actix_web::test::init_service(
+            App::new()
+                .wrap(FlashMessagesFramework::builder(stale_store).build())
+                .service(resource("/set").route(web::get().to(set))),
+        )
+        .await;
+        let resp = actix_web::test::call_service(
+            &stale_app,
+            actix_web::test::TestRequest::get().uri("/set").to_request(),
+        )
+        .await;
+        let stale_cookie = resp
+            .response()
+            .cookies()
+            .find(|c| c.name() == "_flash")
+            .unwrap()
+            .into_owned();
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/show_lenient")
+                .cookie(stale_cookie)
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let removal_cookie = resp
+            .response()
+            .cookies()
+            .find(|c| c.name() == "_flash")
+            .unwrap()
+            .into_owned();
+        assert_eq!(removal_cookie.value(), "");
+        assert_eq!(
+            removal_cookie.max_age(),
+            Some(actix_web::cookie::time::Duration::ZERO)
+        );
+        let body_bytes = actix_web::test::read_body(resp).await;
+        assert_eq!(
+            std::str::from_utf8(&body_bytes).unwrap(),
+            "integrity failure: true\n"
+        );
+    }
+}
+
+#[cfg(feature = "cookies")]
+mod initial_capacity {
+    use super::*;
+    use actix_web_flash_messages::storage::CookieMessageStore;
+
+    async fn set_many() -> impl Responder {
+        for i in 1..=4 {
+            FlashMessage::info(format!("Message {}", i)).send();
+        }
+        HttpResponse::Ok()
+    }
+
+    #[actix_rt::test]
+    async fn test_messages_beyond_the_initial_capacity_are_flushed() {
+        let cookie_store = CookieMessageStore::builder(Key::generate()).build();
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(
+                    FlashMessagesFramework::builder(cookie_store)
+                        .initial_capacity(2)
+                        .build(),
+                )
+                .service(resource("/set").route(web::get().to(set_many)))
+                .service(resource("/show").route(web::get().to(show))),
+        )
+        .await;
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get().uri("/set").to_request(),
+        )
+        .await;
+        let flash_cookie = resp
+            .response()
+            .cookies()
+            .find(|c| c.name() == "_flash")
+            .unwrap()
+            .into_owned();
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/show")
+                .cookie(flash_cookie)
+                .to_request(),
+        )
+        .await;
+        let body_bytes = actix_web::test::read_body(resp).await;
+        assert_eq!(
+            std::str::from_utf8(&body_bytes).unwrap(),
+            "Message 1 - info\nMessage 2 - info\nMessage 3 - info\nMessage 4 - info\n"
+        );
+    }
+}
+
+#[cfg(feature = "sessions")]
+mod session_middleware_ordering {
+    use super::*;
+    use actix_session::{storage::CookieSessionStore, SessionMiddleware};
+    use actix_web_flash_messages::storage::SessionMessageStore;
+
+    #[actix_rt::test]
+    async fn test_messages_are_lost_if_the_session_middleware_is_mounted_in_the_wrong_order() {
+        let app = actix_web::test::init_service(
+            App::new()
+                // Wrong order: the session middleware should wrap the flash messages framework.
+                .wrap(SessionMiddleware::new(
+                    CookieSessionStore::default(),
+                    Key::generate(),
+                ))
+                .wrap(FlashMessagesFramework::builder(SessionMessageStore::default()).build())
+                .service(resource("/set").route(web::get().to(set)))
+                .service(resource("/show").route(web::get().to(show))),
+        )
+        .await;
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get().uri("/set").to_request(),
+        )
+        .await;
+        // The session state was persisted before outgoing messages were stored: no session
+        // cookie is set and the messages never reach the next request.
+        assert_eq!(resp.response().cookies().count(), 0);
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/show")
+                .to_request(),
+        )
+        .await;
+        assert!(resp.status().is_success());
+        let body_bytes = actix_web::test::read_body(resp).await;
+        assert_eq!(std::str::from_utf8(&body_bytes).unwrap(), "");
+    }
+}
+
+#[cfg(feature = "cookies")]
+mod set_outgoing {
+    use super::*;
+    use actix_web_flash_messages::storage::CookieMessageStore;
+
+    async fn replace() -> impl Responder {
+        FlashMessage::error("Draft error").send();
+        FlashMessage::warning("Draft warning").send();
+        FlashMessage::set_outgoing(vec![
+            FlashMessage::success("Saved"),
+            // Below the minimum level - it is dropped.
+            FlashMessage::debug("Took 3ms"),
+        ]);
+        HttpResponse::Ok()
+    }
+
+    #[actix_rt::test]
+    async fn test_outgoing_messages_are_replaced() {
+        let cookie_store = CookieMessageStore::builder(Key::generate()).build();
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(FlashMessagesFramework::builder(cookie_store).build())
+                .service(resource("/replace").route(web::get().to(replace)))
+                .service(resource("/show").route(web::get().to(show))),
+        )
+        .await;
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/replace")
+                .to_request(),
+        )
+        .await;
+        let flash_cookie = resp
+            .response()
+            .cookies()
+            .find(|c| c.name() == "_flash")
+            .unwrap()
+            .into_owned();
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/show")
+                .cookie(flash_cookie)
+                .to_request(),
+        )
+        .await;
+        let body_bytes = actix_web::test::read_body(resp).await;
+        assert_eq!(
+            std::str::from_utf8(&body_bytes).unwrap(),
+            "Saved - success\n"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Failed to set outgoing flash messages!")]
+    fn test_set_outgoing_panics_outside_of_the_framework() {
+        FlashMessage::set_outgoing(vec![]);
+    }
+}
+
+mod one_time {
+    use super::*;
+    use actix_web::dev::ResponseHead;
+    use actix_web::HttpRequest;
+    use actix_web_flash_messages::storage::{FlashMessageStore, LoadError, StoreError};
+    use std::sync::{Arc, Mutex};
+
+    /// Always returns the same incoming message and records what it is asked to store.
+    #[derive(Clone)]
+    struct StickyStore {
+        one_time: bool,
+        stored: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl FlashMessageStore for StickyStore {
+        fn load(&self, _request: &HttpRequest) -> Result<Vec<FlashMessage>, LoadError> {
+            Ok(vec![FlashMessage::info("Sticky")])
+        }
+
+        fn is_one_time(&self) -> bool {
+            self.one_time
+        }
+
+        fn store(
+            &self,
+            messages: &[FlashMessage],
+            _request: HttpRequest,
+            _response: &mut ResponseHead,
+        ) -> Result<(), StoreError> {
+            *self.stored.lock().unwrap() =
+                messages.iter().map(|m| m.content().to_owned()).collect();
+            Ok(())
+        }
+    }
+
+    async fn send_only() -> impl Responder {
+        FlashMessage::info("Fresh").send();
+        HttpResponse::Ok()
+    }
+
+    async fn stored_after_unread_request(one_time: bool) -> Vec<String> {
+        let store = StickyStore {
+            one_time,
+            stored: Default::default(),
+        };
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(
+                    FlashMessagesFramework::builder(store.clone())
+                        .clear_on_read(true)
+                        .build(),
+                )
+                .service(resource("/send").route(web::get().to(send_only))),
+        )
+        .await;
+        actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/send")
+                .to_request(),
+        )
+        .await;
+        let stored = store.stored.lock().unwrap().clone();
+        stored
+    }
+
+    #[actix_rt::test]
+    async fn test_unread_messages_are_carried_over_for_one_time_stores() {
+        assert_eq!(
+            stored_after_unread_request(true).await,
+            vec!["Sticky", "Fresh"]
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_unread_messages_are_not_carried_over_for_other_stores() {
+        assert_eq!(stored_after_unread_request(false).await, vec!["Fresh"]);
+    }
+
+    #[test]
+    fn test_built_in_stores_report_whether_they_are_one_time() {
+        use actix_web_flash_messages::storage::{HeaderMessageStore, TeeStore};
+
+        assert!(!HeaderMessageStore::default().is_one_time());
+        assert!(TeeStore::new(vec![]).is_one_time());
+        assert!(!TeeStore::new(vec![Arc::new(HeaderMessageStore::default())]).is_one_time());
+    }
+}
+
+#[cfg(all(feature = "html", feature = "cookies"))]
+mod html {
+    use super::*;
+    use actix_web_flash_messages::storage::CookieMessageStore;
+    use actix_web_flash_messages::{HtmlRenderer, Level};
+
+    async fn show_html(messages: IncomingFlashMessages) -> impl Responder {
+        HttpResponse::Ok().body(messages.render_html())
+    }
+
+    async fn set_unsafe() -> impl Responder {
+        FlashMessage::error("<script>alert('pwned')</script>").send();
+        FlashMessage::builder()
+            .level(Level::Success)
+            .title("Saved & done")
+            .content("Profile \"updated\"")
+            .send();
+        HttpResponse::Ok()
+    }
+
+    #[actix_rt::test]
+    async fn test_messages_are_rendered_as_escaped_accessible_markup() {
+        let cookie_store = CookieMessageStore::builder(Key::generate()).build();
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(FlashMessagesFramework::builder(cookie_store).build())
+                .service(resource("/set").route(web::get().to(set_unsafe)))
+                .service(resource("/show").route(web::get().to(show_html))),
+        )
+        .await;
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get().uri("/set").to_request(),
+        )
+        .await;
+        let flash_cookie = resp
+            .response()
+            .cookies()
+            .find(|c| c.name() == "_flash")
+            .unwrap()
+            .into_owned();
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/show")
+                .cookie(flash_cookie)
+                .to_request(),
+        )
+        .await;
+        let body_bytes = actix_web::test::read_body(resp).await;
+        assert_eq!(
+            std::str::from_utf8(&body_bytes).unwrap(),
+            "<div role=\"alert\" class=\"flash-error\">&lt;script&gt;alert(&#x27;pwned&#x27;)&lt;/script&gt;</div>\n\
+            <div role=\"alert\" class=\"flash-success\"><strong class=\"flash-title\">Saved &amp; done</strong> Profile &quot;updated&quot;</div>"
+        );
+    }
+
+    #[test]
+    fn test_element_and_class_prefix_are_configurable() {
+        let renderer = HtmlRenderer::new()
+            .element("li")
+            .class_prefix("alert-\"")
+            .dismissible(true);
+        assert_eq!(
+            renderer.render_message(&FlashMessage::warning("Careful")),
+            "<li role=\"alert\" class=\"alert-&quot;warning\">Careful\
+            <button type=\"button\" class=\"alert-&quot;dismiss\" aria-label=\"Dismiss\">&times;</button></li>"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid HTML element name, `div onclick=alert(1)`")]
+    fn test_invalid_element_names_are_rejected() {
+        let _ = HtmlRenderer::new().element("div onclick=alert(1)");
+    }
+}
+
+#[cfg(feature = "sessions")]
+mod session_merge {
+    use super::*;
+    use actix_session::{storage::CookieSessionStore, SessionMiddleware};
+    use actix_web::dev::ResponseHead;
+    use actix_web::http::StatusCode;
+    use actix_web::HttpRequest;
+    use actix_web_flash_messages::storage::{FlashMessageStore, SessionMessageStore};
+
+    /// Another writer flashes into the same session key while the request is being processed.
+    async fn flash_twice(req: HttpRequest) -> impl Responder {
+        SessionMessageStore::default()
+            .store(
+                &[FlashMessage::info("From elsewhere")],
+                req,
+                &mut ResponseHead::new(StatusCode::OK),
+            )
+            .unwrap();
+        FlashMessage::info("From handler").send();
+        HttpResponse::Ok()
+    }
+
+    /// Flashes a message without reading incoming messages.
+    async fn flash_without_reading() -> impl Responder {
+        FlashMessage::info("Fresh").send();
+        HttpResponse::Ok()
+    }
+
+    /// Replaces the messages in the session, rather than appending to them.
+    async fn replace(req: HttpRequest) -> impl Responder {
+        let store = SessionMessageStore::default();
+        store.clear(&req);
+        store
+            .store(
+                &[FlashMessage::info("From elsewhere")],
+                req,
+                &mut ResponseHead::new(StatusCode::OK),
+            )
+            .unwrap();
+        FlashMessage::info("From handler").send();
+        HttpResponse::Ok()
+    }
+
+    #[actix_rt::test]
+    async fn test_messages_replacing_the_incoming_ones_are_merged() {
+        let session_middleware =
+            SessionMiddleware::builder(CookieSessionStore::default(), Key::generate())
+                .cookie_name("_session".to_string())
+                .build();
+        let store = SessionMessageStore::default().merge(true);
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(FlashMessagesFramework::builder(store).build())
+                .wrap(session_middleware)
+                .service(resource("/set").route(web::get().to(set)))
+                .service(resource("/replace").route(web::get().to(replace)))
+                .service(resource("/show").route(web::get().to(show))),
+        )
+        .await;
+        let session_cookie = |resp: &actix_web::dev::ServiceResponse| {
+            resp.response()
+                .cookies()
+                .find(|c| c.name() == "_session")
+                .unwrap()
+                .into_owned()
+        };
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get().uri("/set").to_request(),
+        )
+        .await;
+        let cookie = session_cookie(&resp);
+        // One message ("Hey there!") arrives in the session, then the handler replaces it.
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/replace")
+                .cookie(cookie)
+                .to_request(),
+        )
+        .await;
+        let cookie = session_cookie(&resp);
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/show")
+                .cookie(cookie)
+                .to_request(),
+        )
+        .await;
+        let body_bytes = actix_web::test::read_body(resp).await;
+        assert_eq!(
+            std::str::from_utf8(&body_bytes).unwrap(),
+            "From elsewhere - info\nFrom handler - info\n"
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_unread_incoming_messages_are_not_merged_back_in() {
+        let session_middleware =
+            SessionMiddleware::builder(CookieSessionStore::default(), Key::generate())
+                .cookie_name("_session".to_string())
+                .build();
+        let store = SessionMessageStore::default().merge(true);
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(FlashMessagesFramework::builder(store).build())
+                .wrap(session_middleware)
+                .service(resource("/set").route(web::get().to(set)))
+                .service(resource("/flash").route(web::get().to(flash_without_reading)))
+                .service(resource("/show").route(web::get().to(show))),
+        )
+        .await;
+        let session_cookie = |resp: &actix_web::dev::ServiceResponse| {
+            resp.response()
+                .cookies()
+                .find(|c| c.name() == "_session")
+                .unwrap()
+                .into_owned()
+        };
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get().uri("/set").to_request(),
+        )
+        .await;
+        let cookie = session_cookie(&resp);
+        // "Hey there!" is never read: it is cleared, as it would be without `merge`.
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/flash")
+                .cookie(cookie)
+                .to_request(),
+        )
+        .await;
+        let cookie = session_cookie(&resp);
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/show")
+                .cookie(cookie)
+                .to_request(),
+        )
+        .await;
+        let body_bytes = actix_web::test::read_body(resp).await;
+        assert_eq!(std::str::from_utf8(&body_bytes).unwrap(), "Fresh - info\n");
+    }
+
+    async fn flash_and_show(store: SessionMessageStore) -> Vec<String> {
+        let session_middleware =
+            SessionMiddleware::builder(CookieSessionStore::default(), Key::generate())
+                .cookie_name("_session".to_string())
+                .build();
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(FlashMessagesFramework::builder(store).build())
+                .wrap(session_middleware)
+                .service(resource("/flash").route(web::get().to(flash_twice)))
+                .service(resource("/show").route(web::get().to(show))),
+        )
+        .await;
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/flash")
+                .to_request(),
+        )
+        .await;
+        let mut session_cookie = resp
+            .response()
+            .cookies()
+            .find(|c| c.name() == "_session")
+            .unwrap()
+            .into_owned();
+        let mut bodies = vec![];
+        for _ in 0..2 {
+            let resp = actix_web::test::call_service(
+                &app,
+                actix_web::test::TestRequest::get()
+                    .uri("/show")
+                    .cookie(session_cookie.clone())
+                    .to_request(),
+            )
+            .await;
+            if let Some(cookie) = resp.response().cookies().find(|c| c.name() == "_session") {
+                session_cookie = cookie.into_owned();
+            }
+            let body_bytes = actix_web::test::read_body(resp).await;
+            bodies.push(std::str::from_utf8(&body_bytes).unwrap().to_owned());
+        }
+        bodies
+    }
+
+    #[actix_rt::test]
+    async fn test_store_overwrites_by_default() {
+        let bodies = flash_and_show(SessionMessageStore::default()).await;
+        assert_eq!(bodies, vec!["From handler - info\n", ""]);
+    }
+
+    #[actix_rt::test]
+    async fn test_store_appends_to_messages_written_during_the_request_when_merging() {
+        let bodies = flash_and_show(SessionMessageStore::default().merge(true)).await;
+        // Incoming messages are still shown once - they are not merged back in.
+        assert_eq!(
+            bodies,
+            vec!["From elsewhere - info\nFrom handler - info\n", ""]
+        );
+    }
+}
+
+#[cfg(feature = "cookies")]
+mod error_responses {
+    use super::*;
+    use actix_web::http::StatusCode;
+    use actix_web_flash_messages::storage::CookieMessageStore;
+
+    #[derive(Debug)]
+    struct OutOfStock;
+
+    impl std::fmt::Display for OutOfStock {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "The item is out of stock")
+        }
+    }
+
+    impl actix_web::ResponseError for OutOfStock {
+        fn status_code(&self) -> StatusCode {
+            StatusCode::CONFLICT
+        }
+    }
+
+    async fn fail() -> Result<HttpResponse, OutOfStock> {
+        FlashMessage::error("Sorry, we ran out of it!").send();
+        Err(OutOfStock)
+    }
+
+    #[actix_rt::test]
+    async fn test_messages_are_stored_on_responses_to_handler_errors() {
+        let cookie_store = CookieMessageStore::builder(Key::generate()).build();
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(FlashMessagesFramework::builder(cookie_store).build())
+                .service(resource("/fail").route(web::get().to(fail)))
+                .service(resource("/show").route(web::get().to(show))),
+        )
+        .await;
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/fail")
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+        let flash_cookie = resp
+            .response()
+            .cookies()
+            .find(|c| c.name() == "_flash")
+            .unwrap()
+            .into_owned();
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/show")
+                .cookie(flash_cookie)
+                .to_request(),
+        )
+        .await;
+        let body_bytes = actix_web::test::read_body(resp).await;
+        assert_eq!(
+            std::str::from_utf8(&body_bytes).unwrap(),
+            "Sorry, we ran out of it! - error\n"
+        );
+    }
+}
+
+#[cfg(feature = "cookies")]
+mod decode_value {
+    use super::*;
+    use actix_web_flash_messages::storage::{CookieMessageStore, InMemoryReplayGuard};
+    use actix_web_flash_messages::Level;
+
+    /// Two messages - `Hey there!` (info) and `Oops` (error) - signed with `[7; 64]`.
+    const KNOWN_VALUE: &str = "GeRaWdvaAe1aqfnmLBdydoOWFqFCdH2PD+yBwguGkvE%3D%7B%22v%22%3A1,%22messages%22%3A%5B%7B%22content%22%3A%22Hey%20there!%22,%22level%22%3A%22info%22%7D,%7B%22content%22%3A%22Oops%22,%22level%22%3A%22error%22%7D%5D%7D";
+
+    #[test]
+    fn test_a_known_value_is_decoded_with_its_key() {
+        let store = CookieMessageStore::builder(Key::from(&[7u8; 64])).build();
+        let messages = store.decode_value(KNOWN_VALUE).unwrap();
+        let messages: Vec<_> = messages.iter().map(|m| (m.content(), m.level())).collect();
+        assert_eq!(
+            messages,
+            vec![("Hey there!", Level::Info), ("Oops", Level::Error)]
+        );
+    }
+
+    #[test]
+    fn test_a_known_value_is_rejected_with_another_key() {
+        let store = CookieMessageStore::builder(Key::from(&[8u8; 64])).build();
+        let error = store.decode_value(KNOWN_VALUE).err().unwrap();
+        assert!(error.is_integrity_failure());
+    }
+
+    #[test]
+    fn test_values_produced_by_the_store_roundtrip() {
+        let store = CookieMessageStore::builder(Key::generate())
+            .key_id("v2".into())
+            .build();
+        let cookie = super::key_rotation::flash_cookie(&store);
+        let raw = cookie.encoded().stripped().to_string();
+        let raw = raw.strip_prefix("_flash=").unwrap();
+        let messages = store.decode_value(raw).unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].content(), "Hey there!");
+    }
+
+    #[test]
+    fn test_decoding_a_value_does_not_use_up_its_nonce() {
+        let store = CookieMessageStore::builder(Key::generate())
+            .replay_guard(InMemoryReplayGuard::default())
+            .build();
+        let cookie = super::key_rotation::flash_cookie(&store);
+        let raw = cookie.encoded().stripped().to_string();
+        let raw = raw.strip_prefix("_flash=").unwrap().to_owned();
+
+        // Decoding the value repeatedly does not count as using it...
+        for _ in 0..2 {
+            let messages = store.decode_value(&raw).unwrap();
+            assert_eq!(messages.len(), 1);
+        }
+        // ...the client can still send it, once.
+        let messages = super::key_rotation::load(&store, cookie);
+        assert_eq!(messages.len(), 1);
+        // Values that have already been used can still be inspected.
+        let messages = store.decode_value(&raw).unwrap();
+        assert_eq!(messages[0].content(), "Hey there!");
+    }
+
+    #[test]
+    fn test_the_signing_key_cannot_come_from_app_data() {
+        let store = CookieMessageStore::builder_from_app_data().build();
+        let error = store.decode_value(KNOWN_VALUE).err().unwrap();
+        assert!(error.is_generic());
+    }
+}
+
+#[cfg(feature = "cookies")]
+mod resend {
+    use super::*;
+    use actix_web_flash_messages::storage::CookieMessageStore;
+
+    async fn submit() -> impl Responder {
+        FlashMessage::error("Name is required").send();
+        HttpResponse::SeeOther()
+            .insert_header((actix_web::http::header::LOCATION, "/form"))
+            .finish()
+    }
+
+    /// The user must log in before filling the form: the form errors must survive another redirect.
+    async fn form(messages: IncomingFlashMessages) -> impl Responder {
+        FlashMessage::info("Please log in").send();
+        messages.resend();
+        HttpResponse::SeeOther()
+            .insert_header((actix_web::http::header::LOCATION, "/show"))
+            .finish()
+    }
+
+    #[actix_rt::test]
+    async fn test_resent_messages_survive_another_redirect_exactly_once() {
+        let cookie_store = CookieMessageStore::builder(Key::generate()).build();
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(FlashMessagesFramework::builder(cookie_store).build())
+                .service(resource("/submit").route(web::get().to(submit)))
+                .service(resource("/form").route(web::get().to(form)))
+                .service(resource("/show").route(web::get().to(show))),
+        )
+        .await;
+
+        let mut flash_cookie = None;
+        let mut bodies = vec![];
+        for uri in ["/submit", "/form", "/show", "/show"] {
+            let mut request = actix_web::test::TestRequest::get().uri(uri);
+            if let Some(cookie) = flash_cookie.take() {
+                request = request.cookie(cookie);
+            }
+            let resp = actix_web::test::call_service(&app, request.to_request()).await;
+            flash_cookie = resp
+                .response()
+                .cookies()
+                .find(|c| c.name() == "_flash" && !c.value().is_empty())
+                .map(|c| c.into_owned());
+            let body_bytes = actix_web::test::read_body(resp).await;
+            bodies.push(std::str::from_utf8(&body_bytes).unwrap().to_owned());
+        }
+        assert_eq!(
+            bodies,
+            vec![
+                "",
+                "",
+                "Please log in - info\nName is required - error\n",
+                ""
+            ]
+        );
+    }
+}
+
+#[cfg(all(feature = "cookies", feature = "sessions"))]
+mod config {
+    use super::*;
+    use actix_web_flash_messages::{ConfigError, CookieConfig, FlashConfig, Level, SessionConfig};
+
+    #[test]
+    fn test_config_roundtrips() {
+        let config = FlashConfig {
+            minimum_level: Level::Debug,
+            cookie: Some(CookieConfig {
+                cookie_name: Some("_notices".into()),
+                bytes_size_limit: Some(2048),
+                ..Default::default()
+            }),
+            session: None,
+        };
+        let serialized = serde_json::to_string(&config).unwrap();
+        let deserialized: FlashConfig = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(deserialized, config);
+    }
+
+    #[test]
+    fn test_unset_fields_fall_back_to_defaults() {
+        let config: FlashConfig = serde_json::from_str(r#"{ "session": {} }"#).unwrap();
+        assert_eq!(config.minimum_level, Level::Info);
+        assert_eq!(config.cookie, None);
+        assert_eq!(config.session, Some(SessionConfig::default()));
+    }
+
+    #[test]
+    fn test_unknown_fields_are_rejected() {
+        assert!(serde_json::from_str::<FlashConfig>(r#"{ "cookies": {} }"#).is_err());
+    }
+
+    #[test]
+    fn test_exactly_one_store_must_be_configured() {
+        let none: FlashConfig = serde_json::from_str("{}").unwrap();
+        assert!(matches!(
+            FlashMessagesFramework::from_config(none, Key::generate()),
+            Err(ConfigError::MissingStore)
+        ));
+        let both: FlashConfig = serde_json::from_str(r#"{ "cookie": {}, "session": {} }"#).unwrap();
+        assert!(matches!(
+            FlashMessagesFramework::from_config(both, Key::generate()),
+            Err(ConfigError::MultipleStores)
+        ));
+    }
+
+    #[test]
+    fn test_invalid_cookie_configurations_are_reported_as_errors() {
+        for config in [
+            r#"{ "cookie": { "cookie_name": "bad name" } }"#,
+            r#"{ "cookie": { "key_id": "v1.2" } }"#,
+            r#"{ "cookie": { "cookie_name": "__Host-flash", "domain": "example.com" } }"#,
+        ] {
+            let config: FlashConfig = serde_json::from_str(config).unwrap();
+            assert!(matches!(
+                FlashMessagesFramework::from_config(config, Key::generate()),
+                Err(ConfigError::InvalidCookieConfig(_))
+            ));
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_framework_built_from_config_delivers_messages() {
+        let config: FlashConfig = serde_json::from_str(
+            r#"{ "minimum_level": "debug", "cookie": { "cookie_name": "_notices" } }"#,
+        )
+        .unwrap();
+        let framework = FlashMessagesFramework::from_config(config, Key::generate()).unwrap();
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(framework)
+                .service(resource("/set").route(web::get().to(set)))
+                .service(resource("/show").route(web::get().to(show))),
+        )
+        .await;
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get().uri("/set").to_request(),
+        )
+        .await;
+        let flash_cookie = resp
+            .response()
+            .cookies()
+            .find(|c| c.name() == "_notices")
+            .unwrap()
+            .into_owned();
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/show")
+                .cookie(flash_cookie)
+                .to_request(),
+        )
+        .await;
+        let body_bytes = actix_web::test::read_body(resp).await;
+        assert_eq!(
+            std::str::from_utf8(&body_bytes).unwrap(),
+            "Hey there! - info\nHow is it going? - debug\n"
+        );
+    }
+}
+
+#[cfg(feature = "cookies")]
+mod on_load {
+    use super::*;
+    use actix_web_flash_messages::storage::CookieMessageStore;
+    use actix_web_flash_messages::FlashMessageCount;
+    use actix_web_flash_messages::Level;
+
+    async fn set_staging() -> impl Responder {
+        FlashMessage::info("[staging] Hey there!").send();
+        FlashMessage::debug("[staging] Cache miss").send();
+        FlashMessage::error("Oops").send();
+        HttpResponse::Ok()
+    }
+
+    #[actix_rt::test]
+    async fn test_incoming_messages_are_dropped_and_rewritten() {
+        let cookie_store = CookieMessageStore::builder(Key::generate()).build();
+        let framework = FlashMessagesFramework::builder(cookie_store)
+            .minimum_level(Level::Debug)
+            .on_load(|messages| {
+                messages
+                    .into_iter()
+                    .filter(|m| m.level() != Level::Debug)
+                    .map(|m| match m.content().strip_prefix("[staging] ") {
+                        Some(content) => {
+                            let content = content.to_owned();
+                            m.with_content(content)
+                        }
+                        None => m,
+                    })
+                    .collect()
+            })
+            .build();
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(framework)
+                .service(resource("/set").route(web::get().to(set_staging)))
+                .service(resource("/show").route(web::get().to(show))),
+        )
+        .await;
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get().uri("/set").to_request(),
+        )
+        .await;
+        let flash_cookie = resp
+            .response()
+            .cookies()
+            .find(|c| c.name() == "_flash")
+            .unwrap()
+            .into_owned();
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/show")
+                .cookie(flash_cookie)
+                .to_request(),
+        )
+        .await;
+        let body_bytes = actix_web::test::read_body(resp).await;
+        assert_eq!(
+            std::str::from_utf8(&body_bytes).unwrap(),
+            "Hey there! - info\nOops - error\n"
+        );
+    }
+
+    async fn set_nested_prefix() -> impl Responder {
+        FlashMessage::info("[staging] [staging] Hey there!").send();
+        HttpResponse::Ok()
+    }
+
+    async fn peek(FlashMessageCount(count): FlashMessageCount) -> impl Responder {
+        HttpResponse::Ok().body(count.to_string())
+    }
+
+    #[actix_rt::test]
+    async fn test_the_hook_is_not_applied_twice_to_carried_over_messages() {
+        let cookie_store = CookieMessageStore::builder(Key::generate()).build();
+        let framework = FlashMessagesFramework::builder(cookie_store)
+            .on_load(|messages| {
+                messages
+                    .into_iter()
+                    .map(|m| match m.content().strip_prefix("[staging] ") {
+                        Some(content) => {
+                            let content = content.to_owned();
+                            m.with_content(content)
+                        }
+                        None => m,
+                    })
+                    .collect()
+            })
+            .build();
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(framework)
+                .service(resource("/set").route(web::get().to(set_nested_prefix)))
+                .service(resource("/peek").route(web::get().to(peek)))
+                .service(resource("/show").route(web::get().to(show))),
+        )
+        .await;
+        let flash_cookie = |resp: &actix_web::dev::ServiceResponse| {
+            resp.response()
+                .cookies()
+                .find(|c| c.name() == "_flash")
+                .unwrap()
+                .into_owned()
+        };
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get().uri("/set").to_request(),
+        )
+        .await;
+        let cookie = flash_cookie(&resp);
+        // Peeking does not consume incoming messages: they are carried over.
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/peek")
+                .cookie(cookie)
+                .to_request(),
+        )
+        .await;
+        let cookie = flash_cookie(&resp);
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/show")
+                .cookie(cookie)
+                .to_request(),
+        )
+        .await;
+        let body_bytes = actix_web::test::read_body(resp).await;
+        assert_eq!(
+            std::str::from_utf8(&body_bytes).unwrap(),
+            "[staging] Hey there! - info\n"
+        );
+    }
+}
+
+#[cfg(all(feature = "html", feature = "cookies"))]
+mod body_injection {
+    use super::*;
+    use actix_web_flash_messages::storage::CookieMessageStore;
+    use actix_web_flash_messages::BodyInjection;
+
+    async fn set_unsafe() -> impl Responder {
+        FlashMessage::error("<script>alert(1)</script>").send();
+        HttpResponse::Ok()
+    }
+
+    async fn page() -> impl Responder {
+        HttpResponse::Ok()
+            .content_type("text/html; charset=utf-8")
+            .body("<html><body><h1>Welcome</h1></BODY></html>")
+    }
+
+    async fn plain() -> impl Responder {
+        HttpResponse::Ok().body("</body>")
+    }
+
+    async fn page_reading_messages(messages: IncomingFlashMessages) -> impl Responder {
+        HttpResponse::Ok()
+            .content_type("text/html")
+            .body(format!("<body>{}</body>", messages.iter().count()))
+    }
+
+    /// Send a message, then call `uri` with the resulting flash cookie.
+    /// Returns the body of the second response and whether it cleared the flash cookie.
+    async fn show_on(uri: &str) -> (String, bool) {
+        let cookie_store = CookieMessageStore::builder(Key::generate()).build();
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(BodyInjection::new())
+                .wrap(FlashMessagesFramework::builder(cookie_store).build())
+                .service(resource("/set").route(web::get().to(set_unsafe)))
+                .service(resource("/page").route(web::get().to(page)))
+                .service(resource("/plain").route(web::get().to(plain)))
+                .service(resource("/read").route(web::get().to(page_reading_messages))),
+        )
+        .await;
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get().uri("/set").to_request(),
+        )
+        .await;
+        let flash_cookie = resp
+            .response()
+            .cookies()
+            .find(|c| c.name() == "_flash")
+            .unwrap()
+            .into_owned();
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri(uri)
+                .cookie(flash_cookie)
+                .to_request(),
+        )
+        .await;
+        let cleared = resp
+            .response()
+            .cookies()
+            .any(|c| c.name() == "_flash" && c.value().is_empty());
+        let body_bytes = actix_web::test::read_body(resp).await;
+        (
+            std::str::from_utf8(&body_bytes).unwrap().to_owned(),
+            cleared,
+        )
+    }
+
+    #[actix_rt::test]
+    async fn test_messages_are_injected_before_the_closing_body_tag() {
+        let (body, cleared) = show_on("/page").await;
+        assert_eq!(
+            body,
+            "<html><body><h1>Welcome</h1>\
+            <div role=\"alert\" class=\"flash-error\">&lt;script&gt;alert(1)&lt;/script&gt;</div>\n\
+            </BODY></html>"
+        );
+        assert!(cleared);
+    }
+
+    #[actix_rt::test]
+    async fn test_non_html_responses_are_left_untouched() {
+        let (body, _) = show_on("/plain").await;
+        assert_eq!(body, "</body>");
+    }
+
+    #[actix_rt::test]
+    async fn test_messages_read_by_the_handler_are_not_injected() {
+        let (body, cleared) = show_on("/read").await;
+        assert_eq!(body, "<body>1</body>");
+        assert!(cleared);
+    }
+}
+
+mod severity_sorting {
+    use actix_web_flash_messages::IncomingFlashMessages;
+
+    #[test]
+    fn test_messages_are_sorted_by_descending_severity_and_stable_within_a_level() {
+        let messages: IncomingFlashMessages = serde_json::from_str(
+            r#"[
+                {"content": "first info", "level": "info"},
+                {"content": "first error", "level": "error"},
+                {"content": "debug", "level": "debug"},
+                {"content": "second info", "level": "info"},
+                {"content": "warning", "level": "warning"},
+                {"content": "second error", "level": "error"},
+                {"content": "success", "level": "success"}
+            ]"#,
+        )
+        .unwrap();
+        let sorted: Vec<_> = messages
+            .sorted_by_severity()
+            .into_iter()
+            .map(|m| m.content())
+            .collect();
+        assert_eq!(
+            sorted,
+            vec![
+                "first error",
+                "second error",
+                "warning",
+                "success",
+                "first info",
+                "second info",
+                "debug"
+            ]
+        );
+        // The original order is untouched.
+        assert_eq!(messages.iter().next().unwrap().content(), "first info");
+    }
+}
+
+#[cfg(feature = "cookies")]
+mod max_messages_per_request {
+    use super::*;
+    use actix_web_flash_messages::storage::CookieMessageStore;
+    use actix_web_flash_messages::FlashScope;
+
+    async fn flood(mut scope: FlashScope) -> impl Responder {
+        for i in 0..3 {
+            FlashMessage::info(format!("Sent #{}", i)).send();
+        }
+        scope.info("Scoped");
+        scope.commit();
+        HttpResponse::Ok()
+    }
+
+    #[actix_rt::test]
+    async fn test_extra_messages_are_dropped() {
+        let cookie_store = CookieMessageStore::builder(Key::generate()).build();
+        let framework = FlashMessagesFramework::builder(cookie_store)
+            .max_messages_per_request(2)
+            .build();
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(framework)
+                .service(resource("/flood").route(web::get().to(flood)))
+                .service(resource("/show").route(web::get().to(show))),
+        )
+        .await;
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/flood")
+                .to_request(),
+        )
+        .await;
+        let flash_cookie = resp
+            .response()
+            .cookies()
+            .find(|c| c.name() == "_flash")
+            .unwrap()
+            .into_owned();
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/show")
+                .cookie(flash_cookie)
+                .to_request(),
+        )
+        .await;
+        let body_bytes = actix_web::test::read_body(resp).await;
+        assert_eq!(
+            std::str::from_utf8(&body_bytes).unwrap(),
+            "Sent #0 - info\nSent #1 - info\n"
+        );
+    }
+}
+
+mod htmx {
+    use super::*;
+    use actix_web_flash_messages::storage::{HtmxMessageStore, HxTriggerTiming};
+
+    async fn set_with_trigger() -> impl Responder {
+        FlashMessage::success("Café saved").send();
+        HttpResponse::Ok()
+            .insert_header(("HX-Trigger", "refresh-list"))
+            .finish()
+    }
+
+    #[actix_rt::test]
+    async fn test_messages_are_sent_with_the_configured_timing() {
+        for (timing, header) in [
+            (HxTriggerTiming::Immediate, "HX-Trigger"),
+            (HxTriggerTiming::AfterSwap, "HX-Trigger-After-Swap"),
+            (HxTriggerTiming::AfterSettle, "HX-Trigger-After-Settle"),
+        ] {
+            let message_store = HtmxMessageStore::default().timing(timing);
+            let app = actix_web::test::init_service(
+                App::new()
+                    .wrap(FlashMessagesFramework::builder(message_store).build())
+                    .service(resource("/set").route(web::get().to(set))),
+            )
+            .await;
+
+            let resp = actix_web::test::call_service(
+                &app,
+                actix_web::test::TestRequest::get().uri("/set").to_request(),
+            )
+            .await;
+            assert_eq!(
+                resp.headers().get(header).unwrap().to_str().unwrap(),
+                r#"{"flash":{"messages":[{"content":"Hey there!","level":"info"}]}}"#,
+                "Unexpected `{}` header",
+                header
+            );
+            let n_htmx_headers = resp
+                .headers()
+                .keys()
+                .filter(|name| name.as_str().starts_with("hx-"))
+                .count();
+            assert_eq!(n_htmx_headers, 1);
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_events_set_by_the_handler_are_preserved() {
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(
+                    FlashMessagesFramework::builder(HtmxMessageStore::new("toast".into())).build(),
+                )
+                .service(resource("/set").route(web::get().to(set_with_trigger))),
+        )
+        .await;
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get().uri("/set").to_request(),
+        )
+        .await;
+        let trigger = resp.headers().get("HX-Trigger").unwrap().to_str().unwrap();
+        let events: serde_json::Value = serde_json::from_str(trigger).unwrap();
+        assert_eq!(
+            events,
+            serde_json::json!({
+                "refresh-list": null,
+                "toast": {"messages": [{"level": "success", "content": "Café saved"}]},
+            })
+        );
+    }
+}
+
+#[cfg(feature = "cookies")]
+mod source_path {
+    use super::*;
+    use actix_web_flash_messages::storage::CookieMessageStore;
+
+    async fn show_source_path(messages: IncomingFlashMessages) -> impl Responder {
+        let mut body = String::new();
+        for message in messages.iter() {
+            writeln!(
+                body,
+                "{} - {}",
+                message.content(),
+                message.source_path().unwrap_or("unknown")
+            )
+            .unwrap();
+        }
+        HttpResponse::Ok().body(body)
+    }
+
+    #[actix_rt::test]
+    async fn test_source_path_is_only_recorded_if_enabled() {
+        for (record_source_path, expected) in [
+            (true, "Hey there! - /accounts/set\n"),
+            (false, "Hey there! - unknown\n"),
+        ] {
+            let cookie_store = CookieMessageStore::builder(Key::generate()).build();
+            let framework = FlashMessagesFramework::builder(cookie_store)
+                .record_source_path(record_source_path)
+                .build();
+            let app = actix_web::test::init_service(
+                App::new()
+                    .wrap(framework)
+                    .service(resource("/accounts/set").route(web::get().to(set)))
+                    .service(resource("/show").route(web::get().to(show_source_path))),
+            )
+            .await;
+
+            let resp = actix_web::test::call_service(
+                &app,
+                actix_web::test::TestRequest::get()
+                    .uri("/accounts/set")
+                    .to_request(),
+            )
+            .await;
+            let flash_cookie = resp
+                .response()
+                .cookies()
+                .find(|c| c.name() == "_flash")
+                .unwrap()
+                .into_owned();
+            let resp = actix_web::test::call_service(
+                &app,
+                actix_web::test::TestRequest::get()
+                    .uri("/show")
+                    .cookie(flash_cookie)
+                    .to_request(),
+            )
+            .await;
+            let body_bytes = actix_web::test::read_body(resp).await;
+            assert_eq!(std::str::from_utf8(&body_bytes).unwrap(), expected);
+        }
+    }
+}
+
+mod async_store {
+    use super::*;
+    use actix_web::dev::ResponseHead;
+    use actix_web::http::header::{HeaderName, HeaderValue};
+    use actix_web::HttpRequest;
+    use actix_web_flash_messages::storage::{
+        FlashMessageStore, LoadError, StoreError, StoreFuture,
+    };
+    use std::sync::{Arc, Mutex};
+
+    /// A store that only supports asynchronous writes, as if it were backed by a remote database.
+    #[derive(Clone, Default)]
+    struct MockAsyncStore {
+        written: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl FlashMessageStore for MockAsyncStore {
+        fn load(&self, _request: &HttpRequest) -> Result<Vec<FlashMessage>, LoadError> {
+            Ok(vec![])
+        }
+
+        fn store(
+            &self,
+            _messages: &[FlashMessage],
+            _request: HttpRequest,
+            _response: &mut ResponseHead,
+        ) -> Result<(), StoreError> {
+            panic!("`MockAsyncStore` only supports asynchronous writes")
+        }
+
+        fn store_async<'a>(
+            &'a self,
+            messages: &'a [FlashMessage],
+            _request: HttpRequest,
+            response: &'a mut ResponseHead,
+        ) -> StoreFuture<'a> {
+            Box::pin(async move {
+                // Simulate a round-trip to the database.
+                actix_rt::time::sleep(std::time::Duration::from_millis(5)).await;
+                self.written
+                    .lock()
+                    .unwrap()
+                    .extend(messages.iter().map(|m| m.content().to_owned()));
+                response.headers_mut().insert(
+                    HeaderName::from_static("x-flash-written"),
+                    HeaderValue::from(messages.len()),
+                );
+                Ok(())
+            })
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_the_response_is_returned_once_the_async_write_completes() {
+        let message_store = MockAsyncStore::default();
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(FlashMessagesFramework::builder(message_store.clone()).build())
+                .service(resource("/set").route(web::get().to(set))),
+        )
+        .await;
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get().uri("/set").to_request(),
+        )
+        .await;
+        assert_eq!(resp.headers().get("x-flash-written").unwrap(), "1");
+        assert_eq!(
+            *message_store.written.lock().unwrap(),
+            vec!["Hey there!".to_string()]
+        );
+    }
+
+    #[cfg(all(feature = "cookies", feature = "tracing"))]
+    #[actix_rt::test]
+    async fn test_combinators_forward_async_writes() {
+        use actix_web_flash_messages::storage::{CookieMessageStore, LoggingStore, TeeStore};
+
+        let async_store = MockAsyncStore::default();
+        let cookie_store = CookieMessageStore::builder(Key::generate()).build();
+        let message_store = LoggingStore::new(TeeStore::new(vec![
+            Arc::new(cookie_store),
+            Arc::new(async_store.clone()),
+        ]));
+        let app = actix_web::test::init_service(
+            App::new()
+                .wrap(FlashMessagesFramework::builder(message_store).build())
+                .service(resource("/set").route(web::get().to(set))),
+        )
+        .await;
+
+        let resp = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get().uri("/set").to_request(),
+        )
+        .await;
+        assert!(resp.response().cookies().any(|c| c.name() == "_flash"));
+        assert_eq!(resp.headers().get("x-flash-written").unwrap(), "1");
+        assert_eq!(
+            *async_store.written.lock().unwrap(),
+            vec!["Hey there!".to_string()]
+        );
+    }
+}
+
+#[cfg(all(feature = "cookies", feature = "tracing"))]
+mod debug {
+    use super::*;
+    use actix_web_flash_messages::storage::{CookieMessageStore, FlashMessageStore, LoggingStore};
+    use actix_web_flash_messages::Level;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_debug_output_names_the_store_type() {
+        let signing_key = Key::from(&[42u8; 64]);
+        let cookie_store = CookieMessageStore::builder(signing_key.clone()).build();
+        let framework = FlashMessagesFramework::builder(LoggingStore::new(cookie_store))
+            .minimum_level(Level::Debug)
+            .build();
+        let debug = format!("{:?}", framework);
+        assert!(debug.starts_with("FlashMessagesFramework { minimum_level: debug, "));
+        assert!(debug.contains("::LoggingStore<"));
+        assert!(debug.contains("::CookieMessageStore>"));
+
+        // The concrete type is named even if the store is type-erased.
+        let store: Arc<dyn FlashMessageStore> =
+            Arc::new(CookieMessageStore::builder(signing_key).build());
+        assert!(store.type_name().ends_with("::CookieMessageStore"));
+    }
 }